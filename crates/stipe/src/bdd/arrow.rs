@@ -1,9 +1,9 @@
 use crate::{
-    bdd::{Bdd, TyAtom},
+    bdd::{Bdd, Constraints, Solver, Tally, TyAtom, Type},
     ty::TyConfig,
 };
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Arrow<'a, C, T>(pub &'a Bdd<'a, C, T>, pub &'a Bdd<'a, C, T>)
 where
     C: TyConfig,
@@ -14,3 +14,153 @@ where
     T: TyAtom,
 {
 }
+impl<'a, C> Bdd<'a, C, Arrow<'a, C, Type<'a, C>>>
+where
+    C: TyConfig,
+{
+    pub fn is_empty(&'a self, solver: &Solver<'_, 'a, C>) -> bool {
+        let arena = solver.arena;
+        self.all_clauses(&mut |pos, neg| {
+            let dom = pos.iter().fold(Bdd::bot(arena), |dom, Arrow(d, _)| {
+                Bdd::union(arena, dom, d)
+            });
+            neg.iter().any(|Arrow(d, c)| {
+                solver.is_subtype(d, dom)
+                    && Self::clause_is_empty(solver, d, Bdd::not(solver.arena, c), pos)
+            })
+        })
+    }
+
+    /// Whether the positive arrows in `pos` cannot map some value of `d` into the complement
+    /// `c` of a negated arrow's codomain. Each arrow either doesn't apply (its domain is removed
+    /// from `d`) or restricts the possible results (its codomain is intersected with `c`).
+    fn clause_is_empty(
+        solver: &Solver<'_, 'a, C>,
+        d: &'a Bdd<'a, C, Type<'a, C>>,
+        c: &'a Bdd<'a, C, Type<'a, C>>,
+        pos: &[&'a Arrow<'a, C, Type<'a, C>>],
+    ) -> bool {
+        match pos.split_first() {
+            None => solver.is_empty(d) || solver.is_empty(c),
+            Some((Arrow(pd, pc), rest)) => {
+                Self::clause_is_empty(solver, Bdd::diff(solver.arena, d, pd), c, rest)
+                    && Self::clause_is_empty(solver, d, Bdd::inter(solver.arena, c, pc), rest)
+            }
+        }
+    }
+
+    /// The constraints under which the arrows are empty, following `is_empty`.
+    pub(crate) fn tally(&'a self, tally: &Tally<'_, '_, 'a, C>) -> Constraints<'a, C> {
+        let arena = tally.solver().arena;
+        let mut constraints = Constraints::sat();
+        self.all_clauses(&mut |pos, neg| {
+            let dom = pos.iter().fold(Bdd::bot(arena), |dom, Arrow(d, _)| {
+                Bdd::union(arena, dom, d)
+            });
+            let clause = neg.iter().fold(Constraints::unsat(), |acc, Arrow(d, c)| {
+                acc.or(tally.norm(Bdd::diff(arena, d, dom)).and(
+                    arena,
+                    &Self::clause_tally(tally, d, Bdd::not(arena, c), pos),
+                ))
+            });
+            constraints =
+                std::mem::replace(&mut constraints, Constraints::unsat()).and(arena, &clause);
+            !constraints.is_unsat()
+        });
+        constraints
+    }
+
+    fn clause_tally(
+        tally: &Tally<'_, '_, 'a, C>,
+        d: &'a Bdd<'a, C, Type<'a, C>>,
+        c: &'a Bdd<'a, C, Type<'a, C>>,
+        pos: &[&'a Arrow<'a, C, Type<'a, C>>],
+    ) -> Constraints<'a, C> {
+        let arena = tally.solver().arena;
+        match pos.split_first() {
+            None => tally.norm(d).or(tally.norm(c)),
+            Some((Arrow(pd, pc), rest)) => {
+                Self::clause_tally(tally, Bdd::diff(arena, d, pd), c, rest).and(
+                    arena,
+                    &Self::clause_tally(tally, d, Bdd::inter(arena, c, pc), rest),
+                )
+            }
+        }
+    }
+}
+
+impl<'a, C> Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    /// The domain of a function type: the arguments that every function of the type accepts.
+    /// Top-level variables are over-approximated (see `Bdd::upper_bound`).
+    pub fn domain(solver: &Solver<'_, 'a, C>, ty: &'a Self) -> &'a Self {
+        let arena = solver.arena;
+        let mut dom = Bdd::top(arena);
+        solver.upper_bound(ty).arrows.all_clauses(&mut |pos, neg| {
+            if !Bdd::clause(arena, pos, neg).is_empty(solver) {
+                let clause_dom = pos.iter().fold(Bdd::bot(arena), |acc, Arrow(d, _)| {
+                    Bdd::union(arena, acc, d)
+                });
+                dom = Bdd::inter(solver.arena, dom, clause_dom);
+            }
+            true
+        });
+        dom
+    }
+
+    /// The type of the result of applying a function of type `ty` to an argument of type `arg`,
+    /// which is expected to be in its domain. Top-level variables are over-approximated.
+    pub fn apply(solver: &Solver<'_, 'a, C>, ty: &'a Self, arg: &'a Self) -> &'a Self {
+        let arena = solver.arena;
+        let mut result = Bdd::bot(arena);
+        solver.upper_bound(ty).arrows.all_clauses(&mut |pos, neg| {
+            if Bdd::clause(arena, pos, neg).is_empty(solver) {
+                return true;
+            }
+            Self::apply_clause(
+                solver,
+                arg,
+                Bdd::bot(arena),
+                Bdd::top(arena),
+                false,
+                pos,
+                &mut result,
+            );
+            true
+        });
+        result
+    }
+
+    /// Adds to `result` the results of applying the arrows in `pos` to `arg`, given the union
+    /// `dom` of the domains of the arrows that don't apply so far and the intersection `cod` of
+    /// the codomains of those that do. Each arrow either doesn't apply or restricts the results;
+    /// a choice yields `cod` if the argument escapes `dom` and some arrow applies. Choices are
+    /// cut short once `dom` covers the argument or `result` already has `cod`, since the
+    /// remaining arrows can only grow `dom` and shrink `cod`.
+    fn apply_clause(
+        solver: &Solver<'_, 'a, C>,
+        arg: &'a Self,
+        dom: &'a Self,
+        cod: &'a Self,
+        applied: bool,
+        pos: &[&'a Arrow<'a, C, Type<'a, C>>],
+        result: &mut &'a Self,
+    ) {
+        let arena = solver.arena;
+        if solver.is_subtype(arg, dom) || solver.is_subtype(cod, result) {
+            return;
+        }
+        match pos.split_first() {
+            None if applied => *result = Bdd::union(arena, result, cod),
+            None => {}
+            Some((Arrow(d, c), rest)) => {
+                let dom_d = Bdd::union(arena, dom, d);
+                Self::apply_clause(solver, arg, dom_d, cod, applied, rest, result);
+                let cod_c = Bdd::inter(arena, cod, c);
+                Self::apply_clause(solver, arg, dom, cod_c, true, rest, result);
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{
+    bdd::{Bdd, Intervals, TyAtom, clause_contradicts},
+    ty::TyConfig,
+};
+
+/// An atom of the basic component of a type: either all the values of a basic type, or a single
+/// literal value. Literals and basics share a BDD so that cofinite sets such as `Int \ {0}` can
+/// be represented.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Basic<C>
+where
+    C: TyConfig,
+{
+    All(C::Basic),
+    Literal(C::Literal),
+}
+impl<C> Basic<C>
+where
+    C: TyConfig,
+{
+    /// The basic type containing the values of the atom.
+    pub fn basic(&self) -> std::borrow::Cow<'_, C::Basic> {
+        match self {
+            Self::All(basic) => std::borrow::Cow::Borrowed(basic),
+            Self::Literal(literal) => std::borrow::Cow::Owned(C::literal_basic(literal)),
+        }
+    }
+}
+impl<C> TyAtom for Basic<C> where C: TyConfig {}
+
+impl<'a, C> Bdd<'a, C, Basic<C>>
+where
+    C: TyConfig,
+{
+    /// Separates the integers from the other basic values, with `TyConfig::INTERVALS`: the
+    /// integer basic and literals stand for intervals, and the other atoms contain no integers.
+    /// Returns the basics without integers and the integers as intervals.
+    pub fn split_ints(&'a self, arena: &'a bumpalo::Bump) -> (&'a Self, &'a Intervals<'a, C>) {
+        if !C::INTERVALS {
+            return (self, Intervals::empty(arena));
+        }
+        let ints = self.ints(arena);
+        let mut has_ints = false;
+        self.for_each_atom(&mut |basic| has_ints |= !Self::atom_ints(arena, basic).is_empty());
+        if !has_ints {
+            return (self, ints);
+        }
+        let basics = Bdd::map_polar(arena, self, true, &|basic, _| {
+            if Self::atom_ints(arena, basic).is_empty() {
+                Bdd::atom(arena, basic)
+            } else {
+                Bdd::bot(arena)
+            }
+        });
+        (basics, ints)
+    }
+
+    /// The integers of the BDD, when every atom stands for its integers.
+    fn ints(&'a self, arena: &'a bumpalo::Bump) -> &'a Intervals<'a, C> {
+        match self {
+            Self::Top => Intervals::top(arena),
+            Self::Bot => Intervals::empty(arena),
+            Self::Atom {
+                atom, pos, lu, neg, ..
+            } => {
+                let ints = Self::atom_ints(arena, atom);
+                let pos = Intervals::inter(arena, ints, pos.ints(arena));
+                let neg = Intervals::diff(arena, neg.ints(arena), ints);
+                Intervals::union(arena, Intervals::union(arena, pos, lu.ints(arena)), neg)
+            }
+        }
+    }
+
+    fn atom_ints(arena: &'a bumpalo::Bump, basic: &Basic<C>) -> &'a Intervals<'a, C> {
+        match basic {
+            Basic::All(basic) if C::int_basic(basic) => Intervals::top(arena),
+            Basic::Literal(literal) => match C::literal_int(literal) {
+                Some(n) => Intervals::range(arena, n, n),
+                None => Intervals::empty(arena),
+            },
+            Basic::All(_) => Intervals::empty(arena),
+        }
+    }
+}
+
+impl<'a, C> Bdd<'a, C, Basic<C>>
+where
+    C: TyConfig,
+{
+    /// Distinct basics are disjoint, distinct literals are disjoint, and a literal is contained
+    /// only in its own basic.
+    pub fn is_empty(&'a self) -> bool {
+        self.all_clauses(&mut |pos, neg| {
+            if clause_contradicts(pos, neg) {
+                return true;
+            }
+            let Some((first, rest)) = pos.split_first() else {
+                return false;
+            };
+            let basic = first.basic();
+            if rest.iter().any(|atom| atom.basic() != basic) {
+                return true;
+            }
+            let mut literals = pos.iter().filter_map(|atom| match atom {
+                Basic::All(_) => None,
+                Basic::Literal(literal) => Some(literal),
+            });
+            let literal = literals.next();
+            if literals.next().is_some() {
+                return true;
+            }
+            if neg
+                .iter()
+                .any(|atom| matches!(atom, Basic::All(b) if *b == *basic))
+            {
+                return true;
+            }
+            match literal {
+                Some(_) => false,
+                None => C::basic_literals(&basic).is_some_and(|all| {
+                    all.iter().all(|literal| {
+                        neg.iter()
+                            .any(|atom| matches!(atom, Basic::Literal(l) if l == literal))
+                    })
+                }),
+            }
+        })
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{
+    bdd::{Bdd, TyAtom},
+    ty::TyConfig,
+};
+
+/// An iterator over the clauses of a BDD, the positive and negative atoms along each path to
+/// `Top`, whose union is the BDD. The paths are walked depth-first, so only the current one is
+/// kept in memory.
+pub struct Dnf<'a, C, T>
+where
+    C: TyConfig,
+    T: TyAtom,
+{
+    stack: Vec<Step<'a, C, T>>,
+    pos: Vec<&'a T>,
+    neg: Vec<&'a T>,
+}
+
+enum Step<'a, C, T>
+where
+    C: TyConfig,
+    T: TyAtom,
+{
+    Visit(&'a Bdd<'a, C, T>),
+    /// Adds an atom to the current path, with its polarity.
+    Push(&'a T, bool),
+    /// Removes the last atom of the given polarity from the current path.
+    Pop(bool),
+}
+
+impl<'a, C, T> Iterator for Dnf<'a, C, T>
+where
+    C: TyConfig,
+    T: TyAtom,
+{
+    type Item = (Vec<&'a T>, Vec<&'a T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(step) = self.stack.pop() {
+            match step {
+                Step::Visit(Bdd::Top) => return Some((self.pos.clone(), self.neg.clone())),
+                Step::Visit(Bdd::Bot) => {}
+                Step::Visit(Bdd::Atom {
+                    atom, pos, lu, neg, ..
+                }) => {
+                    // Pushed in reverse: the positive branch, then the lazy union, then the
+                    // negative branch.
+                    if **neg != Bdd::Bot {
+                        self.stack.extend([
+                            Step::Pop(false),
+                            Step::Visit(neg),
+                            Step::Push(atom, false),
+                        ]);
+                    }
+                    self.stack.push(Step::Visit(lu));
+                    if **pos != Bdd::Bot {
+                        self.stack.extend([
+                            Step::Pop(true),
+                            Step::Visit(pos),
+                            Step::Push(atom, true),
+                        ]);
+                    }
+                }
+                Step::Push(atom, true) => self.pos.push(atom),
+                Step::Push(atom, false) => self.neg.push(atom),
+                Step::Pop(true) => {
+                    self.pos.pop();
+                }
+                Step::Pop(false) => {
+                    self.neg.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<'a, C, T> Bdd<'a, C, T>
+where
+    C: TyConfig,
+    T: TyAtom,
+{
+    /// The clauses of the BDD, as pairs of positive and negative atoms.
+    pub fn dnf(&'a self) -> Dnf<'a, C, T> {
+        Dnf {
+            stack: vec![Step::Visit(self)],
+            pos: Vec::new(),
+            neg: Vec::new(),
+        }
+    }
+}
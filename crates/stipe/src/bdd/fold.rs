@@ -0,0 +1,433 @@
+//! Generic traversals of types, across all the components of `Type` and into the components of
+//! nested constructors. A type is a DAG whose nodes are often shared (the operations on BDDs reuse
+//! their operands), so the traversals remember the nodes they've already been through: each one
+//! is visited, or folded, only once.
+
+use std::{
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+    ptr,
+};
+
+use crate::{
+    bdd::{Arrow, Basic, Bdd, Intervals, Product, Record, Refr, TyAtom, Type, Var},
+    ty::TyConfig,
+};
+
+/// A read-only traversal of a type, with a hook for each kind of atom. The hooks of the
+/// constructors return whether to walk their components. Each distinct atom is visited once,
+/// however many times it's shared.
+pub trait TypeVisitor<'a, C>
+where
+    C: TyConfig,
+{
+    fn visit_var(&mut self, _var: &'a Var<C>) {}
+
+    fn visit_basic(&mut self, _basic: &'a Basic<C>) {}
+
+    fn visit_ints(&mut self, _ints: &'a Intervals<'a, C>) {}
+
+    fn visit_product(&mut self, _product: &'a Product<'a, C, Type<'a, C>>) -> bool {
+        true
+    }
+
+    fn visit_arrow(&mut self, _arrow: &'a Arrow<'a, C, Type<'a, C>>) -> bool {
+        true
+    }
+
+    fn visit_record(&mut self, _record: &'a Record<'a, C, Type<'a, C>>) -> bool {
+        true
+    }
+
+    fn visit_refr(&mut self, _refr: &'a Refr<'a, C, Type<'a, C>>) -> bool {
+        true
+    }
+}
+
+/// A rebuilding traversal of a type, replacing each atom with a BDD of the same kind (variables
+/// may be replaced with any type). Constructors are given with their components already folded.
+/// By default, every atom is kept.
+pub trait BddFold<'a, C>
+where
+    C: TyConfig,
+{
+    fn fold_var(
+        &mut self,
+        arena: &'a bumpalo::Bump,
+        var: &'a Var<C>,
+    ) -> &'a Bdd<'a, C, Type<'a, C>> {
+        Bdd::atom(
+            arena,
+            arena.alloc(Type::from_vars(arena, Bdd::atom(arena, var))),
+        )
+    }
+
+    fn fold_basic(
+        &mut self,
+        arena: &'a bumpalo::Bump,
+        basic: &'a Basic<C>,
+    ) -> &'a Bdd<'a, C, Basic<C>> {
+        Bdd::atom(arena, basic)
+    }
+
+    fn fold_ints(
+        &mut self,
+        _arena: &'a bumpalo::Bump,
+        ints: &'a Intervals<'a, C>,
+    ) -> &'a Intervals<'a, C> {
+        ints
+    }
+
+    fn fold_product(
+        &mut self,
+        arena: &'a bumpalo::Bump,
+        product: &'a Product<'a, C, Type<'a, C>>,
+    ) -> &'a Bdd<'a, C, Product<'a, C, Type<'a, C>>> {
+        Bdd::atom(arena, product)
+    }
+
+    fn fold_arrow(
+        &mut self,
+        arena: &'a bumpalo::Bump,
+        arrow: &'a Arrow<'a, C, Type<'a, C>>,
+    ) -> &'a Bdd<'a, C, Arrow<'a, C, Type<'a, C>>> {
+        Bdd::atom(arena, arrow)
+    }
+
+    fn fold_record(
+        &mut self,
+        arena: &'a bumpalo::Bump,
+        record: &'a Record<'a, C, Type<'a, C>>,
+    ) -> &'a Bdd<'a, C, Record<'a, C, Type<'a, C>>> {
+        Bdd::atom(arena, record)
+    }
+
+    fn fold_refr(
+        &mut self,
+        arena: &'a bumpalo::Bump,
+        refr: &'a Refr<'a, C, Type<'a, C>>,
+    ) -> &'a Bdd<'a, C, Refr<'a, C, Type<'a, C>>> {
+        Bdd::atom(arena, refr)
+    }
+}
+
+impl<'a, C> Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    pub fn visit<V>(&'a self, visitor: &mut V)
+    where
+        V: TypeVisitor<'a, C>,
+    {
+        Visit {
+            visitor,
+            seen: HashSet::new(),
+        }
+        .walk(self);
+    }
+
+    pub fn fold<F>(arena: &'a bumpalo::Bump, bdd: &'a Self, folder: &mut F) -> &'a Self
+    where
+        F: BddFold<'a, C>,
+    {
+        Fold {
+            folder,
+            arena,
+            types: HashMap::new(),
+            atoms: HashMap::new(),
+            vars: HashMap::new(),
+            basics: HashMap::new(),
+            products: HashMap::new(),
+            arrows: HashMap::new(),
+            records: HashMap::new(),
+            refrs: HashMap::new(),
+        }
+        .fold(bdd)
+    }
+}
+
+/// Collects the atoms of the nodes of `bdd` that aren't in `seen` yet, adding them to it.
+fn new_atoms<'a, C, T>(
+    bdd: &'a Bdd<'a, C, T>,
+    seen: &mut HashSet<*const ()>,
+    atoms: &mut Vec<&'a T>,
+) where
+    C: TyConfig,
+    T: TyAtom,
+{
+    if !seen.insert(ptr::from_ref(bdd).cast()) {
+        return;
+    }
+    if let Bdd::Atom {
+        atom, pos, lu, neg, ..
+    } = bdd
+    {
+        if seen.insert(ptr::from_ref(*atom).cast()) {
+            atoms.push(atom);
+        }
+        new_atoms(pos, seen, atoms);
+        new_atoms(lu, seen, atoms);
+        new_atoms(neg, seen, atoms);
+    }
+}
+
+struct Visit<'v, V> {
+    visitor: &'v mut V,
+    /// The BDD nodes and atoms already visited.
+    seen: HashSet<*const ()>,
+}
+impl<'v, V> Visit<'v, V> {
+    fn atoms<'a, C, T>(&mut self, bdd: &'a Bdd<'a, C, T>) -> Vec<&'a T>
+    where
+        C: TyConfig,
+        T: TyAtom,
+    {
+        let mut atoms = Vec::new();
+        new_atoms(bdd, &mut self.seen, &mut atoms);
+        atoms
+    }
+
+    fn walk<'a, C>(&mut self, bdd: &'a Bdd<'a, C, Type<'a, C>>)
+    where
+        C: TyConfig,
+        V: TypeVisitor<'a, C>,
+    {
+        for ty in self.atoms(bdd) {
+            for var in self.atoms(ty.vars) {
+                self.visitor.visit_var(var);
+            }
+            for basic in self.atoms(ty.basics) {
+                self.visitor.visit_basic(basic);
+            }
+            if self.seen.insert(ptr::from_ref(ty.ints).cast()) {
+                self.visitor.visit_ints(ty.ints);
+            }
+            for product @ Product(l, r) in self.atoms(ty.products) {
+                if self.visitor.visit_product(product) {
+                    self.walk(l);
+                    self.walk(r);
+                }
+            }
+            for arrow @ Arrow(d, c) in self.atoms(ty.arrows) {
+                if self.visitor.visit_arrow(arrow) {
+                    self.walk(d);
+                    self.walk(c);
+                }
+            }
+            for record in self.atoms(ty.records) {
+                if self.visitor.visit_record(record) {
+                    for (_, ty) in record.map.iter() {
+                        self.walk(ty);
+                    }
+                }
+            }
+            for refr in self.atoms(ty.refrs) {
+                if self.visitor.visit_refr(refr) {
+                    for arg in refr.args.iter() {
+                        self.walk(arg);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The images of the atoms of some kind, by address.
+type Images<'a, C, T, T2> = HashMap<*const T, &'a Bdd<'a, C, T2>>;
+
+/// The images of atoms folded into BDDs of the same kind.
+type Folded<'a, C, T> = Images<'a, C, T, T>;
+
+struct Fold<'f, 'a, C, F>
+where
+    C: TyConfig,
+{
+    folder: &'f mut F,
+    arena: &'a bumpalo::Bump,
+    /// The folded types, by address.
+    types: Images<'a, C, Bdd<'a, C, Type<'a, C>>, Type<'a, C>>,
+    atoms: Folded<'a, C, Type<'a, C>>,
+    vars: Images<'a, C, Var<C>, Type<'a, C>>,
+    basics: Folded<'a, C, Basic<C>>,
+    products: Folded<'a, C, Product<'a, C, Type<'a, C>>>,
+    arrows: Folded<'a, C, Arrow<'a, C, Type<'a, C>>>,
+    records: Folded<'a, C, Record<'a, C, Type<'a, C>>>,
+    refrs: Folded<'a, C, Refr<'a, C, Type<'a, C>>>,
+}
+impl<'f, 'a, C, F> Fold<'f, 'a, C, F>
+where
+    C: TyConfig,
+    F: BddFold<'a, C>,
+{
+    fn fold(&mut self, bdd: &'a Bdd<'a, C, Type<'a, C>>) -> &'a Bdd<'a, C, Type<'a, C>> {
+        if let Some(folded) = self.types.get(&ptr::from_ref(bdd)) {
+            return folded;
+        }
+        let folded = self.fold_bdd(bdd, |f| &mut f.atoms, Self::fold_type);
+        self.types.insert(bdd, folded);
+        folded
+    }
+
+    /// Rebuilds `bdd` from the images of its atoms, computed by `image` unless they're already
+    /// in the map `images` selects.
+    fn fold_bdd<T, T2>(
+        &mut self,
+        bdd: &'a Bdd<'a, C, T>,
+        images: fn(&mut Self) -> &mut Images<'a, C, T, T2>,
+        image: fn(&mut Self, &'a T) -> &'a Bdd<'a, C, T2>,
+    ) -> &'a Bdd<'a, C, T2>
+    where
+        T: TyAtom,
+        T2: TyAtom,
+    {
+        let mut atoms = Vec::new();
+        new_atoms(bdd, &mut HashSet::new(), &mut atoms);
+        for atom in atoms {
+            if !images(self).contains_key(&ptr::from_ref(atom)) {
+                let folded = image(self, atom);
+                images(self).insert(atom, folded);
+            }
+        }
+        rebuild(self.arena, bdd, images(self), &mut HashMap::new())
+    }
+
+    fn fold_type(&mut self, ty: &'a Type<'a, C>) -> &'a Bdd<'a, C, Type<'a, C>> {
+        let arena = self.arena;
+        let vars = self.fold_bdd(
+            ty.vars,
+            |f| &mut f.vars,
+            |f, var| f.folder.fold_var(f.arena, var),
+        );
+        let basics = self.fold_bdd(
+            ty.basics,
+            |f| &mut f.basics,
+            |f, basic| f.folder.fold_basic(f.arena, basic),
+        );
+        // The basics may have been folded into integers.
+        let (basics, basic_ints) = basics.split_ints(arena);
+        let ints = Intervals::union(arena, self.folder.fold_ints(arena, ty.ints), basic_ints);
+        let products = self.fold_bdd(
+            ty.products,
+            |f| &mut f.products,
+            |f, product| {
+                let Product(l, r) = product;
+                let (fl, fr) = (f.fold(l), f.fold(r));
+                let product = if ptr::eq(*l, fl) && ptr::eq(*r, fr) {
+                    product
+                } else {
+                    f.arena.alloc(Product(fl, fr))
+                };
+                f.folder.fold_product(f.arena, product)
+            },
+        );
+        let arrows = self.fold_bdd(
+            ty.arrows,
+            |f| &mut f.arrows,
+            |f, arrow| {
+                let Arrow(d, c) = arrow;
+                let (fd, fc) = (f.fold(d), f.fold(c));
+                let arrow = if ptr::eq(*d, fd) && ptr::eq(*c, fc) {
+                    arrow
+                } else {
+                    f.arena.alloc(Arrow(fd, fc))
+                };
+                f.folder.fold_arrow(f.arena, arrow)
+            },
+        );
+        let records = self.fold_bdd(
+            ty.records,
+            |f| &mut f.records,
+            |f, record| {
+                let map: Vec<_> = record
+                    .map
+                    .iter()
+                    .map(|(prop, ty)| (prop, *ty, f.fold(ty)))
+                    .collect();
+                let record = if map.iter().all(|(_, ty, folded)| ptr::eq(*ty, *folded)) {
+                    record
+                } else {
+                    f.arena.alloc(Record {
+                        map: bumpalo::collections::Vec::from_iter_in(
+                            map.into_iter().map(|(prop, _, ty)| (prop.clone(), ty)),
+                            f.arena,
+                        ),
+                        open: record.open,
+                    })
+                };
+                f.folder.fold_record(f.arena, record)
+            },
+        );
+        let refrs = self.fold_bdd(
+            ty.refrs,
+            |f| &mut f.refrs,
+            |f, refr| {
+                let args: Vec<_> = refr.args.iter().map(|arg| (*arg, f.fold(arg))).collect();
+                let refr = if args.iter().all(|(arg, folded)| ptr::eq(*arg, *folded)) {
+                    refr
+                } else {
+                    f.arena.alloc(Refr {
+                        id: refr.id.clone(),
+                        args: bumpalo::collections::Vec::from_iter_in(
+                            args.into_iter().map(|(_, arg)| arg),
+                            f.arena,
+                        ),
+                    })
+                };
+                f.folder.fold_refr(f.arena, refr)
+            },
+        );
+        Bdd::union(
+            arena,
+            vars,
+            Bdd::atom(
+                arena,
+                arena.alloc(Type {
+                    vars: Bdd::bot(arena),
+                    basics,
+                    ints,
+                    products,
+                    arrows,
+                    records,
+                    refrs,
+                    _c: PhantomData,
+                }),
+            ),
+        )
+    }
+}
+
+/// Rebuilds `bdd` from the images of its atoms, rebuilding each node once.
+fn rebuild<'a, C, T, T2>(
+    arena: &'a bumpalo::Bump,
+    bdd: &'a Bdd<'a, C, T>,
+    images: &Images<'a, C, T, T2>,
+    nodes: &mut HashMap<*const Bdd<'a, C, T>, &'a Bdd<'a, C, T2>>,
+) -> &'a Bdd<'a, C, T2>
+where
+    C: TyConfig,
+    T: TyAtom,
+    T2: TyAtom,
+{
+    if let Some(rebuilt) = nodes.get(&ptr::from_ref(bdd)) {
+        return rebuilt;
+    }
+    let rebuilt = match bdd {
+        Bdd::Atom {
+            atom, pos, lu, neg, ..
+        } => {
+            let image = images[&ptr::from_ref(*atom)];
+            let pos = rebuild(arena, pos, images, nodes);
+            let lu = rebuild(arena, lu, images, nodes);
+            let neg = rebuild(arena, neg, images, nodes);
+            Bdd::union(
+                arena,
+                Bdd::union(arena, Bdd::inter(arena, image, pos), lu),
+                Bdd::diff(arena, neg, image),
+            )
+        }
+        Bdd::Bot => Bdd::bot(arena),
+        Bdd::Top => Bdd::top(arena),
+    };
+    nodes.insert(bdd, rebuilt);
+    rebuilt
+}
@@ -0,0 +1,141 @@
+use crate::ty::TyConfig;
+
+/// An integer type usable as the bounds of intervals.
+pub trait Bound:
+    Copy + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash + std::fmt::Debug
+{
+    const MIN: Self;
+    const MAX: Self;
+
+    /// The next integer. Never called on `MAX`.
+    fn succ(self) -> Self;
+
+    /// The previous integer. Never called on `MIN`.
+    fn pred(self) -> Self;
+}
+
+macro_rules! impl_bound {
+    ($($int:ty),*) => {
+        $(impl Bound for $int {
+            const MIN: Self = <$int>::MIN;
+            const MAX: Self = <$int>::MAX;
+
+            fn succ(self) -> Self {
+                self + 1
+            }
+
+            fn pred(self) -> Self {
+                self - 1
+            }
+        })*
+    };
+}
+impl_bound!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+/// A set of integers, as a sorted list of disjoint, non-adjacent, inclusive ranges. The
+/// representation is canonical, so two sets are equal exactly when their ranges are. Without
+/// `TyConfig::INTERVALS`, there are no integers and every set is empty.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Intervals<'a, C>
+where
+    C: TyConfig,
+{
+    pub ranges: bumpalo::collections::Vec<'a, (C::Int, C::Int)>,
+}
+impl<'a, C> Intervals<'a, C>
+where
+    C: TyConfig,
+{
+    pub fn empty(arena: &'a bumpalo::Bump) -> &'a Self {
+        arena.alloc(Self {
+            ranges: bumpalo::collections::Vec::new_in(arena),
+        })
+    }
+
+    pub fn top(arena: &'a bumpalo::Bump) -> &'a Self {
+        Self::range(arena, C::Int::MIN, C::Int::MAX)
+    }
+
+    /// The integers from `lo` to `hi`, inclusive.
+    pub fn range(arena: &'a bumpalo::Bump, lo: C::Int, hi: C::Int) -> &'a Self {
+        Self::from_ranges(arena, [(lo, hi)])
+    }
+
+    /// The union of arbitrary ranges, which may be unsorted, overlapping or empty.
+    pub fn from_ranges<I>(arena: &'a bumpalo::Bump, ranges: I) -> &'a Self
+    where
+        I: IntoIterator<Item = (C::Int, C::Int)>,
+    {
+        if !C::INTERVALS {
+            return Self::empty(arena);
+        }
+        let mut sorted: Vec<_> = ranges.into_iter().filter(|(lo, hi)| lo <= hi).collect();
+        sorted.sort();
+
+        let mut merged = bumpalo::collections::Vec::<(C::Int, C::Int)>::new_in(arena);
+        for (lo, hi) in sorted {
+            match merged.last_mut() {
+                Some((_, last)) if *last == C::Int::MAX || lo <= last.succ() => {
+                    *last = (*last).max(hi);
+                }
+                _ => merged.push((lo, hi)),
+            }
+        }
+        arena.alloc(Self { ranges: merged })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, n: C::Int) -> bool {
+        self.ranges.iter().any(|&(lo, hi)| lo <= n && n <= hi)
+    }
+
+    pub fn union(arena: &'a bumpalo::Bump, i1: &Self, i2: &Self) -> &'a Self {
+        Self::from_ranges(arena, i1.ranges.iter().chain(i2.ranges.iter()).copied())
+    }
+
+    pub fn inter(arena: &'a bumpalo::Bump, i1: &Self, i2: &Self) -> &'a Self {
+        let mut ranges = bumpalo::collections::Vec::new_in(arena);
+        let (mut r1, mut r2) = (i1.ranges.iter().peekable(), i2.ranges.iter().peekable());
+        while let (Some(&&(lo1, hi1)), Some(&&(lo2, hi2))) = (r1.peek(), r2.peek()) {
+            let (lo, hi) = (lo1.max(lo2), hi1.min(hi2));
+            if lo <= hi {
+                ranges.push((lo, hi));
+            }
+            if hi1 < hi2 {
+                r1.next();
+            } else {
+                r2.next();
+            }
+        }
+        arena.alloc(Self { ranges })
+    }
+
+    pub fn not(arena: &'a bumpalo::Bump, ints: &Self) -> &'a Self {
+        if !C::INTERVALS {
+            return Self::empty(arena);
+        }
+        let mut ranges = bumpalo::collections::Vec::new_in(arena);
+        let mut next = Some(C::Int::MIN);
+        for &(lo, hi) in ints.ranges.iter() {
+            if let Some(start) = next
+                && start < lo
+            {
+                ranges.push((start, lo.pred()));
+            }
+            next = (hi != C::Int::MAX).then(|| hi.succ());
+        }
+        if let Some(start) = next {
+            ranges.push((start, C::Int::MAX));
+        }
+        arena.alloc(Self { ranges })
+    }
+
+    pub fn diff(arena: &'a bumpalo::Bump, i1: &Self, i2: &Self) -> &'a Self {
+        Self::inter(arena, i1, Self::not(arena, i2))
+    }
+}
@@ -1,29 +1,47 @@
 mod arrow;
+mod basic;
+mod dnf;
+mod fold;
+mod interval;
+mod polarity;
 mod product;
 mod record;
 mod refr;
+mod solver;
+mod subst;
+mod tally;
+mod var;
 
 use crate::ty::TyConfig;
 use std::{cmp::Ordering, marker::PhantomData};
 
 pub use arrow::Arrow;
+pub use basic::Basic;
+pub use dnf::Dnf;
+pub use fold::{BddFold, TypeVisitor};
+pub use interval::{Bound, Intervals};
+pub use polarity::Polarity;
 pub use product::Product;
 pub use record::{Openness, Record};
 pub use refr::Refr;
+pub use solver::Solver;
+pub use tally::{ConstraintSet, Constraints, Substitution, Tally, TypePair};
+pub use var::Var;
 
-pub trait TyAtom: PartialEq + Eq + PartialOrd + Ord + std::fmt::Debug {}
+pub trait TyAtom: PartialEq + Eq + PartialOrd + Ord + std::hash::Hash + std::fmt::Debug {}
 
 // NOTES: Start with some "whole, top-level" type that includes variables. Only after Step 5
 // (original paper, elimination of toplevel variables) do we "separate the constructors"
 // (Step 6) to get the more specific BDDs.
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Type<'a, C>
 where
     C: TyConfig,
 {
-    pub vars: &'a Bdd<'a, C, C::Var>,
-    pub basics: &'a Bdd<'a, C, C::Basic>,
+    pub vars: &'a Bdd<'a, C, Var<C>>,
+    pub basics: &'a Bdd<'a, C, Basic<C>>,
+    pub ints: &'a Intervals<'a, C>,
     pub products: &'a Bdd<'a, C, Product<'a, C, Type<'a, C>>>,
     pub arrows: &'a Bdd<'a, C, Arrow<'a, C, Type<'a, C>>>,
     pub records: &'a Bdd<'a, C, Record<'a, C, Type<'a, C>>>,
@@ -38,6 +56,7 @@ where
         Self {
             vars: Bdd::bot(arena),
             basics: Bdd::bot(arena),
+            ints: Intervals::empty(arena),
             products: Bdd::bot(arena),
             arrows: Bdd::bot(arena),
             records: Bdd::bot(arena),
@@ -46,21 +65,160 @@ where
         }
     }
 
-    pub fn from_basics(arena: &'a bumpalo::Bump, basics: &'a Bdd<'a, C, C::Basic>) -> Self {
+    /// The type of some basic values. With intervals, the integers among them are moved to the
+    /// interval component (see `Bdd::split_ints`).
+    pub fn from_basics(arena: &'a bumpalo::Bump, basics: &'a Bdd<'a, C, Basic<C>>) -> Self {
+        let (basics, ints) = basics.split_ints(arena);
         Self {
-            vars: Bdd::bot(arena),
             basics,
-            products: Bdd::bot(arena),
-            arrows: Bdd::bot(arena),
-            records: Bdd::bot(arena),
-            refrs: Bdd::bot(arena),
+            ints,
+            ..Self::empty(arena)
+        }
+    }
+
+    pub fn top(arena: &'a bumpalo::Bump) -> Self {
+        Self {
+            vars: Bdd::top(arena),
+            basics: Bdd::top(arena),
+            ints: Intervals::top(arena),
+            products: Bdd::top(arena),
+            arrows: Bdd::top(arena),
+            records: Bdd::top(arena),
+            refrs: Bdd::top(arena),
             _c: PhantomData,
         }
     }
+
+    pub fn from_vars(arena: &'a bumpalo::Bump, vars: &'a Bdd<'a, C, Var<C>>) -> Self {
+        Self {
+            vars,
+            ..Self::empty(arena)
+        }
+    }
+
+    pub fn from_ints(arena: &'a bumpalo::Bump, ints: &'a Intervals<'a, C>) -> Self {
+        Self {
+            ints,
+            ..Self::empty(arena)
+        }
+    }
+
+    pub fn from_products(
+        arena: &'a bumpalo::Bump,
+        products: &'a Bdd<'a, C, Product<'a, C, Type<'a, C>>>,
+    ) -> Self {
+        Self {
+            products,
+            ..Self::empty(arena)
+        }
+    }
+
+    pub fn from_arrows(
+        arena: &'a bumpalo::Bump,
+        arrows: &'a Bdd<'a, C, Arrow<'a, C, Type<'a, C>>>,
+    ) -> Self {
+        Self {
+            arrows,
+            ..Self::empty(arena)
+        }
+    }
+
+    pub fn from_records(
+        arena: &'a bumpalo::Bump,
+        records: &'a Bdd<'a, C, Record<'a, C, Type<'a, C>>>,
+    ) -> Self {
+        Self {
+            records,
+            ..Self::empty(arena)
+        }
+    }
+
+    pub fn from_refrs(
+        arena: &'a bumpalo::Bump,
+        refrs: &'a Bdd<'a, C, Refr<'a, C, Type<'a, C>>>,
+    ) -> Self {
+        Self {
+            refrs,
+            ..Self::empty(arena)
+        }
+    }
+
+    // The constructor components of a `Type` denote disjoint kinds of values, so the
+    // set-theoretic operations are applied component by component. This is only exact when
+    // there are no top-level variables, which can stand for values of any kind.
+
+    pub fn union(arena: &'a bumpalo::Bump, t1: &Self, t2: &Self) -> Self {
+        Self {
+            vars: Bdd::union(arena, t1.vars, t2.vars),
+            basics: Bdd::union(arena, t1.basics, t2.basics),
+            ints: Intervals::union(arena, t1.ints, t2.ints),
+            products: Bdd::union(arena, t1.products, t2.products),
+            arrows: Bdd::union(arena, t1.arrows, t2.arrows),
+            records: Bdd::union(arena, t1.records, t2.records),
+            refrs: Bdd::union(arena, t1.refrs, t2.refrs),
+            _c: PhantomData,
+        }
+    }
+
+    pub fn inter(arena: &'a bumpalo::Bump, t1: &Self, t2: &Self) -> Self {
+        Self {
+            vars: Bdd::inter(arena, t1.vars, t2.vars),
+            basics: Bdd::inter(arena, t1.basics, t2.basics),
+            ints: Intervals::inter(arena, t1.ints, t2.ints),
+            products: Bdd::inter(arena, t1.products, t2.products),
+            arrows: Bdd::inter(arena, t1.arrows, t2.arrows),
+            records: Bdd::inter(arena, t1.records, t2.records),
+            refrs: Bdd::inter(arena, t1.refrs, t2.refrs),
+            _c: PhantomData,
+        }
+    }
+
+    pub fn diff(arena: &'a bumpalo::Bump, t1: &Self, t2: &Self) -> Self {
+        Self {
+            vars: Bdd::diff(arena, t1.vars, t2.vars),
+            basics: Bdd::diff(arena, t1.basics, t2.basics),
+            ints: Intervals::diff(arena, t1.ints, t2.ints),
+            products: Bdd::diff(arena, t1.products, t2.products),
+            arrows: Bdd::diff(arena, t1.arrows, t2.arrows),
+            records: Bdd::diff(arena, t1.records, t2.records),
+            refrs: Bdd::diff(arena, t1.refrs, t2.refrs),
+            _c: PhantomData,
+        }
+    }
+
+    pub fn not(arena: &'a bumpalo::Bump, ty: &Self) -> Self {
+        Self {
+            vars: Bdd::not(arena, ty.vars),
+            basics: Bdd::not(arena, ty.basics),
+            ints: Intervals::not(arena, ty.ints),
+            products: Bdd::not(arena, ty.products),
+            arrows: Bdd::not(arena, ty.arrows),
+            records: Bdd::not(arena, ty.records),
+            refrs: Bdd::not(arena, ty.refrs),
+            _c: PhantomData,
+        }
+    }
+
+    /// Whether all the constructor components are empty. The variable component is ignored, so
+    /// top-level variables must have been eliminated first (see `Bdd::eliminate_vars`).
+    pub fn is_empty(&self, solver: &Solver<'_, 'a, C>) -> bool {
+        self.basics.is_empty()
+            && self.ints.is_empty()
+            && self.products.is_empty(solver)
+            && self.arrows.is_empty(solver)
+            && self.records.is_empty(solver)
+            && self.refrs.is_empty(solver)
+    }
 }
 impl<'a, C> TyAtom for Type<'a, C> where C: TyConfig {}
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// Whether a clause of opaque atoms is empty because some atom occurs both positively and
+/// negatively.
+pub(crate) fn clause_contradicts<T: PartialEq>(pos: &[&T], neg: &[&T]) -> bool {
+    pos.iter().any(|p| neg.contains(p))
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Bdd<'a, C, T>
 where
     C: TyConfig,
@@ -106,6 +264,39 @@ where
         }
     }
 
+    /// Rebuilds the BDD from the images of its atoms under `f`, which is given the polarity of
+    /// each occurrence (`true` for positive): an atom under a negation has the opposite polarity.
+    pub fn map_polar<T2: TyAtom, F: Fn(&'a T, bool) -> &'a Bdd<'a, C, T2>>(
+        arena: &'a bumpalo::Bump,
+        bdd: &'a Self,
+        positive: bool,
+        f: &F,
+    ) -> &'a Bdd<'a, C, T2> {
+        match bdd {
+            Bdd::Atom {
+                atom, pos, lu, neg, ..
+            } => Bdd::union(
+                arena,
+                Bdd::union(
+                    arena,
+                    Bdd::inter(
+                        arena,
+                        f(atom, positive),
+                        Self::map_polar(arena, pos, positive, f),
+                    ),
+                    Self::map_polar(arena, lu, positive, f),
+                ),
+                Bdd::diff(
+                    arena,
+                    Self::map_polar(arena, neg, positive, f),
+                    f(atom, !positive),
+                ),
+            ),
+            Bdd::Bot => Bdd::bot(arena),
+            Bdd::Top => Bdd::top(arena),
+        }
+    }
+
     pub fn top(arena: &'a bumpalo::Bump) -> &'a Self {
         arena.alloc(Self::Top)
     }
@@ -134,9 +325,9 @@ where
                 _c,
             } => arena.alloc(Self::Atom {
                 atom,
-                pos: Self::not(arena, pos),
-                lu: Self::not(arena, lu),
-                neg: Self::not(arena, neg),
+                pos: Self::not(arena, Self::union(arena, pos, lu)),
+                lu: Self::bot(arena),
+                neg: Self::not(arena, Self::union(arena, neg, lu)),
                 _c: PhantomData,
             }),
             Bdd::Bot => Bdd::top(arena),
@@ -255,22 +446,7 @@ where
 
             (_, Self::Bot) => b1,
 
-            (
-                Self::Top,
-                Self::Atom {
-                    atom,
-                    pos: b1,
-                    lu,
-                    neg: b2,
-                    _c: _,
-                },
-            ) => arena.alloc(Self::Atom {
-                atom,
-                pos: Self::diff(arena, Self::top(arena), b1),
-                lu,
-                neg: Self::diff(arena, Self::top(arena), b2),
-                _c: PhantomData,
-            }),
+            (Self::Top, _) => Self::not(arena, b2),
 
             (
                 Self::Atom {
@@ -331,4 +507,189 @@ where
             _ => bdd,
         }
     }
+
+    /// The intersection of the atoms in `pos` and of the complements of the atoms in `neg`.
+    pub(crate) fn clause(arena: &'a bumpalo::Bump, pos: &[&'a T], neg: &[&'a T]) -> &'a Self {
+        let pos = pos.iter().fold(Self::top(arena), |acc, atom| {
+            Self::inter(arena, acc, Self::atom(arena, atom))
+        });
+        neg.iter().fold(pos, |acc, atom| {
+            Self::diff(arena, acc, Self::atom(arena, atom))
+        })
+    }
+
+    /// Calls `f` on every atom occurrence of the BDD.
+    pub(crate) fn for_each_atom<F: FnMut(&'a T)>(&'a self, f: &mut F) {
+        if let Self::Atom {
+            atom, pos, lu, neg, ..
+        } = self
+        {
+            f(atom);
+            pos.for_each_atom(f);
+            lu.for_each_atom(f);
+            neg.for_each_atom(f);
+        }
+    }
+
+    /// Calls `f` on every atom occurrence of the BDD with its polarity (`true` for positive),
+    /// as in `Bdd::map_polar`. An atom with both a positive and a negative branch is visited
+    /// once with each polarity.
+    pub(crate) fn for_each_atom_polar<F: FnMut(&'a T, bool)>(&'a self, positive: bool, f: &mut F) {
+        if let Self::Atom {
+            atom, pos, lu, neg, ..
+        } = self
+        {
+            if **pos != Self::Bot {
+                f(atom, positive);
+            }
+            if **neg != Self::Bot {
+                f(atom, !positive);
+            }
+            pos.for_each_atom_polar(positive, f);
+            lu.for_each_atom_polar(positive, f);
+            neg.for_each_atom_polar(positive, f);
+        }
+    }
+
+    /// Checks `f` against every clause of the BDD (see `Bdd::dnf`), stopping at the first
+    /// clause for which it returns `false`. Unlike `Dnf`, the clauses are passed as slices of a
+    /// single path, without copying them.
+    pub(crate) fn all_clauses<F>(&'a self, f: &mut F) -> bool
+    where
+        F: FnMut(&[&'a T], &[&'a T]) -> bool,
+    {
+        self.all_clauses_from(&mut Vec::new(), &mut Vec::new(), f)
+    }
+
+    fn all_clauses_from<F>(&'a self, pos: &mut Vec<&'a T>, neg: &mut Vec<&'a T>, f: &mut F) -> bool
+    where
+        F: FnMut(&[&'a T], &[&'a T]) -> bool,
+    {
+        match self {
+            Self::Top => f(pos, neg),
+            Self::Bot => true,
+            Self::Atom {
+                atom,
+                pos: c,
+                lu: u,
+                neg: d,
+                _c,
+            } => {
+                pos.push(atom);
+                let holds = c.all_clauses_from(pos, neg, f);
+                pos.pop();
+                if !holds || !u.all_clauses_from(pos, neg, f) {
+                    return false;
+                }
+                neg.push(atom);
+                let holds = d.all_clauses_from(pos, neg, f);
+                neg.pop();
+                holds
+            }
+        }
+    }
+}
+
+impl<'a, C> Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    /// Collapses a boolean combination of `Type`s into a single `Type`.
+    pub fn flatten(arena: &'a bumpalo::Bump, bdd: &'a Self) -> Type<'a, C> {
+        match bdd {
+            Self::Top => Type::top(arena),
+            Self::Bot => Type::empty(arena),
+            Self::Atom {
+                atom, pos, lu, neg, ..
+            } => {
+                let pos = Type::inter(arena, atom, &Self::flatten(arena, pos));
+                let neg = Type::diff(arena, &Self::flatten(arena, neg), atom);
+                Type::union(
+                    arena,
+                    &Type::union(arena, &pos, &Self::flatten(arena, lu)),
+                    &neg,
+                )
+            }
+        }
+    }
+
+    /// Collapses a boolean combination of `Type`s into a single `Type` without top-level
+    /// variables, by deciding with `f` whether each variable occurrence (given its polarity, as in
+    /// `Bdd::eval`) covers every value or none.
+    pub fn eliminate_vars<F>(
+        arena: &'a bumpalo::Bump,
+        bdd: &'a Self,
+        positive: bool,
+        f: &F,
+    ) -> Type<'a, C>
+    where
+        F: Fn(&'a Var<C>, bool) -> bool,
+    {
+        let eliminate = |ty: &'a Type<'a, C>, positive| {
+            if ty.vars.eval(positive, f) {
+                Type {
+                    vars: Bdd::bot(arena),
+                    ..Type::top(arena)
+                }
+            } else {
+                Type {
+                    vars: Bdd::bot(arena),
+                    ..*ty
+                }
+            }
+        };
+        match bdd {
+            Self::Top => Type {
+                vars: Bdd::bot(arena),
+                ..Type::top(arena)
+            },
+            Self::Bot => Type::empty(arena),
+            Self::Atom {
+                atom, pos, lu, neg, ..
+            } => {
+                let pos = Type::inter(
+                    arena,
+                    &eliminate(atom, positive),
+                    &Self::eliminate_vars(arena, pos, positive, f),
+                );
+                let neg = Type::diff(
+                    arena,
+                    &Self::eliminate_vars(arena, neg, positive, f),
+                    &eliminate(atom, !positive),
+                );
+                Type::union(
+                    arena,
+                    &Type::union(arena, &pos, &Self::eliminate_vars(arena, lu, positive, f)),
+                    &neg,
+                )
+            }
+        }
+    }
+
+    /// Replaces the top-level occurrences of `var` with the top type if `value` holds, or the
+    /// empty type otherwise.
+    pub fn restrict(
+        arena: &'a bumpalo::Bump,
+        bdd: &'a Self,
+        var: &Var<C>,
+        value: bool,
+    ) -> &'a Self {
+        Bdd::map_polar(arena, bdd, true, &|ty, _| {
+            let vars = Bdd::map_polar(arena, ty.vars, true, &|v, _| match v == var {
+                true if value => Bdd::top(arena),
+                true => Bdd::bot(arena),
+                false => Bdd::atom(arena, v),
+            });
+            Bdd::atom(arena, arena.alloc(Type { vars, ..*ty }))
+        })
+    }
+
+    /// The variables occurring at the top level of the type, outside of any constructor.
+    pub fn top_vars(&'a self) -> Vec<&'a Var<C>> {
+        let mut vars = Vec::new();
+        self.for_each_atom(&mut |ty| ty.vars.for_each_atom(&mut |var| vars.push(var)));
+        vars.sort();
+        vars.dedup();
+        vars
+    }
 }
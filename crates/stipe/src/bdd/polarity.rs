@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    bdd::{Bdd, Type, TypeVisitor, Var},
+    defs::{Defs, Variance},
+    ty::TyConfig,
+};
+
+/// The polarities of the occurrences of a variable in a type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    Positive,
+    Negative,
+    Both,
+}
+impl Polarity {
+    pub fn is_positive(self) -> bool {
+        matches!(self, Self::Positive | Self::Both)
+    }
+
+    pub fn is_negative(self) -> bool {
+        matches!(self, Self::Negative | Self::Both)
+    }
+
+    fn join(self, other: Self) -> Self {
+        if self == other { self } else { Self::Both }
+    }
+}
+
+impl<'a, C> Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    /// Calls `f` with each variable occurrence and its variance, the type being in a position of
+    /// variance `variance`. Arguments of references take the variances of the parameters in
+    /// `defs` into account, and are invariant if the type isn't defined. Occurrences in
+    /// bivariant positions are skipped.
+    pub fn for_each_var<F>(&'a self, defs: &Defs<'a, C>, variance: Variance, f: &mut F)
+    where
+        F: FnMut(&'a Var<C>, Variance),
+    {
+        self.for_each_atom_polar(true, &mut |ty, positive| {
+            let variance = if positive { variance } else { variance.flip() };
+            ty.vars.for_each_atom_polar(true, &mut |var, positive| {
+                f(var, if positive { variance } else { variance.flip() })
+            });
+            let mut nested = Vec::new();
+            ty.products
+                .for_each_atom_polar(true, &mut |product, positive| {
+                    let variance = if positive { variance } else { variance.flip() };
+                    nested.push((product.0, variance));
+                    nested.push((product.1, variance));
+                });
+            ty.arrows.for_each_atom_polar(true, &mut |arrow, positive| {
+                let variance = if positive { variance } else { variance.flip() };
+                nested.push((arrow.0, variance.flip()));
+                nested.push((arrow.1, variance));
+            });
+            ty.records
+                .for_each_atom_polar(true, &mut |record, positive| {
+                    let variance = if positive { variance } else { variance.flip() };
+                    nested.extend(record.map.iter().map(|(_, ty)| (*ty, variance)));
+                });
+            ty.refrs.for_each_atom_polar(true, &mut |refr, positive| {
+                let variance = if positive { variance } else { variance.flip() };
+                for (i, arg) in refr.args.iter().enumerate() {
+                    let inner = defs
+                        .get(&refr.id)
+                        .and_then(|def| def.variances.get(i).copied())
+                        .unwrap_or(Variance::Invariant);
+                    nested.push((arg, variance.compose(inner)));
+                }
+            });
+            for (ty, variance) in nested {
+                if variance != Variance::Bivariant {
+                    ty.for_each_var(defs, variance, f);
+                }
+            }
+        });
+    }
+
+    /// The variables occurring anywhere in the type, in order.
+    pub fn free_vars(&'a self) -> Vec<&'a C::Var> {
+        struct FreeVars<'a, C: TyConfig>(Vec<&'a C::Var>);
+        impl<'a, C: TyConfig> TypeVisitor<'a, C> for FreeVars<'a, C> {
+            fn visit_var(&mut self, var: &'a Var<C>) {
+                if let Var::Named(var) = var {
+                    self.0.push(var);
+                }
+            }
+        }
+
+        let mut vars = FreeVars(Vec::new());
+        self.visit(&mut vars);
+        vars.0.sort();
+        vars.0.dedup();
+        vars.0
+    }
+
+    /// The polarity of each variable occurring in the type, where the variables of the
+    /// arguments of references to the types in `defs` follow their variances.
+    pub fn polarities(&'a self, defs: &Defs<'a, C>) -> BTreeMap<&'a C::Var, Polarity> {
+        let mut polarities = BTreeMap::new();
+        self.for_each_var(defs, Variance::Covariant, &mut |var, variance| {
+            let polarity = match variance {
+                Variance::Bivariant => return,
+                Variance::Covariant => Polarity::Positive,
+                Variance::Contravariant => Polarity::Negative,
+                Variance::Invariant => Polarity::Both,
+            };
+            if let Var::Named(var) = var {
+                polarities
+                    .entry(var)
+                    .and_modify(|p: &mut Polarity| *p = p.join(polarity))
+                    .or_insert(polarity);
+            }
+        });
+        polarities
+    }
+}
+
+impl<'a, C> Type<'a, C>
+where
+    C: TyConfig,
+{
+    pub fn free_vars(&'a self, arena: &'a bumpalo::Bump) -> Vec<&'a C::Var> {
+        Bdd::atom(arena, self).free_vars()
+    }
+}
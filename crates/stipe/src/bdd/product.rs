@@ -1,9 +1,9 @@
 use crate::{
-    bdd::{Bdd, TyAtom},
+    bdd::{Bdd, Constraints, Solver, Tally, TyAtom, Type},
     ty::TyConfig,
 };
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Product<'a, C, T>(pub &'a Bdd<'a, C, T>, pub &'a Bdd<'a, C, T>)
 where
     C: TyConfig,
@@ -14,3 +14,149 @@ where
     T: TyAtom,
 {
 }
+impl<'a, C> Bdd<'a, C, Product<'a, C, Type<'a, C>>>
+where
+    C: TyConfig,
+{
+    pub fn is_empty(&'a self, solver: &Solver<'_, 'a, C>) -> bool {
+        let arena = solver.arena;
+        self.all_clauses(&mut |pos, neg| {
+            let (l, r) = pos.iter().fold(
+                (Bdd::top(arena), Bdd::top(arena)),
+                |(l, r), Product(pl, pr)| {
+                    (
+                        Bdd::inter(solver.arena, l, pl),
+                        Bdd::inter(solver.arena, r, pr),
+                    )
+                },
+            );
+            Self::clause_is_empty(solver, l, r, neg)
+        })
+    }
+
+    /// `l × r` minus the products in `neg` is empty if, for the first of them, both ways of
+    /// escaping it (through the left or the right component) lead to empty products.
+    fn clause_is_empty(
+        solver: &Solver<'_, 'a, C>,
+        l: &'a Bdd<'a, C, Type<'a, C>>,
+        r: &'a Bdd<'a, C, Type<'a, C>>,
+        neg: &[&'a Product<'a, C, Type<'a, C>>],
+    ) -> bool {
+        if solver.is_empty(l) || solver.is_empty(r) {
+            return true;
+        }
+        match neg.split_first() {
+            None => false,
+            Some((Product(nl, nr), rest)) => {
+                Self::clause_is_empty(solver, Bdd::diff(solver.arena, l, nl), r, rest)
+                    && Self::clause_is_empty(solver, l, Bdd::diff(solver.arena, r, nr), rest)
+            }
+        }
+    }
+
+    /// The constraints under which the products are empty, following `is_empty`.
+    pub(crate) fn tally(&'a self, tally: &Tally<'_, '_, 'a, C>) -> Constraints<'a, C> {
+        let arena = tally.solver().arena;
+        let mut constraints = Constraints::sat();
+        self.all_clauses(&mut |pos, neg| {
+            let (l, r) = pos.iter().fold(
+                (Bdd::top(arena), Bdd::top(arena)),
+                |(l, r), Product(pl, pr)| (Bdd::inter(arena, l, pl), Bdd::inter(arena, r, pr)),
+            );
+            constraints = std::mem::replace(&mut constraints, Constraints::unsat())
+                .and(arena, &Self::clause_tally(tally, l, r, neg));
+            !constraints.is_unsat()
+        });
+        constraints
+    }
+
+    fn clause_tally(
+        tally: &Tally<'_, '_, 'a, C>,
+        l: &'a Bdd<'a, C, Type<'a, C>>,
+        r: &'a Bdd<'a, C, Type<'a, C>>,
+        neg: &[&'a Product<'a, C, Type<'a, C>>],
+    ) -> Constraints<'a, C> {
+        let arena = tally.solver().arena;
+        let empty = tally.norm(l).or(tally.norm(r));
+        match neg.split_first() {
+            None => empty,
+            Some((Product(nl, nr), rest)) => {
+                empty.or(
+                    Self::clause_tally(tally, Bdd::diff(arena, l, nl), r, rest).and(
+                        arena,
+                        &Self::clause_tally(tally, l, Bdd::diff(arena, r, nr), rest),
+                    ),
+                )
+            }
+        }
+    }
+}
+
+impl<'a, C> Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    /// The type of the left components of the pairs of a product type. Top-level variables are
+    /// over-approximated (see `Bdd::upper_bound`).
+    pub fn proj_left(solver: &Solver<'_, 'a, C>, ty: &'a Self) -> &'a Self {
+        Self::proj(solver, ty, true)
+    }
+
+    /// The type of the right components of the pairs of a product type.
+    pub fn proj_right(solver: &Solver<'_, 'a, C>, ty: &'a Self) -> &'a Self {
+        Self::proj(solver, ty, false)
+    }
+
+    fn proj(solver: &Solver<'_, 'a, C>, ty: &'a Self, left: bool) -> &'a Self {
+        let arena = solver.arena;
+        let mut result = Bdd::bot(arena);
+        solver
+            .upper_bound(ty)
+            .products
+            .all_clauses(&mut |pos, neg| {
+                let (l, r) = pos.iter().fold(
+                    (Bdd::top(arena), Bdd::top(arena)),
+                    |(l, r), Product(pl, pr)| {
+                        (
+                            Bdd::inter(solver.arena, l, pl),
+                            Bdd::inter(solver.arena, r, pr),
+                        )
+                    },
+                );
+                Self::proj_clause(solver, l, r, neg, left, &mut result);
+                true
+            });
+        result
+    }
+
+    /// Adds to `result` the left (or right) components of `l × r` minus the products in `neg`.
+    /// Each negated product is escaped through either its left or its right component. Escaping
+    /// through the other component comes first, so that the component kept is as large as
+    /// possible, and choices are cut short once `l` or `r` is empty or `result` already has the
+    /// component kept, since escaping the remaining products can only shrink them.
+    fn proj_clause(
+        solver: &Solver<'_, 'a, C>,
+        l: &'a Self,
+        r: &'a Self,
+        neg: &[&'a Product<'a, C, Type<'a, C>>],
+        left: bool,
+        result: &mut &'a Self,
+    ) {
+        let arena = solver.arena;
+        let kept = if left { l } else { r };
+        if solver.is_empty(l) || solver.is_empty(r) || solver.is_subtype(kept, result) {
+            return;
+        }
+        match neg.split_first() {
+            None => *result = Bdd::union(arena, result, kept),
+            Some((Product(nl, nr), rest)) => {
+                let escaped = [(l, Bdd::diff(arena, r, nr)), (Bdd::diff(arena, l, nl), r)];
+                let order = if left { [0, 1] } else { [1, 0] };
+                for i in order {
+                    let (l, r) = escaped[i];
+                    Self::proj_clause(solver, l, r, rest, left, result);
+                }
+            }
+        }
+    }
+}
@@ -1,15 +1,15 @@
 use crate::{
-    bdd::{Bdd, TyAtom},
-    ty::{Ty, TyConfig},
+    bdd::{Bdd, Constraints, Solver, Tally, TyAtom, Type},
+    ty::TyConfig,
 };
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Openness {
     Open,
     Closed,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Record<'a, C, T>
 where
     C: TyConfig,
@@ -24,3 +24,174 @@ where
     T: TyAtom,
 {
 }
+impl<'a, C, T> Record<'a, C, T>
+where
+    C: TyConfig,
+    T: TyAtom,
+{
+    pub fn get(&self, prop: &C::Prop) -> Option<&'a Bdd<'a, C, T>> {
+        self.map.iter().find(|(p, _)| p == prop).map(|(_, ty)| *ty)
+    }
+}
+
+/// The possible contents of a record field: a value of some type, or the field being absent.
+struct Field<'a, C>
+where
+    C: TyConfig,
+{
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+    absent: bool,
+}
+impl<'a, C> Clone for Field<'a, C>
+where
+    C: TyConfig,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'a, C> Copy for Field<'a, C> where C: TyConfig {}
+impl<'a, C> Field<'a, C>
+where
+    C: TyConfig,
+{
+    fn inter(arena: &'a bumpalo::Bump, f1: Self, f2: Self) -> Self {
+        Self {
+            ty: Bdd::inter(arena, f1.ty, f2.ty),
+            absent: f1.absent && f2.absent,
+        }
+    }
+
+    fn diff(arena: &'a bumpalo::Bump, f1: Self, f2: Self) -> Self {
+        Self {
+            ty: Bdd::diff(arena, f1.ty, f2.ty),
+            absent: f1.absent && !f2.absent,
+        }
+    }
+
+    fn is_empty(&self, solver: &Solver<'_, 'a, C>) -> bool {
+        !self.absent && solver.is_empty(self.ty)
+    }
+
+    fn tally(&self, tally: &Tally<'_, '_, 'a, C>) -> Constraints<'a, C> {
+        if self.absent {
+            Constraints::unsat()
+        } else {
+            tally.norm(self.ty)
+        }
+    }
+}
+
+impl<'a, C> Bdd<'a, C, Record<'a, C, Type<'a, C>>>
+where
+    C: TyConfig,
+{
+    /// Records are checked as products with one component per property mentioned in the
+    /// clause, plus a final component standing for all the other properties.
+    pub fn is_empty(&'a self, solver: &Solver<'_, 'a, C>) -> bool {
+        self.all_clauses(&mut |pos, neg| {
+            let (pos, neg) = Self::clause_fields(solver.arena, pos, neg);
+            Self::clause_is_empty(solver, &pos, &neg)
+        })
+    }
+
+    /// The constraints under which the records are empty, following `is_empty`.
+    pub(crate) fn tally(&'a self, tally: &Tally<'_, '_, 'a, C>) -> Constraints<'a, C> {
+        let arena = tally.solver().arena;
+        let mut constraints = Constraints::sat();
+        self.all_clauses(&mut |pos, neg| {
+            let (pos, neg) = Self::clause_fields(arena, pos, neg);
+            constraints = std::mem::replace(&mut constraints, Constraints::unsat())
+                .and(arena, &Self::clause_tally(tally, &pos, &neg));
+            !constraints.is_unsat()
+        });
+        constraints
+    }
+
+    /// The fields of the intersection of the positive records and those of each negative record,
+    /// for the properties mentioned in the clause and then the other properties.
+    #[allow(clippy::type_complexity)]
+    fn clause_fields(
+        arena: &'a bumpalo::Bump,
+        pos: &[&'a Record<'a, C, Type<'a, C>>],
+        neg: &[&'a Record<'a, C, Type<'a, C>>],
+    ) -> (Vec<Field<'a, C>>, Vec<Vec<Field<'a, C>>>) {
+        let mut props: Vec<&C::Prop> = pos
+            .iter()
+            .chain(neg)
+            .flat_map(|rec| rec.map.iter().map(|(p, _)| p))
+            .collect();
+        props.sort();
+        props.dedup();
+
+        let fields = |rec: &'a Record<'a, C, Type<'a, C>>| -> Vec<Field<'a, C>> {
+            let other = Field {
+                ty: match rec.open {
+                    Openness::Open => Bdd::top(arena),
+                    Openness::Closed => Bdd::bot(arena),
+                },
+                absent: true,
+            };
+            props
+                .iter()
+                .map(|p| match rec.get(p) {
+                    Some(ty) => Field { ty, absent: false },
+                    None => other,
+                })
+                .chain(std::iter::once(other))
+                .collect()
+        };
+
+        let any = Field {
+            ty: Bdd::top(arena),
+            absent: true,
+        };
+        let pos = pos.iter().fold(vec![any; props.len() + 1], |acc, rec| {
+            acc.into_iter()
+                .zip(fields(rec))
+                .map(|(f1, f2)| Field::inter(arena, f1, f2))
+                .collect()
+        });
+        let neg = neg.iter().map(|rec| fields(rec)).collect();
+        (pos, neg)
+    }
+
+    fn clause_is_empty(
+        solver: &Solver<'_, 'a, C>,
+        pos: &[Field<'a, C>],
+        neg: &[Vec<Field<'a, C>>],
+    ) -> bool {
+        if pos.iter().any(|f| f.is_empty(solver)) {
+            return true;
+        }
+        match neg.split_first() {
+            None => false,
+            Some((first, rest)) => (0..pos.len()).all(|i| {
+                let mut escaped = pos.to_vec();
+                escaped[i] = Field::diff(solver.arena, pos[i], first[i]);
+                Self::clause_is_empty(solver, &escaped, rest)
+            }),
+        }
+    }
+
+    fn clause_tally(
+        tally: &Tally<'_, '_, 'a, C>,
+        pos: &[Field<'a, C>],
+        neg: &[Vec<Field<'a, C>>],
+    ) -> Constraints<'a, C> {
+        let empty = pos
+            .iter()
+            .fold(Constraints::unsat(), |acc, f| acc.or(f.tally(tally)));
+        match neg.split_first() {
+            None => empty,
+            Some((first, rest)) => {
+                let arena = tally.solver().arena;
+                empty.or((0..pos.len()).fold(Constraints::sat(), |acc, i| {
+                    let mut escaped = pos.to_vec();
+                    escaped[i] = Field::diff(arena, pos[i], first[i]);
+                    acc.and(arena, &Self::clause_tally(tally, &escaped, rest))
+                }))
+            }
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use crate::{
-    bdd::{Bdd, TyAtom},
+    bdd::{Bdd, Constraints, Solver, Tally, TyAtom, Type},
+    defs::Variance,
     ty::TyConfig,
 };
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Refr<'a, C, T>
 where
     C: TyConfig,
@@ -18,3 +19,61 @@ where
     T: TyAtom,
 {
 }
+impl<'a, C> Bdd<'a, C, Refr<'a, C, Type<'a, C>>>
+where
+    C: TyConfig,
+{
+    /// References left after unfolding are opaque: distinct references may overlap, so a clause
+    /// is only empty when it includes a reference that refines an excluded one.
+    pub fn is_empty(&'a self, solver: &Solver<'_, 'a, C>) -> bool {
+        self.all_clauses(&mut |pos, neg| solver.refr_clause_is_empty(pos, neg))
+    }
+
+    /// The constraints under which the references are empty, following `is_empty`: some included
+    /// reference must refine an excluded one.
+    pub(crate) fn tally(&'a self, tally: &Tally<'_, '_, 'a, C>) -> Constraints<'a, C> {
+        let arena = tally.solver().arena;
+        let mut constraints = Constraints::sat();
+        self.all_clauses(&mut |pos, neg| {
+            let mut clause = Constraints::unsat();
+            for p in pos {
+                for n in neg {
+                    clause = clause.or(Self::refines_tally(tally, p, n));
+                }
+            }
+            constraints =
+                std::mem::replace(&mut constraints, Constraints::unsat()).and(arena, &clause);
+            !constraints.is_unsat()
+        });
+        constraints
+    }
+
+    fn refines_tally(
+        tally: &Tally<'_, '_, 'a, C>,
+        r1: &'a Refr<'a, C, Type<'a, C>>,
+        r2: &'a Refr<'a, C, Type<'a, C>>,
+    ) -> Constraints<'a, C> {
+        let solver = tally.solver();
+        let arena = solver.arena;
+        if r1.id != r2.id || r1.args.len() != r2.args.len() {
+            return Constraints::unsat();
+        }
+        let variances = solver.defs.get(&r1.id).map(|def| &def.variances);
+        let sub = |t1, t2| tally.norm(Bdd::diff(arena, t1, t2));
+        r1.args.iter().zip(r2.args.iter()).enumerate().fold(
+            Constraints::sat(),
+            |acc, (i, (a1, a2))| {
+                let arg = match variances
+                    .and_then(|variances| variances.get(i))
+                    .unwrap_or(&Variance::Invariant)
+                {
+                    Variance::Bivariant => Constraints::sat(),
+                    Variance::Covariant => sub(a1, a2),
+                    Variance::Contravariant => sub(a2, a1),
+                    Variance::Invariant => sub(a1, a2).and(arena, &sub(a2, a1)),
+                };
+                acc.and(arena, &arg)
+            },
+        )
+    }
+}
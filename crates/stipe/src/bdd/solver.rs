@@ -0,0 +1,266 @@
+use std::cell::RefCell;
+
+use crate::{
+    Context,
+    bdd::{Bdd, Refr, Type, Var},
+    defs::{Defs, Variance},
+    ty::TyConfig,
+};
+
+/// Decides emptiness and subtyping, unfolding references to the named types defined in `defs`.
+pub struct Solver<'d, 'a, C>
+where
+    C: TyConfig,
+{
+    pub arena: &'a bumpalo::Bump,
+    pub defs: &'d Defs<'a, C>,
+    /// The context of the types, which makes the fresh variables of tallying.
+    pub(crate) ctx: &'a Context<C>,
+    /// The opaque types whose representation is visible to this solver.
+    revealed: Vec<C::TyName>,
+    /// The types being checked, which are assumed to be empty if they recur.
+    assumed: RefCell<Vec<&'a Bdd<'a, C, Type<'a, C>>>>,
+}
+impl<'d, 'a, C> Solver<'d, 'a, C>
+where
+    C: TyConfig,
+{
+    pub fn new(ctx: &'a Context<C>, defs: &'d Defs<'a, C>) -> Self {
+        Self {
+            arena: &ctx.arena,
+            defs,
+            ctx,
+            revealed: Vec::new(),
+            assumed: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Makes the solver see through references to the opaque type `name`, as in the module that
+    /// defines it.
+    pub fn reveal(mut self, name: C::TyName) -> Self {
+        self.revealed.push(name);
+        self
+    }
+
+    /// A type is empty if it is empty for every assignment of its variables. Top-level references
+    /// are unfolded, then top-level variables are eliminated clause by clause (see
+    /// `Solver::all_assignments`), and the components of constructors are checked in the same
+    /// way. Recursive types are handled coinductively: a type that recurs while it is being
+    /// checked is assumed to be empty.
+    pub fn is_empty(&self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> bool {
+        match ty {
+            Bdd::Top => false,
+            Bdd::Bot => true,
+            Bdd::Atom { .. } => {
+                if self.assumed.borrow().contains(&ty) {
+                    return true;
+                }
+                let unfolded = self.unfold(ty);
+                self.assumed.borrow_mut().push(ty);
+                let empty = if unfolded.top_vars().is_empty() {
+                    Bdd::eliminate_vars(self.arena, unfolded, true, &|_, _| false).is_empty(self)
+                } else {
+                    unfolded.all_clauses(&mut |pos, neg| {
+                        self.all_assignments(pos, neg, &mut |kinds| kinds.is_empty(self))
+                    })
+                };
+                self.assumed.borrow_mut().pop();
+                empty
+            }
+        }
+    }
+
+    /// Checks `f` against the constructor components left of a clause of types by each
+    /// assignment of its variables to every value or none, stopping at the first for which it
+    /// returns `false`. Assignments under which a negated type covers every value are skipped, as
+    /// the clause is then empty. The variables are assigned one at a time, and an assignment is
+    /// cut short once the components left are empty, since assigning the others only removes
+    /// values.
+    pub(crate) fn all_assignments<F>(
+        &self,
+        pos: &[&'a Type<'a, C>],
+        neg: &[&'a Type<'a, C>],
+        f: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&Type<'a, C>) -> bool,
+    {
+        let mut vars = Vec::new();
+        for ty in pos.iter().chain(neg) {
+            ty.vars.for_each_atom(&mut |var| vars.push(var));
+        }
+        vars.sort();
+        vars.dedup();
+        self.all_assignments_from(pos, neg, &vars, &mut Vec::new(), f)
+    }
+
+    fn all_assignments_from<F>(
+        &self,
+        pos: &[&'a Type<'a, C>],
+        neg: &[&'a Type<'a, C>],
+        vars: &[&'a Var<C>],
+        assigned: &mut Vec<(&'a Var<C>, bool)>,
+        f: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&Type<'a, C>) -> bool,
+    {
+        let arena = self.arena;
+        let mut decided = true;
+        let mut kinds = Type {
+            vars: Bdd::bot(arena),
+            ..Type::top(arena)
+        };
+        for ty in neg {
+            match ty.vars.eval_partial(assigned) {
+                Some(true) => return true,
+                Some(false) => {}
+                None => decided = false,
+            }
+            kinds = Type::diff(arena, &kinds, ty);
+        }
+        for ty in pos {
+            match ty.vars.eval_partial(assigned) {
+                Some(true) => {}
+                Some(false) => kinds = Type::inter(arena, &kinds, ty),
+                None => decided = false,
+            }
+        }
+        let Some((var, rest)) = vars.split_first().filter(|_| !decided) else {
+            return f(&kinds);
+        };
+        if kinds.is_empty(self) {
+            return true;
+        }
+        [true, false].into_iter().all(|value| {
+            assigned.push((var, value));
+            let holds = self.all_assignments_from(pos, neg, rest, assigned, f);
+            assigned.pop();
+            holds
+        })
+    }
+
+    pub fn is_subtype(
+        &self,
+        t1: &'a Bdd<'a, C, Type<'a, C>>,
+        t2: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> bool {
+        self.is_empty(Bdd::diff(self.arena, t1, t2))
+    }
+
+    /// An upper bound of the type without top-level references or variables, obtained by making
+    /// positive variable occurrences cover every value and negative ones cover none.
+    pub fn upper_bound(&self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> Type<'a, C> {
+        Bdd::eliminate_vars(self.arena, self.unfold(ty), true, &|_, positive| positive)
+    }
+
+    /// Replaces the top-level references to defined types with their bodies, unfolded in turn.
+    /// References to undefined types and to opaque types that aren't revealed are kept.
+    /// A reference that recurs without going through a constructor stands for no values.
+    pub fn unfold(&self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> &'a Bdd<'a, C, Type<'a, C>> {
+        self.unfold_within(ty, &[])
+    }
+
+    fn unfold_within(
+        &self,
+        ty: &'a Bdd<'a, C, Type<'a, C>>,
+        unfolding: &[&'a Refr<'a, C, Type<'a, C>>],
+    ) -> &'a Bdd<'a, C, Type<'a, C>> {
+        let mut has_refrs = false;
+        ty.for_each_atom(&mut |ty| has_refrs |= *ty.refrs != Bdd::Bot);
+        if !has_refrs {
+            return ty;
+        }
+        Bdd::map_polar(self.arena, ty, true, &|ty, _| {
+            self.unfold_type(ty, unfolding)
+        })
+    }
+
+    fn unfold_type(
+        &self,
+        ty: &'a Type<'a, C>,
+        unfolding: &[&'a Refr<'a, C, Type<'a, C>>],
+    ) -> &'a Bdd<'a, C, Type<'a, C>> {
+        let arena = self.arena;
+        if *ty.refrs == Bdd::Bot {
+            return Bdd::atom(arena, ty);
+        }
+        let image = |refr: &'a Refr<'a, C, Type<'a, C>>| match self.defs.get(&refr.id) {
+            Some(def) if !def.opaque || self.revealed.contains(&refr.id) => {
+                if unfolding.contains(&refr) {
+                    Bdd::bot(arena)
+                } else {
+                    let body = self.defs.unfold(arena, refr).unwrap();
+                    self.unfold_within(body, &[unfolding, &[refr]].concat())
+                }
+            }
+            _ => Bdd::atom(
+                arena,
+                arena.alloc(Type::from_refrs(arena, Bdd::atom(arena, refr))),
+            ),
+        };
+        let mut refrs = Bdd::bot(arena);
+        ty.refrs.all_clauses(&mut |pos, neg| {
+            if !self.refr_clause_is_empty(pos, neg) {
+                let clause = pos.iter().fold(Bdd::top(arena), |acc, refr| {
+                    Bdd::inter(arena, acc, image(refr))
+                });
+                let clause = neg
+                    .iter()
+                    .fold(clause, |acc, refr| Bdd::diff(arena, acc, image(refr)));
+                refrs = Bdd::union(arena, refrs, clause);
+            }
+            true
+        });
+        Bdd::union(
+            arena,
+            Bdd::atom(
+                arena,
+                arena.alloc(Type {
+                    refrs: Bdd::bot(arena),
+                    ..*ty
+                }),
+            ),
+            refrs,
+        )
+    }
+
+    /// Whether a clause of references is empty because one of them is included in one that is
+    /// excluded, without unfolding them.
+    pub(crate) fn refr_clause_is_empty(
+        &self,
+        pos: &[&'a Refr<'a, C, Type<'a, C>>],
+        neg: &[&'a Refr<'a, C, Type<'a, C>>],
+    ) -> bool {
+        pos.iter()
+            .any(|p| neg.iter().any(|n| p == n || self.refines(p, n)))
+    }
+
+    /// Whether `r1` is a subtype of `r2` by comparing the arguments of references to the same
+    /// type according to the variances of its parameters.
+    pub fn refines(
+        &self,
+        r1: &'a Refr<'a, C, Type<'a, C>>,
+        r2: &'a Refr<'a, C, Type<'a, C>>,
+    ) -> bool {
+        if r1.id != r2.id || r1.args.len() != r2.args.len() {
+            return false;
+        }
+        let variances = self.defs.get(&r1.id).map(|def| &def.variances);
+        r1.args
+            .iter()
+            .zip(r2.args.iter())
+            .enumerate()
+            .all(|(i, (a1, a2))| {
+                match variances
+                    .and_then(|variances| variances.get(i))
+                    .unwrap_or(&Variance::Invariant)
+                {
+                    Variance::Bivariant => true,
+                    Variance::Covariant => self.is_subtype(a1, a2),
+                    Variance::Contravariant => self.is_subtype(a2, a1),
+                    Variance::Invariant => self.is_subtype(a1, a2) && self.is_subtype(a2, a1),
+                }
+            })
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{
+    bdd::{Arrow, Bdd, Product, Record, Refr, Type, Var},
+    defs::{Defs, Variance},
+    ty::TyConfig,
+};
+
+impl<'a, C> Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    /// Replaces the variables of the type with the types `f` returns for them, keeping those for
+    /// which it returns `None`.
+    pub fn subst<F>(arena: &'a bumpalo::Bump, bdd: &'a Self, f: &F) -> &'a Self
+    where
+        F: Fn(&'a Var<C>) -> Option<&'a Self>,
+    {
+        Self::subst_polar(arena, &Defs::new(), bdd, Variance::Covariant, &|var, _| {
+            f(var)
+        })
+    }
+
+    /// Replaces the variables like `subst`, giving `f` the variance of each occurrence, the type
+    /// being in a position of variance `variance`, so that covariant and contravariant
+    /// occurrences can be replaced differently. Arguments of references take the variances of
+    /// the parameters in `defs` into account, and are invariant if the type isn't defined.
+    pub fn subst_polar<F>(
+        arena: &'a bumpalo::Bump,
+        defs: &Defs<'a, C>,
+        bdd: &'a Self,
+        variance: Variance,
+        f: &F,
+    ) -> &'a Self
+    where
+        F: Fn(&'a Var<C>, Variance) -> Option<&'a Self>,
+    {
+        Bdd::map_polar(arena, bdd, true, &|ty, positive| {
+            let variance = if positive { variance } else { variance.flip() };
+            Type::subst_polar(arena, defs, ty, variance, f)
+        })
+    }
+}
+
+impl<'a, C> Type<'a, C>
+where
+    C: TyConfig,
+{
+    pub fn subst_polar<F>(
+        arena: &'a bumpalo::Bump,
+        defs: &Defs<'a, C>,
+        ty: &'a Self,
+        variance: Variance,
+        f: &F,
+    ) -> &'a Bdd<'a, C, Self>
+    where
+        F: Fn(&'a Var<C>, Variance) -> Option<&'a Bdd<'a, C, Self>>,
+    {
+        let subst = |bdd, variance| Bdd::subst_polar(arena, defs, bdd, variance, f);
+        let flip = |positive| if positive { variance } else { variance.flip() };
+
+        let vars = Bdd::map_polar(arena, ty.vars, true, &|var, positive| {
+            f(var, flip(positive)).unwrap_or_else(|| {
+                Bdd::atom(
+                    arena,
+                    arena.alloc(Type::from_vars(arena, Bdd::atom(arena, var))),
+                )
+            })
+        });
+        let products = Bdd::map_polar(arena, ty.products, true, &|Product(l, r), positive| {
+            let variance = flip(positive);
+            Bdd::atom(
+                arena,
+                arena.alloc(Product(subst(l, variance), subst(r, variance))),
+            )
+        });
+        let arrows = Bdd::map_polar(arena, ty.arrows, true, &|Arrow(d, c), positive| {
+            let variance = flip(positive);
+            Bdd::atom(
+                arena,
+                arena.alloc(Arrow(subst(d, variance.flip()), subst(c, variance))),
+            )
+        });
+        let records = Bdd::map_polar(arena, ty.records, true, &|rec, positive| {
+            let variance = flip(positive);
+            Bdd::atom(
+                arena,
+                arena.alloc(Record {
+                    map: bumpalo::collections::Vec::from_iter_in(
+                        rec.map
+                            .iter()
+                            .map(|(prop, ty)| (prop.clone(), subst(ty, variance))),
+                        arena,
+                    ),
+                    open: rec.open,
+                }),
+            )
+        });
+        let refrs = Bdd::map_polar(arena, ty.refrs, true, &|refr, positive| {
+            let variance = flip(positive);
+            let args = refr.args.iter().enumerate().map(|(i, arg)| {
+                let inner = defs
+                    .get(&refr.id)
+                    .and_then(|def| def.variances.get(i).copied())
+                    .unwrap_or(Variance::Invariant);
+                subst(arg, variance.compose(inner))
+            });
+            Bdd::atom(
+                arena,
+                arena.alloc(Refr {
+                    id: refr.id.clone(),
+                    args: bumpalo::collections::Vec::from_iter_in(args, arena),
+                }),
+            )
+        });
+
+        Bdd::union(
+            arena,
+            vars,
+            Bdd::atom(
+                arena,
+                arena.alloc(Type {
+                    vars: Bdd::bot(arena),
+                    products,
+                    arrows,
+                    records,
+                    refrs,
+                    ..*ty
+                }),
+            ),
+        )
+    }
+}
@@ -0,0 +1,334 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use crate::{
+    bdd::{Bdd, Solver, Type, Var},
+    ty::TyConfig,
+};
+
+/// A pair of types, as the bounds of a variable or the sides of a subtyping constraint.
+pub type TypePair<'a, C> = (&'a Bdd<'a, C, Type<'a, C>>, &'a Bdd<'a, C, Type<'a, C>>);
+
+/// Bounds `lower ≤ var ≤ upper` on some flexible variables, which must all hold.
+#[derive(Debug)]
+pub struct ConstraintSet<'a, C>
+where
+    C: TyConfig,
+{
+    bounds: BTreeMap<&'a C::Var, TypePair<'a, C>>,
+}
+impl<'a, C> Clone for ConstraintSet<'a, C>
+where
+    C: TyConfig,
+{
+    fn clone(&self) -> Self {
+        Self {
+            bounds: self.bounds.clone(),
+        }
+    }
+}
+impl<'a, C> ConstraintSet<'a, C>
+where
+    C: TyConfig,
+{
+    pub fn bounds(&self) -> impl Iterator<Item = (&'a C::Var, TypePair<'a, C>)> + '_ {
+        self.bounds.iter().map(|(var, bounds)| (*var, *bounds))
+    }
+
+    fn merge(&self, arena: &'a bumpalo::Bump, other: &Self) -> Self {
+        let mut bounds = self.bounds.clone();
+        for (var, (lower, upper)) in &other.bounds {
+            bounds
+                .entry(var)
+                .and_modify(|(l, u)| {
+                    *l = Bdd::union(arena, l, lower);
+                    *u = Bdd::inter(arena, u, upper);
+                })
+                .or_insert((lower, upper));
+        }
+        Self { bounds }
+    }
+}
+
+/// Alternative sets of constraints, one of which must hold.
+#[derive(Debug)]
+pub struct Constraints<'a, C>
+where
+    C: TyConfig,
+{
+    sets: Vec<ConstraintSet<'a, C>>,
+}
+impl<'a, C> Constraints<'a, C>
+where
+    C: TyConfig,
+{
+    /// The constraints that always hold.
+    pub fn sat() -> Self {
+        Self {
+            sets: vec![ConstraintSet {
+                bounds: BTreeMap::new(),
+            }],
+        }
+    }
+
+    /// The constraints that never hold.
+    pub fn unsat() -> Self {
+        Self { sets: Vec::new() }
+    }
+
+    pub fn single(
+        var: &'a C::Var,
+        lower: &'a Bdd<'a, C, Type<'a, C>>,
+        upper: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> Self {
+        Self {
+            sets: vec![ConstraintSet {
+                bounds: BTreeMap::from([(var, (lower, upper))]),
+            }],
+        }
+    }
+
+    pub fn is_sat(&self) -> bool {
+        self.sets.iter().any(|set| set.bounds.is_empty())
+    }
+
+    pub fn is_unsat(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    pub fn sets(&self) -> &[ConstraintSet<'a, C>] {
+        &self.sets
+    }
+
+    pub fn or(mut self, other: Self) -> Self {
+        if self.is_sat() || other.is_sat() {
+            return Self::sat();
+        }
+        self.sets.extend(other.sets);
+        self
+    }
+
+    pub fn and(self, arena: &'a bumpalo::Bump, other: &Self) -> Self {
+        if self.is_sat() {
+            return Self {
+                sets: other.sets.clone(),
+            };
+        }
+        Self {
+            sets: self
+                .sets
+                .iter()
+                .flat_map(|s1| other.sets.iter().map(|s2| s1.merge(arena, s2)))
+                .collect(),
+        }
+    }
+}
+
+/// A solution of a tallying problem, mapping flexible variables to types.
+#[derive(Debug)]
+pub struct Substitution<'a, C>
+where
+    C: TyConfig,
+{
+    map: BTreeMap<&'a C::Var, &'a Bdd<'a, C, Type<'a, C>>>,
+}
+impl<'a, C> Substitution<'a, C>
+where
+    C: TyConfig,
+{
+    pub fn get(&self, var: &C::Var) -> Option<&'a Bdd<'a, C, Type<'a, C>>> {
+        self.map.get(var).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'a C::Var, &'a Bdd<'a, C, Type<'a, C>>)> + '_ {
+        self.map.iter().map(|(var, ty)| (*var, *ty))
+    }
+
+    pub fn apply(
+        &self,
+        arena: &'a bumpalo::Bump,
+        ty: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> &'a Bdd<'a, C, Type<'a, C>> {
+        Bdd::subst(arena, ty, &|var| match var {
+            Var::Named(var) => self.get(var),
+            Var::Dyn => None,
+        })
+    }
+}
+
+/// Tallying, following Castagna et al., "Polymorphic Functions with Set-Theoretic Types": finds
+/// the substitutions of the flexible variables under which some subtyping constraints hold. The
+/// other variables are rigid, standing for any type.
+pub struct Tally<'s, 'd, 'a, C>
+where
+    C: TyConfig,
+{
+    solver: &'s Solver<'d, 'a, C>,
+    flexible: &'s dyn Fn(&C::Var) -> bool,
+    /// The types being normalized, which are assumed to be empty if they recur.
+    assumed: RefCell<Vec<&'a Bdd<'a, C, Type<'a, C>>>>,
+}
+impl<'s, 'd, 'a, C> Tally<'s, 'd, 'a, C>
+where
+    C: TyConfig,
+{
+    pub fn new(solver: &'s Solver<'d, 'a, C>, flexible: &'s dyn Fn(&C::Var) -> bool) -> Self {
+        Self {
+            solver,
+            flexible,
+            assumed: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn solver(&self) -> &'s Solver<'d, 'a, C> {
+        self.solver
+    }
+
+    /// The constraints on the flexible variables under which `ty` is empty. The smallest flexible
+    /// variable at the top level is isolated: `ty` is empty if `ty[var := 0] ≤ var ≤ ¬ty[var :=
+    /// 1]`. Without any, the other variables are eliminated clause by clause with
+    /// `Solver::all_assignments`, as in `Solver::is_empty`, and the constructors decomposed into
+    /// constraints on their components.
+    pub fn norm(&self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> Constraints<'a, C> {
+        let arena = self.solver.arena;
+        match ty {
+            Bdd::Top => return Constraints::unsat(),
+            Bdd::Bot => return Constraints::sat(),
+            Bdd::Atom { .. } => {}
+        }
+        if self.assumed.borrow().contains(&ty) {
+            return Constraints::sat();
+        }
+        let unfolded = self.solver.unfold(ty);
+        let vars = unfolded.top_vars();
+        let flexible = vars.iter().find_map(|var| match var {
+            Var::Named(v) if (self.flexible)(v) => Some((*var, v)),
+            _ => None,
+        });
+        if let Some((var, v)) = flexible {
+            return Constraints::single(
+                v,
+                Bdd::restrict(arena, unfolded, var, false),
+                Bdd::not(arena, Bdd::restrict(arena, unfolded, var, true)),
+            );
+        }
+        self.assumed.borrow_mut().push(ty);
+        let constraints = if vars.is_empty() {
+            self.norm_type(&Bdd::eliminate_vars(arena, unfolded, true, &|_, _| false))
+        } else {
+            let mut constraints = Constraints::sat();
+            unfolded.all_clauses(&mut |pos, neg| {
+                self.solver.all_assignments(pos, neg, &mut |kinds| {
+                    constraints = std::mem::replace(&mut constraints, Constraints::unsat())
+                        .and(arena, &self.norm_type(kinds));
+                    !constraints.is_unsat()
+                })
+            });
+            constraints
+        };
+        self.assumed.borrow_mut().pop();
+        constraints
+    }
+
+    fn norm_type(&self, ty: &Type<'a, C>) -> Constraints<'a, C> {
+        let arena = self.solver.arena;
+        if !ty.basics.is_empty() || !ty.ints.is_empty() {
+            return Constraints::unsat();
+        }
+        let kinds: [&dyn Fn() -> Constraints<'a, C>; 4] = [
+            &|| ty.products.tally(self),
+            &|| ty.arrows.tally(self),
+            &|| ty.records.tally(self),
+            &|| ty.refrs.tally(self),
+        ];
+        let mut constraints = Constraints::sat();
+        for kind in kinds {
+            constraints = constraints.and(arena, &kind());
+            if constraints.is_unsat() {
+                break;
+            }
+        }
+        constraints
+    }
+
+    /// The substitutions of the flexible variables under which `s ≤ t` for each pair `(s, t)`.
+    /// Each variable is replaced with `lower ∨ (fresh ∧ upper)`, where the fresh variable is made
+    /// by the context (see `Context::fresh_var`). Solutions that would require recursive types
+    /// aren't found.
+    pub fn solve(&self, constraints: &[TypePair<'a, C>]) -> Vec<Substitution<'a, C>> {
+        let arena = self.solver.arena;
+        let mut normalized = Constraints::sat();
+        for (s, t) in constraints {
+            normalized = normalized.and(arena, &self.norm(Bdd::diff(arena, s, t)));
+            if normalized.is_unsat() {
+                break;
+            }
+        }
+        normalized
+            .sets
+            .into_iter()
+            .flat_map(|set| self.saturate(set, &[]))
+            .filter_map(|set| self.solve_set(&set))
+            .collect()
+    }
+
+    /// A fresh variable for the solution of `var`.
+    fn fresh_var(&self, var: &C::Var) -> C::Var {
+        self.solver.ctx.fresh_var(&format!("{var:?}"))
+    }
+
+    /// Adds the constraints needed for the lower bound of each variable to be a subtype of its
+    /// upper bound, until there are no new ones.
+    fn saturate(
+        &self,
+        set: ConstraintSet<'a, C>,
+        done: &[&'a Bdd<'a, C, Type<'a, C>>],
+    ) -> Vec<ConstraintSet<'a, C>> {
+        let arena = self.solver.arena;
+        let pending = set.bounds.values().find_map(|(lower, upper)| {
+            let diff = Bdd::diff(arena, lower, upper);
+            (!done
+                .iter()
+                .any(|d| self.solver.is_subtype(diff, d) && self.solver.is_subtype(d, diff)))
+            .then_some(diff)
+        });
+        let Some(diff) = pending else {
+            return vec![set];
+        };
+        let done = [done, &[diff]].concat();
+        let set = Constraints { sets: vec![set] };
+        self.norm(diff)
+            .and(arena, &set)
+            .sets
+            .into_iter()
+            .flat_map(|set| self.saturate(set, &done))
+            .collect()
+    }
+
+    /// Solves the equations `var = lower ∨ (fresh ∧ upper)` by successive substitutions.
+    fn solve_set(&self, set: &ConstraintSet<'a, C>) -> Option<Substitution<'a, C>> {
+        let arena = self.solver.arena;
+        let mut map: BTreeMap<&'a C::Var, &'a Bdd<'a, C, Type<'a, C>>> = BTreeMap::new();
+        for (var, (lower, upper)) in set.bounds() {
+            let fresh = Bdd::atom(
+                arena,
+                arena.alloc(Type::from_vars(
+                    arena,
+                    Bdd::atom(arena, arena.alloc(Var::Named(self.fresh_var(var)))),
+                )),
+            );
+            let ty = Bdd::union(arena, lower, Bdd::inter(arena, fresh, upper));
+            let ty = Substitution { map: map.clone() }.apply(arena, ty);
+            if ty.free_vars().contains(&var) {
+                return None;
+            }
+            let solved = Substitution {
+                map: BTreeMap::from([(var, ty)]),
+            };
+            for solution in map.values_mut() {
+                *solution = solved.apply(arena, solution);
+            }
+            map.insert(var, ty);
+        }
+        Some(Substitution { map })
+    }
+}
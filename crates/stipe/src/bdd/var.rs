@@ -0,0 +1,70 @@
+use crate::{
+    bdd::{Bdd, TyAtom},
+    ty::TyConfig,
+};
+
+/// An atom of the variable component of a type: a type variable, or the unknown type `?` of
+/// gradual typing.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Var<C>
+where
+    C: TyConfig,
+{
+    Dyn,
+    Named(C::Var),
+}
+impl<C> TyAtom for Var<C> where C: TyConfig {}
+
+impl<'a, C> Bdd<'a, C, Var<C>>
+where
+    C: TyConfig,
+{
+    /// Evaluates the BDD once each variable occurrence has been decided by `f`, which is given
+    /// the polarity of the occurrence (`true` for positive).
+    pub fn eval<F>(&'a self, positive: bool, f: &F) -> bool
+    where
+        F: Fn(&'a Var<C>, bool) -> bool,
+    {
+        match self {
+            Self::Top => true,
+            Self::Bot => false,
+            Self::Atom {
+                atom, pos, lu, neg, ..
+            } => {
+                (f(atom, positive) && pos.eval(positive, f))
+                    || lu.eval(positive, f)
+                    || (!f(atom, !positive) && neg.eval(positive, f))
+            }
+        }
+    }
+
+    /// Evaluates the BDD under an assignment of some of its variables, or `None` if the result
+    /// depends on the others.
+    pub(crate) fn eval_partial(&'a self, assigned: &[(&Var<C>, bool)]) -> Option<bool> {
+        let or = |b1, b2| match (b1, b2) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        };
+        match self {
+            Self::Top => Some(true),
+            Self::Bot => Some(false),
+            Self::Atom {
+                atom, pos, lu, neg, ..
+            } => {
+                let lu = lu.eval_partial(assigned);
+                match assigned.iter().find(|(var, _)| var == atom) {
+                    Some((_, true)) => or(pos.eval_partial(assigned), lu),
+                    Some((_, false)) => or(lu, neg.eval_partial(assigned)),
+                    None => {
+                        let (pos, neg) = (
+                            or(pos.eval_partial(assigned), lu),
+                            or(lu, neg.eval_partial(assigned)),
+                        );
+                        (pos == neg).then_some(pos).flatten()
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,759 @@
+//! A binary format for interface files, holding a set of named type definitions. The atoms of the
+//! `TyConfig` are written as strings, in a string table, and the types as a table of nodes where
+//! each shared node appears once. Both tables are indexed by offsets, so that a reader can load a
+//! single definition, and the nodes it needs, without decoding the rest of the file.
+//!
+//! All the integers are little-endian. The file is made of:
+//! - the header: the magic bytes `STPI`, the version (`u16`), a reserved `u16`, and the numbers of
+//!   strings, nodes and definitions (`u32`s);
+//! - the offsets of the strings and of the nodes in their sections (`u32`s);
+//! - the definitions: the name (a string index), whether it's opaque (`u8`), the body (a node
+//!   index), and the number of parameters followed by their names (string indices);
+//! - the strings section, and the nodes section, each preceded by its length in bytes (`u32`).
+//!
+//! A node is a tag (`u8`) followed by its fields. Nodes only refer to nodes that come before
+//! them.
+
+use std::{collections::HashMap, fmt::Display, marker::PhantomData, ptr, str::FromStr};
+
+use crate::{
+    Context,
+    bdd::{Arrow, Basic, Bdd, Intervals, Openness, Product, Record, Refr, TyAtom, Type, Var},
+    defs::{Definition, Defs},
+    ty::TyConfig,
+};
+
+pub const MAGIC: [u8; 4] = *b"STPI";
+pub const VERSION: u16 = 1;
+
+/// A configuration whose atoms can be written to interface files, as strings.
+pub trait BinaryConfig:
+    TyConfig<
+        TyName: Display + FromStr,
+        Basic: Display + FromStr,
+        Literal: Display + FromStr,
+        Int: Into<i128> + TryFrom<i128>,
+        Var: Display + FromStr,
+        Prop: Display + FromStr,
+    >
+{
+}
+impl<C> BinaryConfig for C where
+    C: TyConfig<
+            TyName: Display + FromStr,
+            Basic: Display + FromStr,
+            Literal: Display + FromStr,
+            Int: Into<i128> + TryFrom<i128>,
+            Var: Display + FromStr,
+            Prop: Display + FromStr,
+        >
+{
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// The file ends in the middle of something.
+    Truncated,
+    /// A string isn't valid UTF-8, or isn't a valid atom.
+    BadString(u32),
+    /// A node is malformed, or refers to a node that doesn't exist or doesn't come before it.
+    BadNode(u32),
+    /// A node isn't of the kind expected where it's referenced.
+    BadKind(u32),
+}
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic => f.write_str("not an interface file"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported interface file version {version}")
+            }
+            Self::Truncated => f.write_str("the interface file is truncated"),
+            Self::BadString(index) => write!(f, "string {index} is invalid"),
+            Self::BadNode(index) => write!(f, "node {index} is malformed"),
+            Self::BadKind(index) => write!(f, "node {index} isn't of the expected kind"),
+        }
+    }
+}
+impl std::error::Error for FormatError {}
+
+const BOT: u8 = 0;
+const TOP: u8 = 1;
+const ATOM: u8 = 2;
+const TYPE: u8 = 3;
+const DYN: u8 = 4;
+const VAR: u8 = 5;
+const BASIC: u8 = 6;
+const LITERAL: u8 = 7;
+const INTS: u8 = 8;
+const PRODUCT: u8 = 9;
+const ARROW: u8 = 10;
+const RECORD: u8 = 11;
+const REFR: u8 = 12;
+
+/// Writes the definitions of `defs` in the binary format.
+pub fn write_defs<'a, C>(defs: &Defs<'a, C>) -> Vec<u8>
+where
+    C: BinaryConfig,
+{
+    let mut writer = Writer {
+        strings: Vec::new(),
+        string_indices: HashMap::new(),
+        nodes: Vec::new(),
+        seen: HashMap::new(),
+        bot: None,
+        top: None,
+        _c: PhantomData,
+    };
+    let mut entries = Vec::new();
+    for (name, def) in defs.iter() {
+        let name = writer.string(name);
+        let body = writer.bdd(def.body, Writer::type_atom);
+        let params: Vec<_> = def.params.iter().map(|p| writer.string(p)).collect();
+        entries.push((name, def.opaque, body, params));
+    }
+
+    let mut out = Vec::new();
+    out.extend(MAGIC);
+    out.extend(VERSION.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    for count in [writer.strings.len(), writer.nodes.len(), entries.len()] {
+        out.extend((count as u32).to_le_bytes());
+    }
+    let mut strings = Vec::new();
+    for string in &writer.strings {
+        out.extend((strings.len() as u32).to_le_bytes());
+        strings.extend((string.len() as u32).to_le_bytes());
+        strings.extend(string.as_bytes());
+    }
+    let mut nodes = Vec::new();
+    for node in &writer.nodes {
+        out.extend((nodes.len() as u32).to_le_bytes());
+        nodes.extend(node);
+    }
+    for (name, opaque, body, params) in entries {
+        out.extend(name.to_le_bytes());
+        out.push(opaque as u8);
+        out.extend(body.to_le_bytes());
+        out.extend((params.len() as u32).to_le_bytes());
+        for param in params {
+            out.extend(param.to_le_bytes());
+        }
+    }
+    for section in [strings, nodes] {
+        out.extend((section.len() as u32).to_le_bytes());
+        out.extend(section);
+    }
+    out
+}
+
+struct Writer<'a, C>
+where
+    C: TyConfig,
+{
+    strings: Vec<String>,
+    string_indices: HashMap<String, u32>,
+    /// The encoded nodes.
+    nodes: Vec<Vec<u8>>,
+    /// The indices of the nodes already encoded, by address.
+    seen: HashMap<*const (), u32>,
+    bot: Option<u32>,
+    top: Option<u32>,
+    _c: PhantomData<&'a C>,
+}
+impl<'a, C> Writer<'a, C>
+where
+    C: BinaryConfig,
+{
+    fn string(&mut self, value: &impl Display) -> u32 {
+        let string = value.to_string();
+        if let Some(index) = self.string_indices.get(&string) {
+            return *index;
+        }
+        let index = self.strings.len() as u32;
+        self.strings.push(string.clone());
+        self.string_indices.insert(string, index);
+        index
+    }
+
+    fn push(&mut self, tag: u8, fields: &[u32]) -> u32 {
+        let mut node = vec![tag];
+        for field in fields {
+            node.extend(field.to_le_bytes());
+        }
+        self.nodes.push(node);
+        (self.nodes.len() - 1) as u32
+    }
+
+    /// The index of the node for `value`, encoded by `encode` (after the nodes it refers to) if
+    /// it hasn't been yet.
+    fn shared<X>(&mut self, value: &'a X, encode: impl FnOnce(&mut Self) -> u32) -> u32 {
+        let key = ptr::from_ref(value).cast();
+        if let Some(index) = self.seen.get(&key) {
+            return *index;
+        }
+        let index = encode(self);
+        self.seen.insert(key, index);
+        index
+    }
+
+    fn bdd<T>(&mut self, bdd: &'a Bdd<'a, C, T>, atom: fn(&mut Self, &'a T) -> u32) -> u32
+    where
+        T: TyAtom,
+    {
+        match bdd {
+            Bdd::Bot => match self.bot {
+                Some(index) => index,
+                None => {
+                    let index = self.push(BOT, &[]);
+                    *self.bot.insert(index)
+                }
+            },
+            Bdd::Top => match self.top {
+                Some(index) => index,
+                None => {
+                    let index = self.push(TOP, &[]);
+                    *self.top.insert(index)
+                }
+            },
+            Bdd::Atom {
+                atom: a,
+                pos,
+                lu,
+                neg,
+                ..
+            } => self.shared(bdd, |this| {
+                let fields = [
+                    atom(this, a),
+                    this.bdd(pos, atom),
+                    this.bdd(lu, atom),
+                    this.bdd(neg, atom),
+                ];
+                this.push(ATOM, &fields)
+            }),
+        }
+    }
+
+    fn type_atom(&mut self, ty: &'a Type<'a, C>) -> u32 {
+        self.shared(ty, |this| {
+            let fields = [
+                this.bdd(ty.vars, |this, var| {
+                    this.shared(var, |this| match var {
+                        Var::Dyn => this.push(DYN, &[]),
+                        Var::Named(var) => {
+                            let var = this.string(var);
+                            this.push(VAR, &[var])
+                        }
+                    })
+                }),
+                this.bdd(ty.basics, |this, basic| {
+                    this.shared(basic, |this| match basic {
+                        Basic::All(basic) => {
+                            let basic = this.string(basic);
+                            this.push(BASIC, &[basic])
+                        }
+                        Basic::Literal(literal) => {
+                            let literal = this.string(literal);
+                            this.push(LITERAL, &[literal])
+                        }
+                    })
+                }),
+                this.shared(ty.ints, |this| {
+                    let mut node = vec![INTS];
+                    node.extend((ty.ints.ranges.len() as u32).to_le_bytes());
+                    for (lo, hi) in ty.ints.ranges.iter() {
+                        node.extend(Into::<i128>::into(*lo).to_le_bytes());
+                        node.extend(Into::<i128>::into(*hi).to_le_bytes());
+                    }
+                    this.nodes.push(node);
+                    (this.nodes.len() - 1) as u32
+                }),
+                this.bdd(ty.products, |this, product| {
+                    this.shared(product, |this| {
+                        let fields = [
+                            this.bdd(product.0, Self::type_atom),
+                            this.bdd(product.1, Self::type_atom),
+                        ];
+                        this.push(PRODUCT, &fields)
+                    })
+                }),
+                this.bdd(ty.arrows, |this, arrow| {
+                    this.shared(arrow, |this| {
+                        let fields = [
+                            this.bdd(arrow.0, Self::type_atom),
+                            this.bdd(arrow.1, Self::type_atom),
+                        ];
+                        this.push(ARROW, &fields)
+                    })
+                }),
+                this.bdd(ty.records, |this, record| {
+                    this.shared(record, |this| {
+                        let mut fields = vec![
+                            (record.open == Openness::Open) as u32,
+                            record.map.len() as u32,
+                        ];
+                        for (prop, ty) in record.map.iter() {
+                            fields.push(this.string(prop));
+                            fields.push(this.bdd(ty, Self::type_atom));
+                        }
+                        this.push(RECORD, &fields)
+                    })
+                }),
+                this.bdd(ty.refrs, |this, refr| {
+                    this.shared(refr, |this| {
+                        let mut fields = vec![this.string(&refr.id), refr.args.len() as u32];
+                        for arg in refr.args.iter() {
+                            fields.push(this.bdd(arg, Self::type_atom));
+                        }
+                        this.push(REFR, &fields)
+                    })
+                }),
+            ];
+            this.push(TYPE, &fields)
+        })
+    }
+}
+
+/// A reader of an interface file, which only decodes the header and the indices up front.
+pub struct InterfaceFile<'b, C>
+where
+    C: TyConfig,
+{
+    strings: &'b [u8],
+    string_offsets: Vec<u32>,
+    nodes: &'b [u8],
+    node_offsets: Vec<u32>,
+    defs: Vec<DefEntry>,
+    _c: PhantomData<C>,
+}
+
+struct DefEntry {
+    name: u32,
+    opaque: bool,
+    body: u32,
+    params: Vec<u32>,
+}
+
+/// A cursor over bytes, failing with `error` when they run out.
+struct Bytes<'b> {
+    bytes: &'b [u8],
+    error: FormatError,
+}
+impl<'b> Bytes<'b> {
+    fn take(&mut self, len: usize) -> Result<&'b [u8], FormatError> {
+        if self.bytes.len() < len {
+            return Err(self.error.clone());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, FormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, FormatError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, FormatError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i128(&mut self) -> Result<i128, FormatError> {
+        Ok(i128::from_le_bytes(self.take(16)?.try_into().unwrap()))
+    }
+}
+
+impl<'b, C> InterfaceFile<'b, C>
+where
+    C: BinaryConfig,
+{
+    pub fn parse(bytes: &'b [u8]) -> Result<Self, FormatError> {
+        let mut input = Bytes {
+            bytes,
+            error: FormatError::Truncated,
+        };
+        if input.take(4)? != MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+        let version = input.u16()?;
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        input.u16()?;
+        let (string_count, node_count, def_count) = (input.u32()?, input.u32()?, input.u32()?);
+        let string_offsets = (0..string_count)
+            .map(|_| input.u32())
+            .collect::<Result<_, _>>()?;
+        let node_offsets = (0..node_count)
+            .map(|_| input.u32())
+            .collect::<Result<_, _>>()?;
+        let mut defs = Vec::new();
+        for _ in 0..def_count {
+            let name = input.u32()?;
+            let opaque = input.u8()? != 0;
+            let body = input.u32()?;
+            let params = (0..input.u32()?)
+                .map(|_| input.u32())
+                .collect::<Result<_, _>>()?;
+            defs.push(DefEntry {
+                name,
+                opaque,
+                body,
+                params,
+            });
+        }
+        let len = input.u32()? as usize;
+        let strings = input.take(len)?;
+        let len = input.u32()? as usize;
+        let nodes = input.take(len)?;
+        Ok(Self {
+            strings,
+            string_offsets,
+            nodes,
+            node_offsets,
+            defs,
+            _c: PhantomData,
+        })
+    }
+
+    /// The names of the types defined in the file.
+    pub fn names(&self) -> Result<Vec<C::TyName>, FormatError> {
+        self.defs.iter().map(|def| self.atom(def.name)).collect()
+    }
+
+    /// Decodes the definition of `name` into the arena of `ctx` and adds it to `defs`, returning
+    /// whether the file defines it. Only the nodes of its body are decoded.
+    pub fn load<'a>(
+        &self,
+        ctx: &'a Context<C>,
+        defs: &mut Defs<'a, C>,
+        name: &C::TyName,
+    ) -> Result<bool, FormatError> {
+        let name = name.to_string();
+        for def in &self.defs {
+            if self.string(def.name)? == name {
+                defs.define_all([self.load_entry(&mut self.loader(ctx), def)?]);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Decodes all the definitions of the file into the arena of `ctx` and adds them to `defs`.
+    pub fn load_all<'a>(
+        &self,
+        ctx: &'a Context<C>,
+        defs: &mut Defs<'a, C>,
+    ) -> Result<(), FormatError> {
+        let mut loader = self.loader(ctx);
+        let entries = self
+            .defs
+            .iter()
+            .map(|def| self.load_entry(&mut loader, def));
+        defs.define_all(entries.collect::<Result<Vec<_>, _>>()?);
+        Ok(())
+    }
+
+    fn loader<'a>(&self, ctx: &'a Context<C>) -> Loader<'_, 'b, 'a, C> {
+        Loader {
+            file: self,
+            arena: &ctx.arena,
+            types: HashMap::new(),
+            type_atoms: HashMap::new(),
+            vars: HashMap::new(),
+            var_atoms: HashMap::new(),
+            basics: HashMap::new(),
+            basic_atoms: HashMap::new(),
+            ints: HashMap::new(),
+            products: HashMap::new(),
+            product_atoms: HashMap::new(),
+            arrows: HashMap::new(),
+            arrow_atoms: HashMap::new(),
+            records: HashMap::new(),
+            record_atoms: HashMap::new(),
+            refrs: HashMap::new(),
+            refr_atoms: HashMap::new(),
+        }
+    }
+
+    fn load_entry<'a>(
+        &self,
+        loader: &mut Loader<'_, 'b, 'a, C>,
+        def: &DefEntry,
+    ) -> Result<Definition<'a, C>, FormatError> {
+        let name = self.atom(def.name)?;
+        let params = def
+            .params
+            .iter()
+            .map(|param| self.atom(*param))
+            .collect::<Result<_, _>>()?;
+        Ok((name, params, loader.ty(def.body)?, def.opaque))
+    }
+
+    fn string(&self, index: u32) -> Result<&'b str, FormatError> {
+        let error = FormatError::BadString(index);
+        let offset = *self
+            .string_offsets
+            .get(index as usize)
+            .ok_or(error.clone())?;
+        let mut input = Bytes {
+            bytes: self.strings.get(offset as usize..).ok_or(error.clone())?,
+            error: error.clone(),
+        };
+        let len = input.u32()? as usize;
+        std::str::from_utf8(input.take(len)?).map_err(|_| error)
+    }
+
+    fn atom<A: FromStr>(&self, index: u32) -> Result<A, FormatError> {
+        self.string(index)?
+            .parse()
+            .map_err(|_| FormatError::BadString(index))
+    }
+
+    /// The tag of the node at `index` and its fields, which must only refer to nodes before it.
+    fn node(&self, index: u32) -> Result<(u8, Bytes<'b>), FormatError> {
+        let error = FormatError::BadNode(index);
+        let offset = *self.node_offsets.get(index as usize).ok_or(error.clone())?;
+        let mut input = Bytes {
+            bytes: self.nodes.get(offset as usize..).ok_or(error.clone())?,
+            error,
+        };
+        Ok((input.u8()?, input))
+    }
+
+    /// Reads the index of a node referred to by the node at `parent`.
+    fn child(&self, input: &mut Bytes<'b>, parent: u32) -> Result<u32, FormatError> {
+        let child = input.u32()?;
+        if child >= parent {
+            return Err(FormatError::BadNode(parent));
+        }
+        Ok(child)
+    }
+}
+
+/// The BDDs of some kind already decoded, by index.
+type Memo<'a, C, T> = HashMap<u32, &'a Bdd<'a, C, T>>;
+
+/// The atoms of some kind already decoded, by index.
+type Atoms<'a, T> = HashMap<u32, &'a T>;
+
+struct Loader<'f, 'b, 'a, C>
+where
+    C: TyConfig,
+{
+    file: &'f InterfaceFile<'b, C>,
+    arena: &'a bumpalo::Bump,
+    types: Memo<'a, C, Type<'a, C>>,
+    type_atoms: Atoms<'a, Type<'a, C>>,
+    vars: Memo<'a, C, Var<C>>,
+    var_atoms: Atoms<'a, Var<C>>,
+    basics: Memo<'a, C, Basic<C>>,
+    basic_atoms: Atoms<'a, Basic<C>>,
+    ints: Atoms<'a, Intervals<'a, C>>,
+    products: Memo<'a, C, Product<'a, C, Type<'a, C>>>,
+    product_atoms: Atoms<'a, Product<'a, C, Type<'a, C>>>,
+    arrows: Memo<'a, C, Arrow<'a, C, Type<'a, C>>>,
+    arrow_atoms: Atoms<'a, Arrow<'a, C, Type<'a, C>>>,
+    records: Memo<'a, C, Record<'a, C, Type<'a, C>>>,
+    record_atoms: Atoms<'a, Record<'a, C, Type<'a, C>>>,
+    refrs: Memo<'a, C, Refr<'a, C, Type<'a, C>>>,
+    refr_atoms: Atoms<'a, Refr<'a, C, Type<'a, C>>>,
+}
+impl<'f, 'b, 'a, C> Loader<'f, 'b, 'a, C>
+where
+    C: BinaryConfig,
+{
+    fn bdd<T>(
+        &mut self,
+        index: u32,
+        memo: fn(&mut Self) -> &mut Memo<'a, C, T>,
+        atom: fn(&mut Self, u32) -> Result<&'a T, FormatError>,
+    ) -> Result<&'a Bdd<'a, C, T>, FormatError>
+    where
+        T: TyAtom,
+    {
+        if let Some(bdd) = memo(self).get(&index) {
+            return Ok(bdd);
+        }
+        let (tag, mut input) = self.file.node(index)?;
+        let bdd = match tag {
+            BOT => Bdd::bot(self.arena),
+            TOP => Bdd::top(self.arena),
+            ATOM => {
+                let mut child = || self.file.child(&mut input, index);
+                let (a, pos, lu, neg) = (child()?, child()?, child()?, child()?);
+                &*self.arena.alloc(Bdd::Atom {
+                    atom: atom(self, a)?,
+                    pos: self.bdd(pos, memo, atom)?,
+                    lu: self.bdd(lu, memo, atom)?,
+                    neg: self.bdd(neg, memo, atom)?,
+                    _c: PhantomData,
+                })
+            }
+            _ => return Err(FormatError::BadKind(index)),
+        };
+        memo(self).insert(index, bdd);
+        Ok(bdd)
+    }
+
+    fn ty(&mut self, index: u32) -> Result<&'a Bdd<'a, C, Type<'a, C>>, FormatError> {
+        self.bdd(index, |l| &mut l.types, Self::type_atom)
+    }
+
+    /// The node at `index`, which must have tag `tag`.
+    fn expect(&self, index: u32, tag: u8) -> Result<Bytes<'b>, FormatError> {
+        match self.file.node(index)? {
+            (t, input) if t == tag => Ok(input),
+            _ => Err(FormatError::BadKind(index)),
+        }
+    }
+
+    /// The atom at `index`, decoded by `load` the first time it's referenced, so that atoms
+    /// shared by several BDDs stay shared.
+    fn atom<T>(
+        &mut self,
+        index: u32,
+        memo: fn(&mut Self) -> &mut Atoms<'a, T>,
+        load: fn(&mut Self, u32) -> Result<T, FormatError>,
+    ) -> Result<&'a T, FormatError> {
+        if let Some(atom) = memo(self).get(&index) {
+            return Ok(atom);
+        }
+        let atom = &*self.arena.alloc(load(self, index)?);
+        memo(self).insert(index, atom);
+        Ok(atom)
+    }
+
+    fn type_atom(&mut self, index: u32) -> Result<&'a Type<'a, C>, FormatError> {
+        self.atom(index, |l| &mut l.type_atoms, Self::load_type)
+    }
+
+    fn load_type(&mut self, index: u32) -> Result<Type<'a, C>, FormatError> {
+        let mut input = self.expect(index, TYPE)?;
+        let mut fields = [0; 7];
+        for field in &mut fields {
+            *field = self.file.child(&mut input, index)?;
+        }
+        let [vars, basics, ints, products, arrows, records, refrs] = fields;
+        Ok(Type {
+            vars: self.bdd(vars, |l| &mut l.vars, Self::var)?,
+            basics: self.bdd(basics, |l| &mut l.basics, Self::basic)?,
+            ints: self.ints(ints)?,
+            products: self.bdd(products, |l| &mut l.products, Self::product)?,
+            arrows: self.bdd(arrows, |l| &mut l.arrows, Self::arrow)?,
+            records: self.bdd(records, |l| &mut l.records, Self::record)?,
+            refrs: self.bdd(refrs, |l| &mut l.refrs, Self::refr)?,
+            _c: PhantomData,
+        })
+    }
+
+    fn var(&mut self, index: u32) -> Result<&'a Var<C>, FormatError> {
+        self.atom(
+            index,
+            |l| &mut l.var_atoms,
+            |l, index| match l.file.node(index)? {
+                (DYN, _) => Ok(Var::Dyn),
+                (VAR, mut input) => Ok(Var::Named(l.file.atom(input.u32()?)?)),
+                _ => Err(FormatError::BadKind(index)),
+            },
+        )
+    }
+
+    fn basic(&mut self, index: u32) -> Result<&'a Basic<C>, FormatError> {
+        self.atom(
+            index,
+            |l| &mut l.basic_atoms,
+            |l, index| match l.file.node(index)? {
+                (BASIC, mut input) => Ok(Basic::All(l.file.atom(input.u32()?)?)),
+                (LITERAL, mut input) => Ok(Basic::Literal(l.file.atom(input.u32()?)?)),
+                _ => Err(FormatError::BadKind(index)),
+            },
+        )
+    }
+
+    fn ints(&mut self, index: u32) -> Result<&'a Intervals<'a, C>, FormatError> {
+        if let Some(ints) = self.ints.get(&index) {
+            return Ok(ints);
+        }
+        let mut input = self.expect(index, INTS)?;
+        let bound = |int: i128| C::Int::try_from(int).map_err(|_| FormatError::BadNode(index));
+        let mut ranges = Vec::new();
+        for _ in 0..input.u32()? {
+            ranges.push((bound(input.i128()?)?, bound(input.i128()?)?));
+        }
+        let ints = Intervals::from_ranges(self.arena, ranges);
+        self.ints.insert(index, ints);
+        Ok(ints)
+    }
+
+    fn product(&mut self, index: u32) -> Result<&'a Product<'a, C, Type<'a, C>>, FormatError> {
+        self.atom(
+            index,
+            |l| &mut l.product_atoms,
+            |l, index| {
+                let mut input = l.expect(index, PRODUCT)?;
+                let (left, right) = (
+                    l.file.child(&mut input, index)?,
+                    l.file.child(&mut input, index)?,
+                );
+                Ok(Product(l.ty(left)?, l.ty(right)?))
+            },
+        )
+    }
+
+    fn arrow(&mut self, index: u32) -> Result<&'a Arrow<'a, C, Type<'a, C>>, FormatError> {
+        self.atom(
+            index,
+            |l| &mut l.arrow_atoms,
+            |l, index| {
+                let mut input = l.expect(index, ARROW)?;
+                let (d, c) = (
+                    l.file.child(&mut input, index)?,
+                    l.file.child(&mut input, index)?,
+                );
+                Ok(Arrow(l.ty(d)?, l.ty(c)?))
+            },
+        )
+    }
+
+    fn record(&mut self, index: u32) -> Result<&'a Record<'a, C, Type<'a, C>>, FormatError> {
+        self.atom(index, |l| &mut l.record_atoms, Self::load_record)
+    }
+
+    fn load_record(&mut self, index: u32) -> Result<Record<'a, C, Type<'a, C>>, FormatError> {
+        let mut input = self.expect(index, RECORD)?;
+        let open = match input.u32()? {
+            0 => Openness::Closed,
+            _ => Openness::Open,
+        };
+        let len = input.u32()?;
+        let mut map = bumpalo::collections::Vec::new_in(self.arena);
+        for _ in 0..len {
+            let prop = self.file.atom(input.u32()?)?;
+            let ty = self.file.child(&mut input, index)?;
+            map.push((prop, self.ty(ty)?));
+        }
+        Ok(Record { map, open })
+    }
+
+    fn refr(&mut self, index: u32) -> Result<&'a Refr<'a, C, Type<'a, C>>, FormatError> {
+        self.atom(index, |l| &mut l.refr_atoms, Self::load_refr)
+    }
+
+    fn load_refr(&mut self, index: u32) -> Result<Refr<'a, C, Type<'a, C>>, FormatError> {
+        let mut input = self.expect(index, REFR)?;
+        let id = self.file.atom(input.u32()?)?;
+        let len = input.u32()?;
+        let mut args = bumpalo::collections::Vec::new_in(self.arena);
+        for _ in 0..len {
+            let arg = self.file.child(&mut input, index)?;
+            args.push(self.ty(arg)?);
+        }
+        Ok(Refr { id, args })
+    }
+}
@@ -0,0 +1,525 @@
+//! Canonical forms of types. BDDs aren't canonical: the same set of values has many
+//! representations, depending on how it was built. A `Canonicalizer` rebuilds a type from a
+//! description of its values that doesn't depend on how the type was built, so that comparing
+//! canonical forms with `==` decides equivalence of types without arrows or references. For
+//! those, equal canonical forms are still equivalent, but equivalent ones may differ, so
+//! comparing them is only a quick check before `Solver::is_subtype`. Canonical forms are a
+//! function of the type alone: no state is shared between calls.
+
+use std::marker::PhantomData;
+
+use crate::{
+    bdd::{Arrow, Basic, Bdd, Intervals, Openness, Product, Record, Refr, Solver, TyAtom, Type},
+    ty::TyConfig,
+};
+
+type Ty<'a, C> = Bdd<'a, C, Type<'a, C>>;
+type Records<'a, C> = Bdd<'a, C, Record<'a, C, Type<'a, C>>>;
+
+/// The possible contents of a record field: a value of some type, or the field being absent.
+type Field<'a, C> = (&'a Ty<'a, C>, bool);
+
+pub struct Canonicalizer<'s, 'd, 'a, C>
+where
+    C: TyConfig,
+{
+    solver: &'s Solver<'d, 'a, C>,
+}
+impl<'s, 'd, 'a, C> Canonicalizer<'s, 'd, 'a, C>
+where
+    C: TyConfig,
+{
+    pub fn new(solver: &'s Solver<'d, 'a, C>) -> Self {
+        Self { solver }
+    }
+
+    /// The canonical form of `ty`. The empty and top types are `Bot` and `Top`. A type with
+    /// top-level variables is split on its first variable into the canonical forms of the type
+    /// with the variable replaced by the top and the empty types, as in an ordered BDD. The
+    /// other types are a single atom whose components are canonical:
+    ///
+    /// - basics list, for each basic type that isn't like the unmentioned ones, the literals
+    ///   that aren't like the rest of the basic type;
+    /// - products pair each set of left components with the right components they have;
+    /// - records do the same for each property that isn't like the unmentioned ones, in order,
+    ///   the last component being whether the other properties are all absent;
+    /// - arrows and references are unions of clauses whose atoms are canonical, without empty,
+    ///   redundant or subsumed parts, and the positive arrows of a clause map disjoint domains.
+    ///
+    /// Equivalent types have the same canonical form, except for arrows and references, where
+    /// the same set of values may still be a union or intersection of different atoms.
+    pub fn canonicalize(&self, ty: &'a Ty<'a, C>) -> &'a Ty<'a, C> {
+        let arena = self.solver.arena;
+        if self.solver.is_empty(ty) {
+            return Bdd::bot(arena);
+        }
+        if self.solver.is_subtype(Bdd::top(arena), ty) {
+            return Bdd::top(arena);
+        }
+
+        if let Some(var) = ty.top_vars().first() {
+            let with = self.canonicalize(Bdd::restrict(arena, ty, var, true));
+            let without = self.canonicalize(Bdd::restrict(arena, ty, var, false));
+            if with == without {
+                return with;
+            }
+            let var = Type::from_vars(arena, Bdd::atom(arena, *var));
+            let var = Bdd::atom(arena, arena.alloc(var));
+            return Bdd::union(
+                arena,
+                Bdd::inter(arena, var, with),
+                Bdd::diff(arena, without, var),
+            );
+        }
+
+        let flat = Bdd::flatten(arena, ty);
+        let (basics, ints) = self.basics(flat.basics);
+        Bdd::atom(
+            arena,
+            arena.alloc(Type {
+                vars: Bdd::bot(arena),
+                basics,
+                ints: Intervals::union(arena, flat.ints, ints),
+                products: self.products(flat.products),
+                arrows: self.arrows(flat.arrows),
+                records: self.records(flat.records),
+                refrs: self.refrs(flat.refrs),
+                _c: PhantomData,
+            }),
+        )
+    }
+
+    /// The canonical basics, and the integers they contain with `TyConfig::INTERVALS`, which
+    /// are left to the interval component.
+    fn basics(
+        &self,
+        basics: &'a Bdd<'a, C, Basic<C>>,
+    ) -> (&'a Bdd<'a, C, Basic<C>>, &'a Intervals<'a, C>) {
+        let arena = self.solver.arena;
+        let (_, ints) = basics.split_ints(arena);
+        let is_int = |atom: &Basic<C>| C::INTERVALS && C::int_basic(&atom.basic());
+
+        let mut atoms = Vec::new();
+        basics.for_each_atom(&mut |atom| {
+            if !is_int(atom) {
+                atoms.push(atom);
+            }
+        });
+        let mut mentioned: Vec<C::Basic> = atoms.iter().map(|a| a.basic().into_owned()).collect();
+        mentioned.sort();
+        mentioned.dedup();
+
+        // Whether the values of no mentioned basic type are included.
+        let others = eval(basics, &|_| false);
+        let mut kept = Bdd::bot(arena);
+        let mut parts = Bdd::bot(arena);
+        for basic in mentioned {
+            let all = Bdd::atom(arena, arena.alloc(Basic::All(basic.clone())));
+            let includes = |literal: Option<&C::Literal>| {
+                eval(basics, &|atom| match atom {
+                    Basic::All(b) => *b == basic,
+                    Basic::Literal(l) => Some(l) == literal,
+                })
+            };
+            let finite = C::basic_literals(&basic);
+            let is_finite = finite.is_some();
+            let rest = !is_finite && includes(None);
+            let mut literals = finite.unwrap_or_else(|| {
+                let literals = atoms.iter().filter_map(|atom| match atom {
+                    Basic::Literal(l) if C::literal_basic(l) == basic => Some(l.clone()),
+                    _ => None,
+                });
+                literals.collect()
+            });
+            literals.sort();
+            literals.dedup();
+            let (included, excluded): (Vec<_>, Vec<_>) =
+                literals.into_iter().partition(|l| includes(Some(l)));
+
+            let literal = |l| Bdd::atom(arena, arena.alloc(Basic::Literal(l)));
+            let part = if excluded.is_empty() && (rest || is_finite && !included.is_empty()) {
+                all
+            } else if rest {
+                let excluded = excluded.into_iter().map(literal);
+                excluded.fold(all, |acc, l| Bdd::diff(arena, acc, l))
+            } else {
+                let included = included.into_iter().map(literal);
+                included.fold(Bdd::bot(arena), |acc, l| Bdd::union(arena, acc, l))
+            };
+            let uniform = match part {
+                Bdd::Bot => Some(false),
+                _ if part == all => Some(true),
+                _ => None,
+            };
+            if uniform != Some(others) {
+                kept = Bdd::union(arena, kept, all);
+                parts = Bdd::union(arena, parts, part);
+            }
+        }
+        let basics = match others {
+            true => Bdd::union(arena, Bdd::not(arena, kept), parts),
+            false => parts,
+        };
+        (basics, ints)
+    }
+
+    /// The canonical products: for each set of right components, the set of left components
+    /// that have exactly them, found from the regions delimited by the left components.
+    fn products(
+        &self,
+        products: &'a Bdd<'a, C, Product<'a, C, Type<'a, C>>>,
+    ) -> &'a Bdd<'a, C, Product<'a, C, Type<'a, C>>> {
+        let arena = self.solver.arena;
+        let mut lefts = Vec::new();
+        products.for_each_atom(&mut |Product(l, _)| lefts.push(*l));
+
+        let mut groups: Vec<(&'a Ty<'a, C>, &'a Ty<'a, C>)> = Vec::new();
+        for region in self.regions(&lefts) {
+            let applies = |l| self.solver.is_subtype(region, l);
+            let right = Bdd::map_polar(arena, products, true, &|Product(l, r), _| {
+                if applies(l) { r } else { Bdd::bot(arena) }
+            });
+            let right = self.canonicalize(right);
+            if *right != Bdd::Bot {
+                group(arena, &mut groups, right, region);
+            }
+        }
+        let mut pairs: Vec<_> = groups
+            .into_iter()
+            .map(|(r, l)| (self.canonicalize(l), r))
+            .collect();
+        pairs.sort();
+        pairs.into_iter().fold(Bdd::bot(arena), |acc, (l, r)| {
+            Bdd::union(arena, acc, Bdd::atom(arena, arena.alloc(Product(l, r))))
+        })
+    }
+
+    /// The nonempty regions of values delimited by `tys`: each region is either contained in
+    /// or disjoint from each type. There are up to `2^n` regions for `n` distinct types, with an
+    /// emptiness check for each candidate, so this is only cheap for components with few atoms
+    /// or mostly disjoint ones, whose empty regions are dropped as they're found.
+    fn regions(&self, tys: &[&'a Ty<'a, C>]) -> Vec<&'a Ty<'a, C>> {
+        let arena = self.solver.arena;
+        let mut tys = tys.to_vec();
+        tys.sort();
+        tys.dedup();
+        tys.iter().fold(vec![Bdd::top(arena)], |regions, ty| {
+            regions
+                .into_iter()
+                .flat_map(|region| [Bdd::inter(arena, region, ty), Bdd::diff(arena, region, ty)])
+                .filter(|region| !self.solver.is_empty(region))
+                .collect()
+        })
+    }
+
+    /// The canonical arrows: the positive arrows of each clause are replaced by arrows with
+    /// disjoint domains, grouped by the intersection of the codomains that apply to them.
+    fn arrows(
+        &self,
+        arrows: &'a Bdd<'a, C, Arrow<'a, C, Type<'a, C>>>,
+    ) -> &'a Bdd<'a, C, Arrow<'a, C, Type<'a, C>>> {
+        let arena = self.solver.arena;
+        self.clauses(
+            arrows,
+            &|bdd| bdd.is_empty(self.solver),
+            &|Arrow(d, c)| arena.alloc(Arrow(self.canonicalize(d), self.canonicalize(c))),
+            &|pos| {
+                let doms: Vec<_> = pos.iter().map(|Arrow(d, _)| *d).collect();
+                let mut groups = Vec::new();
+                for region in self.regions(&doms) {
+                    let applied = pos
+                        .iter()
+                        .filter(|Arrow(d, _)| self.solver.is_subtype(region, d));
+                    let cod = applied.fold(None, |acc, Arrow(_, c)| match acc {
+                        None => Some(*c),
+                        Some(acc) => Some(Bdd::inter(arena, acc, c)),
+                    });
+                    if let Some(cod) = cod {
+                        group(arena, &mut groups, self.canonicalize(cod), region);
+                    }
+                }
+                let groups = groups.into_iter();
+                let arrows = groups.map(|(c, d)| &*arena.alloc(Arrow(self.canonicalize(d), c)));
+                arrows.collect()
+            },
+        )
+    }
+
+    /// The canonical references, whose arguments are canonical.
+    fn refrs(
+        &self,
+        refrs: &'a Bdd<'a, C, Refr<'a, C, Type<'a, C>>>,
+    ) -> &'a Bdd<'a, C, Refr<'a, C, Type<'a, C>>> {
+        let arena = self.solver.arena;
+        self.clauses(
+            refrs,
+            &|bdd| bdd.is_empty(self.solver),
+            &|Refr { id, args }| {
+                let args = args.iter().map(|arg| self.canonicalize(arg));
+                arena.alloc(Refr {
+                    id: id.clone(),
+                    args: bumpalo::collections::Vec::from_iter_in(args, arena),
+                })
+            },
+            &|pos| pos.to_vec(),
+        )
+    }
+
+    /// Normalizes the clauses of `bdd`: the atoms are made canonical by `atom` and the positive
+    /// ones are rewritten by `pos`, negative atoms that don't intersect the positive ones are
+    /// dropped, and so are empty clauses and clauses contained in another one. The remaining
+    /// clauses are sorted.
+    fn clauses<T, E, A, P>(
+        &self,
+        bdd: &'a Bdd<'a, C, T>,
+        is_empty: &E,
+        atom: &A,
+        pos: &P,
+    ) -> &'a Bdd<'a, C, T>
+    where
+        T: TyAtom,
+        E: Fn(&'a Bdd<'a, C, T>) -> bool,
+        A: Fn(&'a T) -> &'a T,
+        P: Fn(&[&'a T]) -> Vec<&'a T>,
+    {
+        let arena = self.solver.arena;
+        let mut clauses = Vec::new();
+        bdd.all_clauses(&mut |p, n| {
+            if is_empty(Bdd::clause(arena, p, n)) {
+                return true;
+            }
+            let p: Vec<_> = p.iter().map(|a| atom(a)).collect();
+            let mut p = pos(&p);
+            p.sort();
+            p.dedup();
+            let positive = Bdd::clause(arena, &p, &[]);
+            let mut n: Vec<_> = n.iter().map(|a| atom(a)).collect();
+            n.retain(|a| !is_empty(Bdd::inter(arena, positive, Bdd::atom(arena, a))));
+            n.sort();
+            n.dedup();
+            clauses.push((p, n));
+            true
+        });
+        clauses.sort();
+        clauses.dedup();
+
+        let clauses: Vec<_> = clauses
+            .iter()
+            .map(|(p, n)| Bdd::clause(arena, p, n))
+            .collect();
+        let contains = |c1, c2| is_empty(Bdd::diff(arena, c2, c1));
+        // Of equivalent clauses, only the first is kept.
+        let kept = clauses.iter().enumerate().filter(|(i, c)| {
+            !clauses.iter().enumerate().any(|(j, other)| {
+                j != *i && contains(*other, **c) && (j < *i || !contains(**c, *other))
+            })
+        });
+        kept.fold(Bdd::bot(arena), |acc, (_, c)| Bdd::union(arena, acc, c))
+    }
+
+    /// The canonical records. Properties that are constrained like the unmentioned ones are
+    /// dropped, then the records are split on the first property: each set of records for the
+    /// other properties is paired with the fields that have exactly them.
+    fn records(&self, records: &'a Records<'a, C>) -> &'a Records<'a, C> {
+        let arena = self.solver.arena;
+        let mut props = Vec::new();
+        records.for_each_atom(&mut |rec| props.extend(rec.map.iter().map(|(p, _)| p)));
+        props.sort();
+        props.dedup();
+
+        let equivalent = |r1: &'a Records<'a, C>, r2| {
+            Bdd::diff(arena, r1, r2).is_empty(self.solver)
+                && Bdd::diff(arena, r2, r1).is_empty(self.solver)
+        };
+        let mut records = records;
+        props.retain(|prop| {
+            let without = Bdd::map_polar(arena, records, true, &|rec, _| {
+                Bdd::atom(arena, without_prop(arena, rec, prop))
+            });
+            let keep = !equivalent(records, without);
+            if !keep {
+                records = without;
+            }
+            keep
+        });
+
+        let Some(prop) = props.first() else {
+            // Only the empty record and the records with other properties are told apart.
+            let empty = eval(records, &|_| true);
+            let others = eval(records, &|rec| rec.open == Openness::Open);
+            let open = Bdd::atom(arena, empty_record(arena, Openness::Open));
+            let closed = Bdd::atom(arena, empty_record(arena, Openness::Closed));
+            return match (empty, others) {
+                (true, true) => open,
+                (true, false) => closed,
+                (false, true) => Bdd::diff(arena, open, closed),
+                (false, false) => Bdd::bot(arena),
+            };
+        };
+
+        let field = |rec: &Record<'a, C, Type<'a, C>>| match rec.get(prop) {
+            Some(ty) => (ty, false),
+            None if rec.open == Openness::Open => (Bdd::top(arena), true),
+            None => (Bdd::bot(arena), true),
+        };
+        let contains = |(t1, a1): Field<'a, C>, (t2, a2): Field<'a, C>| {
+            self.solver.is_subtype(t2, t1) && (a1 || !a2)
+        };
+        let mut fields = Vec::new();
+        records.for_each_atom(&mut |rec| fields.push(field(rec)));
+        fields.sort();
+        fields.dedup();
+        let regions = fields
+            .iter()
+            .fold(vec![(Bdd::top(arena), true)], |regions, (t, a)| {
+                regions
+                    .into_iter()
+                    .flat_map(|(rt, ra)| {
+                        [
+                            (Bdd::inter(arena, rt, t), ra && *a),
+                            (Bdd::diff(arena, rt, t), ra && !*a),
+                        ]
+                    })
+                    .filter(|(t, a)| *a || !self.solver.is_empty(t))
+                    .collect::<Vec<_>>()
+            });
+
+        let mut groups: Vec<(&'a Records<'a, C>, Field<'a, C>)> = Vec::new();
+        for region in regions {
+            let rest = Bdd::map_polar(arena, records, true, &|rec, _| match contains(
+                field(rec),
+                region,
+            ) {
+                true => Bdd::atom(arena, without_prop(arena, rec, prop)),
+                false => Bdd::bot(arena),
+            });
+            let rest = self.records(rest);
+            if *rest == Bdd::Bot {
+                continue;
+            }
+            match groups.iter_mut().find(|(r, _)| *r == rest) {
+                Some((_, (t, a))) => {
+                    *t = Bdd::union(arena, t, region.0);
+                    *a |= region.1;
+                }
+                None => groups.push((rest, region)),
+            }
+        }
+        let mut groups: Vec<_> = groups
+            .into_iter()
+            .map(|(rest, (t, a))| ((self.canonicalize(t), a), rest))
+            .collect();
+        groups.sort();
+
+        let open = |map: Vec<_>| {
+            let rec = Record {
+                map: bumpalo::collections::Vec::from_iter_in(map, arena),
+                open: Openness::Open,
+            };
+            Bdd::atom(arena, &*arena.alloc(rec))
+        };
+        groups
+            .into_iter()
+            .fold(Bdd::bot(arena), |acc, ((t, a), rest)| {
+                let present = match t {
+                    Bdd::Bot => Bdd::bot(arena),
+                    _ => open(vec![((*prop).clone(), t)]),
+                };
+                let absent = match a {
+                    true => Bdd::diff(
+                        arena,
+                        open(vec![]),
+                        open(vec![((*prop).clone(), Bdd::top(arena))]),
+                    ),
+                    false => Bdd::bot(arena),
+                };
+                // The other records, whatever the property.
+                let rest = Bdd::map_polar(arena, rest, true, &|rec, _| {
+                    let atom = Bdd::atom(arena, rec);
+                    match rec.open {
+                        Openness::Open => atom,
+                        Openness::Closed => {
+                            let with = with_prop(arena, rec, prop, Bdd::top(arena));
+                            Bdd::union(arena, atom, Bdd::atom(arena, with))
+                        }
+                    }
+                });
+                let records = Bdd::inter(arena, Bdd::union(arena, present, absent), rest);
+                Bdd::union(arena, acc, records)
+            })
+    }
+}
+
+/// Evaluates a BDD once each atom has been decided by `f`.
+fn eval<C, T>(bdd: &Bdd<'_, C, T>, f: &dyn Fn(&T) -> bool) -> bool
+where
+    C: TyConfig,
+    T: TyAtom,
+{
+    match bdd {
+        Bdd::Top => true,
+        Bdd::Bot => false,
+        Bdd::Atom {
+            atom, pos, lu, neg, ..
+        } => eval(lu, f) || if f(atom) { eval(pos, f) } else { eval(neg, f) },
+    }
+}
+
+/// Adds `ty` to the group of `key` in `groups`, or to a new group.
+fn group<'a, C, K>(
+    arena: &'a bumpalo::Bump,
+    groups: &mut Vec<(K, &'a Ty<'a, C>)>,
+    key: K,
+    ty: &'a Ty<'a, C>,
+) where
+    C: TyConfig,
+    K: PartialEq,
+{
+    match groups.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, tys)) => *tys = Bdd::union(arena, tys, ty),
+        None => groups.push((key, ty)),
+    }
+}
+
+fn empty_record<'a, C>(arena: &'a bumpalo::Bump, open: Openness) -> &'a Record<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    arena.alloc(Record {
+        map: bumpalo::collections::Vec::new_in(arena),
+        open,
+    })
+}
+
+fn without_prop<'a, C>(
+    arena: &'a bumpalo::Bump,
+    rec: &Record<'a, C, Type<'a, C>>,
+    prop: &C::Prop,
+) -> &'a Record<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    let map = rec.map.iter().filter(|(p, _)| p != prop).cloned();
+    arena.alloc(Record {
+        map: bumpalo::collections::Vec::from_iter_in(map, arena),
+        open: rec.open,
+    })
+}
+
+fn with_prop<'a, C>(
+    arena: &'a bumpalo::Bump,
+    rec: &Record<'a, C, Type<'a, C>>,
+    prop: &C::Prop,
+    ty: &'a Ty<'a, C>,
+) -> &'a Record<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    let mut map = bumpalo::collections::Vec::from_iter_in(rec.map.iter().cloned(), arena);
+    let at = map.partition_point(|(p, _)| p < prop);
+    map.insert(at, (prop.clone(), ty));
+    arena.alloc(Record {
+        map,
+        open: rec.open,
+    })
+}
@@ -0,0 +1,369 @@
+//! Compatibility between two versions of a type. The new version is backward-compatible when it
+//! accepts all the values of the old one (old data can still be read), and forward-compatible
+//! when the old version accepts all the values of the new one (new data can be read by old
+//! readers). Both are decided by subtyping; the differences are then explained, field by field,
+//! by the values that each version accepts and the other rejects. Every change carries such
+//! values, so some change breaks a direction exactly when that direction doesn't hold.
+
+use crate::{
+    bdd::{Bdd, Openness, Record, Solver, Type, TypePair},
+    narrow::Step,
+    ty::TyConfig,
+};
+
+/// A change from the old version of a type to the new one, at the given path in the values.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change<'a, C>
+where
+    C: TyConfig,
+{
+    /// A field required by the old version isn't required by the new one.
+    RemovedField(Vec<Step<C>>, C::Prop, Witnesses<'a, C>),
+    /// A field not required by the old version is required by the new one.
+    AddedField(Vec<Step<C>>, C::Prop, Witnesses<'a, C>),
+    /// The new version rejects these values, with fields the old one didn't list.
+    Closed(Vec<Step<C>>, &'a Bdd<'a, C, Type<'a, C>>),
+    /// The new version accepts these values, with fields the old one rejected.
+    Opened(Vec<Step<C>>, &'a Bdd<'a, C, Type<'a, C>>),
+    /// The new version rejects these values, accepted by the old one.
+    Narrowed(Vec<Step<C>>, &'a Bdd<'a, C, Type<'a, C>>),
+    /// The new version accepts these values, rejected by the old one.
+    Widened(Vec<Step<C>>, &'a Bdd<'a, C, Type<'a, C>>),
+}
+impl<C> Change<'_, C>
+where
+    C: TyConfig,
+{
+    /// Whether the change makes the new version reject old values.
+    pub fn breaks_backward(&self) -> bool {
+        match self {
+            Self::RemovedField(_, _, witnesses) | Self::AddedField(_, _, witnesses) => {
+                witnesses.lost.is_some()
+            }
+            Self::Closed(..) | Self::Narrowed(..) => true,
+            Self::Opened(..) | Self::Widened(..) => false,
+        }
+    }
+
+    /// Whether the change makes the old version reject new values.
+    pub fn breaks_forward(&self) -> bool {
+        match self {
+            Self::RemovedField(_, _, witnesses) | Self::AddedField(_, _, witnesses) => {
+                witnesses.gained.is_some()
+            }
+            Self::Opened(..) | Self::Widened(..) => true,
+            Self::Closed(..) | Self::Narrowed(..) => false,
+        }
+    }
+
+    /// The change, keeping only the directions in which the enclosing values break, or `None`
+    /// if it breaks neither of them.
+    fn within(self, backward: bool, forward: bool) -> Option<Self> {
+        match self {
+            Self::RemovedField(path, prop, witnesses) => witnesses
+                .within(backward, forward)
+                .map(|witnesses| Self::RemovedField(path, prop, witnesses)),
+            Self::AddedField(path, prop, witnesses) => witnesses
+                .within(backward, forward)
+                .map(|witnesses| Self::AddedField(path, prop, witnesses)),
+            Self::Closed(..) | Self::Narrowed(..) => backward.then_some(self),
+            Self::Opened(..) | Self::Widened(..) => forward.then_some(self),
+        }
+    }
+}
+
+/// The values showing how a field change breaks compatibility, in either direction.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Witnesses<'a, C>
+where
+    C: TyConfig,
+{
+    /// The values of the old version that the new one rejects because of the change.
+    pub lost: Option<&'a Bdd<'a, C, Type<'a, C>>>,
+    /// The values of the new version that the old one rejects because of the change.
+    pub gained: Option<&'a Bdd<'a, C, Type<'a, C>>>,
+}
+impl<C> Witnesses<'_, C>
+where
+    C: TyConfig,
+{
+    fn within(self, backward: bool, forward: bool) -> Option<Self> {
+        let witnesses = Self {
+            lost: self.lost.filter(|_| backward),
+            gained: self.gained.filter(|_| forward),
+        };
+        (witnesses.lost.is_some() || witnesses.gained.is_some()).then_some(witnesses)
+    }
+}
+
+/// The compatibility of two versions of a type, in both directions, with the changes that break
+/// it.
+#[derive(Debug)]
+pub struct Compat<'a, C>
+where
+    C: TyConfig,
+{
+    /// Whether the new version accepts all the values of the old one.
+    pub backward: bool,
+    /// Whether the old version accepts all the values of the new one.
+    pub forward: bool,
+    /// The changes that break compatibility in either direction.
+    pub changes: Vec<Change<'a, C>>,
+}
+
+/// The compatibility of the `new` version of a type with the `old` one.
+pub fn check_compat<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    old: &'a Bdd<'a, C, Type<'a, C>>,
+    new: &'a Bdd<'a, C, Type<'a, C>>,
+) -> Compat<'a, C>
+where
+    C: TyConfig,
+{
+    let mut changes = Vec::new();
+    compare(solver, &mut Vec::new(), old, new, &mut changes);
+    Compat {
+        backward: solver.is_subtype(old, new),
+        forward: solver.is_subtype(new, old),
+        changes,
+    }
+}
+
+fn compare<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    path: &mut Vec<Step<C>>,
+    old: &'a Bdd<'a, C, Type<'a, C>>,
+    new: &'a Bdd<'a, C, Type<'a, C>>,
+    changes: &mut Vec<Change<'a, C>>,
+) where
+    C: TyConfig,
+{
+    let arena = solver.arena;
+    if solver.is_subtype(old, new) && solver.is_subtype(new, old) {
+        return;
+    }
+    if let (Some(old_records), Some(new_records)) = (records(solver, old), records(solver, new)) {
+        // Pairs each old record with the new one sharing the most fields.
+        let mut matched = vec![false; new_records.len()];
+        for old_record in &old_records {
+            let shared = |new_record: &Record<'a, C, Type<'a, C>>| {
+                let props = old_record.map.iter();
+                props
+                    .filter(|(prop, _)| new_record.get(prop).is_some())
+                    .count()
+            };
+            let best = (0..new_records.len())
+                .max_by_key(|&i| (shared(new_records[i]), !matched[i]))
+                .unwrap();
+            matched[best] = true;
+            let new_record = new_records[best];
+            compare_records(solver, path, (old, new), old_record, new_record, changes);
+        }
+        for (new_record, _) in new_records.iter().zip(matched).filter(|(_, m)| !m) {
+            let gained = Bdd::diff(arena, record_ty(arena, new_record), old);
+            if !solver.is_empty(gained) {
+                changes.push(Change::Widened(path.clone(), gained));
+            }
+        }
+
+        // The other kinds of values are compared as a whole.
+        let rest = |ty| {
+            Bdd::atom(
+                arena,
+                arena.alloc(Type {
+                    records: Bdd::bot(arena),
+                    ..Bdd::flatten(arena, ty)
+                }),
+            )
+        };
+        difference(solver, path, rest(old), rest(new), changes);
+    } else {
+        difference(solver, path, old, new, changes);
+    }
+}
+
+/// Compares a record of the old version with one of the new version, where `whole` are the two
+/// versions. Each change is explained by the values of one record that the other version
+/// rejects, among those where the change shows.
+fn compare_records<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    path: &mut Vec<Step<C>>,
+    whole: TypePair<'a, C>,
+    old: &'a Record<'a, C, Type<'a, C>>,
+    new: &'a Record<'a, C, Type<'a, C>>,
+    changes: &mut Vec<Change<'a, C>>,
+) where
+    C: TyConfig,
+{
+    let arena = solver.arena;
+    let top = Bdd::top(arena);
+    let (old_ty, new_ty) = (record_ty(arena, old), record_ty(arena, new));
+    let lost = |values| {
+        witness(
+            solver,
+            Bdd::diff(arena, Bdd::inter(arena, old_ty, values), whole.1),
+        )
+    };
+    let gained = |values| {
+        witness(
+            solver,
+            Bdd::diff(arena, Bdd::inter(arena, new_ty, values), whole.0),
+        )
+    };
+    let with = |prop: &C::Prop, ty| field_ty(arena, Openness::Open, [(prop.clone(), ty)]);
+
+    for (prop, old_field) in old.map.iter() {
+        match new.get(prop) {
+            Some(new_field) => {
+                path.push(Step::Field(prop.clone()));
+                let mut field_changes = Vec::new();
+                compare(solver, path, old_field, new_field, &mut field_changes);
+                path.pop();
+                if field_changes.is_empty() {
+                    continue;
+                }
+                // The field may differ only in values that the rest of the type accounts for.
+                let backward = lost(with(prop, Bdd::not(arena, new_field))).is_some();
+                let forward = gained(with(prop, Bdd::not(arena, old_field))).is_some();
+                changes.extend(
+                    field_changes
+                        .into_iter()
+                        .filter_map(|change| change.within(backward, forward)),
+                );
+            }
+            None => {
+                let witnesses = Witnesses {
+                    lost: match new.open {
+                        Openness::Open => None,
+                        Openness::Closed => lost(with(prop, top)),
+                    },
+                    gained: gained(Bdd::not(arena, with(prop, old_field))),
+                };
+                if let Some(witnesses) = witnesses.within(true, true) {
+                    changes.push(Change::RemovedField(path.clone(), prop.clone(), witnesses));
+                }
+            }
+        }
+    }
+    for (prop, new_field) in new.map.iter() {
+        if old.get(prop).is_none() {
+            let witnesses = Witnesses {
+                lost: lost(Bdd::not(arena, with(prop, new_field))),
+                gained: match old.open {
+                    Openness::Open => None,
+                    Openness::Closed => gained(with(prop, top)),
+                },
+            };
+            if let Some(witnesses) = witnesses.within(true, true) {
+                changes.push(Change::AddedField(path.clone(), prop.clone(), witnesses));
+            }
+        }
+    }
+
+    // The values with fields listed by neither version.
+    let listed = || {
+        let old_props = old.map.iter().map(|(prop, _)| prop);
+        let new_props = new.map.iter().map(|(prop, _)| prop);
+        let new_props = new_props.filter(|prop| old.get(prop).is_none());
+        old_props.chain(new_props).map(|prop| (prop.clone(), top))
+    };
+    let unlisted = Bdd::diff(
+        arena,
+        field_ty(arena, Openness::Open, listed()),
+        field_ty(arena, Openness::Closed, listed()),
+    );
+    match (old.open, new.open) {
+        (Openness::Open, Openness::Closed) => {
+            if let Some(lost) = lost(unlisted) {
+                changes.push(Change::Closed(path.clone(), lost));
+            }
+        }
+        (Openness::Closed, Openness::Open) => {
+            if let Some(gained) = gained(unlisted) {
+                changes.push(Change::Opened(path.clone(), gained));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Records the values accepted by only one of the versions.
+fn difference<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    path: &[Step<C>],
+    old: &'a Bdd<'a, C, Type<'a, C>>,
+    new: &'a Bdd<'a, C, Type<'a, C>>,
+    changes: &mut Vec<Change<'a, C>>,
+) where
+    C: TyConfig,
+{
+    let lost = Bdd::diff(solver.arena, old, new);
+    if !solver.is_empty(lost) {
+        changes.push(Change::Narrowed(path.to_vec(), lost));
+    }
+    let gained = Bdd::diff(solver.arena, new, old);
+    if !solver.is_empty(gained) {
+        changes.push(Change::Widened(path.to_vec(), gained));
+    }
+}
+
+/// The records of a type that is a union of records and of values of other kinds, if it is one.
+fn records<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+) -> Option<Vec<&'a Record<'a, C, Type<'a, C>>>>
+where
+    C: TyConfig,
+{
+    let flat = Bdd::flatten(solver.arena, ty);
+    if !matches!(flat.vars, Bdd::Bot) {
+        return None;
+    }
+    let mut records = Vec::new();
+    for (pos, neg) in flat.records.dnf() {
+        match (pos.as_slice(), neg.as_slice()) {
+            ([record], []) => records.push(*record),
+            _ => return None,
+        }
+    }
+    (!records.is_empty()).then_some(records)
+}
+
+fn record_ty<'a, C>(
+    arena: &'a bumpalo::Bump,
+    record: &'a Record<'a, C, Type<'a, C>>,
+) -> &'a Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    let records = Bdd::atom(arena, record);
+    Bdd::atom(arena, arena.alloc(Type::from_records(arena, records)))
+}
+
+/// The record type with the given fields.
+fn field_ty<'a, C, I>(
+    arena: &'a bumpalo::Bump,
+    open: Openness,
+    props: I,
+) -> &'a Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+    I: IntoIterator<Item = (C::Prop, &'a Bdd<'a, C, Type<'a, C>>)>,
+{
+    let record = arena.alloc(Record {
+        map: bumpalo::collections::Vec::from_iter_in(props, arena),
+        open,
+    });
+    record_ty(arena, record)
+}
+
+/// The type, unless it's empty.
+fn witness<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+) -> Option<&'a Bdd<'a, C, Type<'a, C>>>
+where
+    C: TyConfig,
+{
+    (!solver.is_empty(ty)).then_some(ty)
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    bdd::{Bdd, Refr, Type, Var},
+    ty::TyConfig,
+};
+
+/// How the values of a parameterized type change as the values of a parameter grow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variance {
+    /// The parameter doesn't matter.
+    Bivariant,
+    Covariant,
+    Contravariant,
+    /// The arguments must be equivalent.
+    Invariant,
+}
+impl Variance {
+    /// The variance of a parameter occurring with both `self` and `other`.
+    pub fn join(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Bivariant, v) | (v, Self::Bivariant) => v,
+            (v1, v2) if v1 == v2 => v1,
+            _ => Self::Invariant,
+        }
+    }
+
+    /// The variance of an occurrence of variance `inner` in a position of variance `self`.
+    pub fn compose(self, inner: Self) -> Self {
+        match inner {
+            Self::Bivariant => Self::Bivariant,
+            Self::Covariant => self,
+            Self::Contravariant => self.flip(),
+            Self::Invariant if self == Self::Bivariant => Self::Bivariant,
+            Self::Invariant => Self::Invariant,
+        }
+    }
+
+    pub fn flip(self) -> Self {
+        match self {
+            Self::Covariant => Self::Contravariant,
+            Self::Contravariant => Self::Covariant,
+            v => v,
+        }
+    }
+}
+
+/// The definition of a named type: its body, in which the parameters occur as variables.
+#[derive(Debug)]
+pub struct TyDef<'a, C>
+where
+    C: TyConfig,
+{
+    pub params: Vec<C::Var>,
+    pub body: &'a Bdd<'a, C, Type<'a, C>>,
+    /// Whether references to the type are nominal: they aren't unfolded when checking emptiness,
+    /// so the type is distinct from its representation.
+    pub opaque: bool,
+    /// The variance of each parameter, inferred from the body and the other definitions.
+    pub variances: Vec<Variance>,
+}
+
+/// A named type to define: its name, parameters and body, and whether it's opaque.
+pub(crate) type Definition<'a, C> = (
+    <C as TyConfig>::TyName,
+    Vec<<C as TyConfig>::Var>,
+    &'a Bdd<'a, C, Type<'a, C>>,
+    bool,
+);
+
+/// The definitions of the named types referenced by `Refr` atoms.
+#[derive(Debug)]
+pub struct Defs<'a, C>
+where
+    C: TyConfig,
+{
+    defs: BTreeMap<C::TyName, TyDef<'a, C>>,
+}
+impl<'a, C> Default for Defs<'a, C>
+where
+    C: TyConfig,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<'a, C> Defs<'a, C>
+where
+    C: TyConfig,
+{
+    pub fn new() -> Self {
+        Self {
+            defs: BTreeMap::new(),
+        }
+    }
+
+    /// Defines (or redefines) a named type. The body may reference any named type, including
+    /// itself and those defined later.
+    pub fn define(
+        &mut self,
+        name: C::TyName,
+        params: Vec<C::Var>,
+        body: &'a Bdd<'a, C, Type<'a, C>>,
+    ) {
+        self.insert(name, params, body, false);
+    }
+
+    /// Defines (or redefines) an opaque named type, whose representation is only available
+    /// through [`Defs::unfold`]. References to it are only related to references to the same type,
+    /// according to the variances of its parameters.
+    pub fn define_opaque(
+        &mut self,
+        name: C::TyName,
+        params: Vec<C::Var>,
+        body: &'a Bdd<'a, C, Type<'a, C>>,
+    ) {
+        self.insert(name, params, body, true);
+    }
+
+    /// Defines several named types, inferring the variances once for all of them rather than
+    /// after each one.
+    pub(crate) fn define_all<I>(&mut self, defs: I)
+    where
+        I: IntoIterator<Item = Definition<'a, C>>,
+    {
+        for (name, params, body, opaque) in defs {
+            let variances = vec![Variance::Bivariant; params.len()];
+            self.defs.insert(
+                name,
+                TyDef {
+                    params,
+                    body,
+                    opaque,
+                    variances,
+                },
+            );
+        }
+        self.infer_variances();
+    }
+
+    fn insert(
+        &mut self,
+        name: C::TyName,
+        params: Vec<C::Var>,
+        body: &'a Bdd<'a, C, Type<'a, C>>,
+        opaque: bool,
+    ) {
+        self.define_all([(name, params, body, opaque)]);
+    }
+
+    pub fn get(&self, name: &C::TyName) -> Option<&TyDef<'a, C>> {
+        self.defs.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&C::TyName, &TyDef<'a, C>)> {
+        self.defs.iter()
+    }
+
+    /// The body of the referenced type with its parameters replaced by the arguments of the
+    /// reference, or `None` if the type isn't defined. This also reveals the representation of
+    /// opaque types.
+    pub fn unfold(
+        &self,
+        arena: &'a bumpalo::Bump,
+        refr: &'a Refr<'a, C, Type<'a, C>>,
+    ) -> Option<&'a Bdd<'a, C, Type<'a, C>>> {
+        let def = self.defs.get(&refr.id)?;
+        Some(Bdd::subst(arena, def.body, &|var| match var {
+            Var::Named(var) => def
+                .params
+                .iter()
+                .position(|param| param == var)
+                .and_then(|i| refr.args.get(i).copied()),
+            Var::Dyn => None,
+        }))
+    }
+
+    /// Infers the variances of the parameters of all the definitions as a least fixpoint, since
+    /// definitions can be mutually recursive.
+    fn infer_variances(&mut self) {
+        for def in self.defs.values_mut() {
+            def.variances.fill(Variance::Bivariant);
+        }
+        loop {
+            let inferred: Vec<_> = self
+                .defs
+                .values()
+                .map(|def| {
+                    let mut variances = vec![Variance::Bivariant; def.params.len()];
+                    def.body
+                        .for_each_var(self, Variance::Covariant, &mut |var, variance| {
+                            if let Var::Named(var) = var
+                                && let Some(i) = def.params.iter().position(|param| param == var)
+                            {
+                                variances[i] = variances[i].join(variance);
+                            }
+                        });
+                    variances
+                })
+                .collect();
+            let mut changed = false;
+            for (def, variances) in self.defs.values_mut().zip(inferred) {
+                changed |= def.variances != variances;
+                def.variances = variances;
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Gradual typing with the unknown type `?`, following the set-theoretic approach of Castagna
+//! and Lanvin. A gradual type stands for the set of its materializations, the static types
+//! obtained by replacing each occurrence of `?` with some type. These lie between its minimal
+//! materialization `t⇓` and its maximal one `t⇑`, and the gradual operators are computed on
+//! these bounds.
+
+use crate::{
+    bdd::{Bdd, Solver, Type, Var},
+    defs::Variance,
+    ty::TyConfig,
+};
+
+/// The unknown type `?`.
+pub fn dynamic<'a, C>(arena: &'a bumpalo::Bump) -> &'a Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    Bdd::atom(
+        arena,
+        arena.alloc(Type::from_vars(
+            arena,
+            Bdd::atom(arena, arena.alloc(Var::Dyn)),
+        )),
+    )
+}
+
+/// Replaces `?` with the top type in covariant positions and the empty type in contravariant
+/// ones (`t⇑`), or the other way around (`t⇓`). Arguments of references follow the variances of
+/// the parameters in the solver's definitions. In invariant positions no single type bounds the
+/// materializations, so `?` is kept there.
+pub fn materialize<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+    max: bool,
+) -> &'a Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    let arena = solver.arena;
+    let top = if max {
+        Variance::Covariant
+    } else {
+        Variance::Contravariant
+    };
+    Bdd::subst_polar(
+        arena,
+        solver.defs,
+        ty,
+        Variance::Covariant,
+        &|var, variance| match var {
+            Var::Dyn if variance == Variance::Invariant => None,
+            Var::Dyn if variance == top => Some(Bdd::top(arena)),
+            Var::Dyn => Some(Bdd::bot(arena)),
+            Var::Named(_) => None,
+        },
+    )
+}
+
+/// Whether some materialization of `t1` is a subtype of some materialization of `t2`.
+pub fn is_consistent_subtype<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    t1: &'a Bdd<'a, C, Type<'a, C>>,
+    t2: &'a Bdd<'a, C, Type<'a, C>>,
+) -> bool
+where
+    C: TyConfig,
+{
+    solver.is_subtype(
+        materialize(solver, t1, false),
+        materialize(solver, t2, true),
+    )
+}
+
+/// The gradual type ranging over the static types from `lower` to `upper`.
+fn between<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    lower: &'a Bdd<'a, C, Type<'a, C>>,
+    upper: &'a Bdd<'a, C, Type<'a, C>>,
+) -> &'a Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    let arena = solver.arena;
+    if solver.is_subtype(upper, lower) {
+        lower
+    } else {
+        Bdd::union(arena, lower, Bdd::inter(arena, dynamic(arena), upper))
+    }
+}
+
+/// The domain of a gradual function type. An argument can be passed to the function when it is
+/// a consistent subtype of the domain.
+pub fn domain<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+) -> &'a Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    // Smaller function types have larger domains.
+    between(
+        solver,
+        Bdd::domain(solver, materialize(solver, ty, true)),
+        Bdd::domain(solver, materialize(solver, ty, false)),
+    )
+}
+
+/// The result of applying a function of gradual type `ty` to an argument of gradual type `arg`.
+pub fn apply<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+    arg: &'a Bdd<'a, C, Type<'a, C>>,
+) -> &'a Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    between(
+        solver,
+        Bdd::apply(
+            solver,
+            materialize(solver, ty, false),
+            materialize(solver, arg, false),
+        ),
+        Bdd::apply(
+            solver,
+            materialize(solver, ty, true),
+            materialize(solver, arg, true),
+        ),
+    )
+}
+
+pub fn proj_left<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+) -> &'a Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    between(
+        solver,
+        Bdd::proj_left(solver, materialize(solver, ty, false)),
+        Bdd::proj_left(solver, materialize(solver, ty, true)),
+    )
+}
+
+pub fn proj_right<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+) -> &'a Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    between(
+        solver,
+        Bdd::proj_right(solver, materialize(solver, ty, false)),
+        Bdd::proj_right(solver, materialize(solver, ty, true)),
+    )
+}
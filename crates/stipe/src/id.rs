@@ -0,0 +1,262 @@
+//! Cheap identities for types, so that they can be used as keys of maps and sets. Comparing or
+//! hashing a `Bdd` walks all of it, while a `TypeKey` is a plain index. Keys are hash-consed by
+//! the context: the key of a node of its arena is computed once, from the keys of its children,
+//! so that computing the key of a type takes time linear in the number of its distinct nodes.
+
+use std::{collections::HashMap, ptr};
+
+use bumpalo::Bump;
+
+use crate::{
+    bdd::{Arrow, Basic, Bdd, Intervals, Openness, Product, Record, Refr, TyAtom, Type, Var},
+    ty::TyConfig,
+};
+
+/// The key of a type in some `Context` (see `Context::key`). Structurally equal types get the
+/// same key, but types that are only equivalent (BDDs aren't canonical) may not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TypeKey(usize);
+
+/// A node of a type, with its children replaced by their keys.
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Node<C>
+where
+    C: TyConfig,
+{
+    Top,
+    Bot,
+    Atom(usize, usize, usize, usize),
+    Type([usize; 7]),
+    Dyn,
+    Var(C::Var),
+    Basic(C::Basic),
+    Literal(C::Literal),
+    Ints(Vec<(C::Int, C::Int)>),
+    Product(usize, usize),
+    Arrow(usize, usize),
+    Record(Openness, Vec<(C::Prop, usize)>),
+    Refr(C::TyName, Vec<usize>),
+}
+
+/// The keys of the nodes seen so far, by value and by address.
+#[derive(Debug)]
+pub(crate) struct Interner<C>
+where
+    C: TyConfig,
+{
+    by_node: HashMap<Node<C>, usize>,
+    /// The keys of the allocations of the context's arena already interned, so that their
+    /// children aren't visited again. They live as long as the context, so their addresses
+    /// aren't reused.
+    by_address: HashMap<(usize, Kind), usize>,
+    /// The keys of the other allocations interned by the current call to `key`. They may be
+    /// freed once it returns, and their addresses reused, so they're forgotten then.
+    borrowed: HashMap<(usize, Kind), usize>,
+    /// The address ranges of the chunks of the context's arena, as of the current call to `key`.
+    chunks: Vec<(usize, usize)>,
+}
+impl<C> Interner<C>
+where
+    C: TyConfig,
+{
+    pub(crate) fn new() -> Self {
+        Self {
+            by_node: HashMap::new(),
+            by_address: HashMap::new(),
+            borrowed: HashMap::new(),
+            chunks: Vec::new(),
+        }
+    }
+
+    /// The key of `ty`, whose nodes may be allocated in `arena`, the arena of the context, or
+    /// elsewhere.
+    pub(crate) fn key(&mut self, arena: &Bump, ty: &Bdd<'_, C, Type<'_, C>>) -> TypeKey {
+        // SAFETY: the chunks are only compared with addresses, not read, and nothing is
+        // allocated in the arena while they're listed.
+        let chunks = unsafe { arena.iter_allocated_chunks_raw() };
+        self.chunks = chunks.map(|(start, len)| (start.addr(), len)).collect();
+        let key = self.intern(ty);
+        self.borrowed.clear();
+        TypeKey(key)
+    }
+
+    fn intern<T>(&mut self, value: &T) -> usize
+    where
+        T: Keyed<C>,
+    {
+        let address = (ptr::from_ref(value).addr(), T::KIND);
+        let owned = self
+            .chunks
+            .iter()
+            .any(|(start, len)| (*start..start + len).contains(&address.0));
+        let cache = if owned {
+            &self.by_address
+        } else {
+            &self.borrowed
+        };
+        if let Some(key) = cache.get(&address) {
+            return *key;
+        }
+        let node = value.node(self);
+        let next = self.by_node.len();
+        let key = *self.by_node.entry(node).or_insert(next);
+        match owned {
+            true => self.by_address.insert(address, key),
+            false => self.borrowed.insert(address, key),
+        };
+        key
+    }
+}
+
+/// The kinds of allocations, which tell apart values allocated at the same address (as
+/// zero-sized ones may be).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    Bdd,
+    Type,
+    Var,
+    Basic,
+    Ints,
+    Product,
+    Arrow,
+    Record,
+    Refr,
+}
+
+/// A value whose node can be made from the keys of its children.
+trait Keyed<C>
+where
+    C: TyConfig,
+{
+    const KIND: Kind;
+
+    fn node(&self, interner: &mut Interner<C>) -> Node<C>;
+}
+
+impl<C, T> Keyed<C> for Bdd<'_, C, T>
+where
+    C: TyConfig,
+    T: TyAtom + Keyed<C>,
+{
+    const KIND: Kind = Kind::Bdd;
+
+    fn node(&self, interner: &mut Interner<C>) -> Node<C> {
+        match self {
+            Self::Top => Node::Top,
+            Self::Bot => Node::Bot,
+            Self::Atom {
+                atom, pos, lu, neg, ..
+            } => Node::Atom(
+                interner.intern(*atom),
+                interner.intern(*pos),
+                interner.intern(*lu),
+                interner.intern(*neg),
+            ),
+        }
+    }
+}
+
+impl<C> Keyed<C> for Type<'_, C>
+where
+    C: TyConfig,
+{
+    const KIND: Kind = Kind::Type;
+
+    fn node(&self, interner: &mut Interner<C>) -> Node<C> {
+        Node::Type([
+            interner.intern(self.vars),
+            interner.intern(self.basics),
+            interner.intern(self.ints),
+            interner.intern(self.products),
+            interner.intern(self.arrows),
+            interner.intern(self.records),
+            interner.intern(self.refrs),
+        ])
+    }
+}
+
+impl<C> Keyed<C> for Var<C>
+where
+    C: TyConfig,
+{
+    const KIND: Kind = Kind::Var;
+
+    fn node(&self, _: &mut Interner<C>) -> Node<C> {
+        match self {
+            Self::Dyn => Node::Dyn,
+            Self::Named(var) => Node::Var(var.clone()),
+        }
+    }
+}
+
+impl<C> Keyed<C> for Basic<C>
+where
+    C: TyConfig,
+{
+    const KIND: Kind = Kind::Basic;
+
+    fn node(&self, _: &mut Interner<C>) -> Node<C> {
+        match self {
+            Self::All(basic) => Node::Basic(basic.clone()),
+            Self::Literal(literal) => Node::Literal(literal.clone()),
+        }
+    }
+}
+
+impl<C> Keyed<C> for Intervals<'_, C>
+where
+    C: TyConfig,
+{
+    const KIND: Kind = Kind::Ints;
+
+    fn node(&self, _: &mut Interner<C>) -> Node<C> {
+        Node::Ints(self.ranges.to_vec())
+    }
+}
+
+impl<'a, C> Keyed<C> for Product<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    const KIND: Kind = Kind::Product;
+
+    fn node(&self, interner: &mut Interner<C>) -> Node<C> {
+        Node::Product(interner.intern(self.0), interner.intern(self.1))
+    }
+}
+
+impl<'a, C> Keyed<C> for Arrow<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    const KIND: Kind = Kind::Arrow;
+
+    fn node(&self, interner: &mut Interner<C>) -> Node<C> {
+        Node::Arrow(interner.intern(self.0), interner.intern(self.1))
+    }
+}
+
+impl<'a, C> Keyed<C> for Record<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    const KIND: Kind = Kind::Record;
+
+    fn node(&self, interner: &mut Interner<C>) -> Node<C> {
+        let map = self.map.iter();
+        let map = map.map(|(prop, ty)| (prop.clone(), interner.intern(*ty)));
+        Node::Record(self.open, map.collect())
+    }
+}
+
+impl<'a, C> Keyed<C> for Refr<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    const KIND: Kind = Kind::Refr;
+
+    fn node(&self, interner: &mut Interner<C>) -> Node<C> {
+        let args = self.args.iter().map(|arg| interner.intern(*arg));
+        Node::Refr(self.id.clone(), args.collect())
+    }
+}
@@ -0,0 +1,368 @@
+//! A reference type checker for a small lambda calculus with pairs, records, type tests and
+//! let-polymorphism. It infers types by generating subtyping constraints on fresh variables and
+//! solving them by tallying, which also happens at each `let` so that the bound expression can
+//! be generalized. Lambdas are checked against the arrows of an expected type when there is one,
+//! and the variable tested by a type test is narrowed in each branch. Only the first solution of
+//! each tallying problem is kept, so some typable expressions are rejected.
+
+use std::cell::RefCell;
+
+use crate::{
+    Context,
+    bdd::{Bdd, Openness, Solver, Substitution, Tally, Type, TypePair},
+    defs::Defs,
+    scheme::Scheme,
+    ty::TyConfig,
+};
+
+#[derive(Debug)]
+pub enum Expr<'a, C>
+where
+    C: TyConfig,
+{
+    Var(String),
+    /// A constant of the given type.
+    Const(&'a Bdd<'a, C, Type<'a, C>>),
+    Lambda(String, Box<Self>),
+    /// A lambda typed as an overloaded function: the intersection of an arrow for each of the
+    /// domains, whose codomain is inferred for the body with the parameter in that domain.
+    Overloaded(String, Vec<&'a Bdd<'a, C, Type<'a, C>>>, Box<Self>),
+    App(Box<Self>, Box<Self>),
+    Pair(Box<Self>, Box<Self>),
+    Fst(Box<Self>),
+    Snd(Box<Self>),
+    Record(Vec<(C::Prop, Self)>),
+    Field(Box<Self>, C::Prop),
+    /// `if scrutinee is test then then else otherwise`, narrowing the scrutinee in each branch
+    /// when it is a variable.
+    If {
+        scrutinee: Box<Self>,
+        test: &'a Bdd<'a, C, Type<'a, C>>,
+        then: Box<Self>,
+        otherwise: Box<Self>,
+    },
+    Let(String, Box<Self>, Box<Self>),
+    /// An expression checked against a type.
+    Annot(Box<Self>, &'a Bdd<'a, C, Type<'a, C>>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TypeError {
+    Unbound(String),
+    /// The constraints gathered so far have no solution.
+    Unsatisfiable,
+}
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unbound(name) => write!(f, "unbound variable `{name}`"),
+            Self::Unsatisfiable => f.write_str("the type constraints have no solution"),
+        }
+    }
+}
+impl std::error::Error for TypeError {}
+
+/// The types of the variables in scope, innermost last.
+pub type Env<'a, C> = Vec<(String, Scheme<'a, C>)>;
+
+pub struct Infer<'d, 'a, C>
+where
+    C: TyConfig,
+{
+    ctx: &'a Context<C>,
+    solver: Solver<'d, 'a, C>,
+    /// The variables made by the engine, which stand for unknown types.
+    vars: RefCell<Vec<C::Var>>,
+    /// The constraints gathered since they were last solved.
+    constraints: RefCell<Vec<TypePair<'a, C>>>,
+    /// The solutions found so far, to be applied in order.
+    solutions: RefCell<Vec<Substitution<'a, C>>>,
+}
+impl<'d, 'a, C> Infer<'d, 'a, C>
+where
+    C: TyConfig,
+{
+    pub fn new(ctx: &'a Context<C>, defs: &'d Defs<'a, C>) -> Self {
+        Self {
+            ctx,
+            solver: ctx.solver(defs),
+            vars: RefCell::new(Vec::new()),
+            constraints: RefCell::new(Vec::new()),
+            solutions: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The simplified type of a closed expression.
+    pub fn infer_closed(
+        &self,
+        expr: &Expr<'a, C>,
+    ) -> Result<&'a Bdd<'a, C, Type<'a, C>>, TypeError> {
+        let ty = self.infer(&mut Vec::new(), expr)?;
+        self.solve()?;
+        let ty = self.resolve(ty);
+        Ok(Scheme::generalize(&self.ctx.arena, ty, [])
+            .simplify(&self.solver)
+            .body)
+    }
+
+    pub fn infer(
+        &self,
+        env: &mut Env<'a, C>,
+        expr: &Expr<'a, C>,
+    ) -> Result<&'a Bdd<'a, C, Type<'a, C>>, TypeError> {
+        let ctx = self.ctx;
+        let arena = &ctx.arena;
+        match expr {
+            Expr::Var(name) => {
+                let (_, scheme) = env
+                    .iter()
+                    .rev()
+                    .find(|(n, _)| n == name)
+                    .ok_or_else(|| TypeError::Unbound(name.clone()))?;
+                let (ty, vars) = scheme.instantiate(ctx);
+                self.vars.borrow_mut().extend(vars);
+                Ok(self.resolve(ty))
+            }
+            Expr::Const(ty) => Ok(ty),
+            Expr::Lambda(param, body) => {
+                let dom = self.fresh(param);
+                env.push((param.clone(), Scheme::mono(dom)));
+                let cod = self.infer(env, body);
+                env.pop();
+                Ok(self.arrow(dom, cod?))
+            }
+            Expr::Overloaded(param, domains, body) => {
+                self.infer_overloaded(env, param, domains, body)
+            }
+            Expr::App(fun, arg) => {
+                let fun = self.infer(env, fun)?;
+                let arg = self.infer(env, arg)?;
+                let result = self.fresh("r");
+                self.constrain(fun, self.arrow(arg, result));
+                Ok(result)
+            }
+            Expr::Pair(l, r) => {
+                let l = self.infer(env, l)?;
+                let r = self.infer(env, r)?;
+                Ok(ctx.ty(Type::from_products(arena, ctx.product(l, r))))
+            }
+            Expr::Fst(pair) | Expr::Snd(pair) => {
+                let pair = self.infer(env, pair)?;
+                let result = self.fresh("p");
+                let expected = match expr {
+                    Expr::Fst(_) => ctx.product(result, ctx.top()),
+                    _ => ctx.product(ctx.top(), result),
+                };
+                self.constrain(pair, ctx.ty(Type::from_products(arena, expected)));
+                Ok(result)
+            }
+            Expr::Record(fields) => {
+                let mut tys = Vec::new();
+                for (prop, field) in fields {
+                    tys.push((prop.clone(), self.infer(env, field)?));
+                }
+                Ok(ctx.ty(Type::from_records(arena, ctx.record(Openness::Closed, tys))))
+            }
+            Expr::Field(record, prop) => {
+                let record = self.infer(env, record)?;
+                let result = self.fresh("f");
+                let expected = ctx.record(Openness::Open, [(prop.clone(), result)]);
+                self.constrain(record, ctx.ty(Type::from_records(arena, expected)));
+                Ok(result)
+            }
+            Expr::If {
+                scrutinee,
+                test,
+                then,
+                otherwise,
+            } => {
+                let ty = self.infer(env, scrutinee)?;
+                let then = self.branch(env, scrutinee, ctx.inter([ty, test]), then)?;
+                let otherwise = self.branch(env, scrutinee, ctx.diff(ty, test), otherwise)?;
+                Ok(ctx.union([then, otherwise]))
+            }
+            Expr::Let(name, bound, body) => {
+                let bound = self.infer(env, bound)?;
+                self.solve()?;
+                let bound = self.resolve(bound);
+                let monomorphic: Vec<_> = env
+                    .iter()
+                    .map(|(_, scheme)| self.resolve(scheme.body))
+                    .collect();
+                let scheme = Scheme::generalize(arena, bound, monomorphic);
+                env.push((name.clone(), scheme));
+                let ty = self.infer(env, body);
+                env.pop();
+                ty
+            }
+            Expr::Annot(expr, ty) => {
+                self.check(env, expr, ty)?;
+                Ok(ty)
+            }
+        }
+    }
+
+    /// The intersection of the arrows from each of `domains` to the type of `body` when `param`
+    /// is in that domain, as for `fn neg(x) = if x is Int then -x else !x` with the domains `Int`
+    /// and `Bool`, typed as `(Int -> Int) & (Bool -> Bool)`.
+    pub fn infer_overloaded(
+        &self,
+        env: &mut Env<'a, C>,
+        param: &str,
+        domains: &[&'a Bdd<'a, C, Type<'a, C>>],
+        body: &Expr<'a, C>,
+    ) -> Result<&'a Bdd<'a, C, Type<'a, C>>, TypeError> {
+        let mut arrows = Vec::new();
+        for dom in domains {
+            env.push((param.into(), Scheme::mono(dom)));
+            let cod = self.infer(env, body);
+            env.pop();
+            arrows.push(self.arrow(dom, cod?));
+        }
+        Ok(self.ctx.inter(arrows))
+    }
+
+    /// Checks that the expression has the type `ty`. A lambda is checked against each arrow of
+    /// `ty` when it is an intersection of arrows.
+    pub fn check(
+        &self,
+        env: &mut Env<'a, C>,
+        expr: &Expr<'a, C>,
+        ty: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> Result<(), TypeError> {
+        if let Expr::Lambda(param, body) = expr
+            && let Some(arrows) = self.arrows(ty)
+        {
+            for (dom, cod) in arrows {
+                env.push((param.clone(), Scheme::mono(dom)));
+                let checked = self.check(env, body, cod);
+                env.pop();
+                checked?;
+            }
+            return Ok(());
+        }
+        let inferred = self.infer(env, expr)?;
+        self.constrain(inferred, ty);
+        Ok(())
+    }
+
+    /// Types a branch of a type test, where a variable scrutinee has the type `narrowed`. A
+    /// branch that can't be taken isn't typed.
+    fn branch(
+        &self,
+        env: &mut Env<'a, C>,
+        scrutinee: &Expr<'a, C>,
+        narrowed: &'a Bdd<'a, C, Type<'a, C>>,
+        branch: &Expr<'a, C>,
+    ) -> Result<&'a Bdd<'a, C, Type<'a, C>>, TypeError> {
+        if self.solver.is_empty(self.resolve(narrowed)) {
+            return Ok(self.ctx.bot());
+        }
+        let Expr::Var(name) = scrutinee else {
+            return self.infer(env, branch);
+        };
+        env.push((name.clone(), Scheme::mono(narrowed)));
+        let ty = self.infer(env, branch);
+        env.pop();
+        ty
+    }
+
+    /// The arrows whose intersection is `ty`, if it is one.
+    #[allow(clippy::type_complexity)]
+    fn arrows(&self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> Option<Vec<TypePair<'a, C>>> {
+        let ty = self.resolve(ty);
+        if !ty.free_vars().is_empty() {
+            return None;
+        }
+        let ty = self.solver.upper_bound(ty);
+        let others_empty = *ty.basics == Bdd::Bot
+            && ty.ints.is_empty()
+            && *ty.products == Bdd::Bot
+            && *ty.records == Bdd::Bot
+            && *ty.refrs == Bdd::Bot;
+        let mut clauses = Vec::new();
+        ty.arrows.all_clauses(&mut |pos, neg| {
+            clauses.push((pos.to_vec(), neg.is_empty()));
+            true
+        });
+        match clauses.as_slice() {
+            [(pos, true)] if others_empty => {
+                Some(pos.iter().map(|arrow| (arrow.0, arrow.1)).collect())
+            }
+            _ => None,
+        }
+    }
+
+    fn fresh(&self, hint: &str) -> &'a Bdd<'a, C, Type<'a, C>> {
+        let var = self.ctx.fresh_var(hint);
+        self.vars.borrow_mut().push(var.clone());
+        self.ctx
+            .ty(Type::from_vars(&self.ctx.arena, self.ctx.var(var)))
+    }
+
+    fn arrow(
+        &self,
+        dom: &'a Bdd<'a, C, Type<'a, C>>,
+        cod: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> &'a Bdd<'a, C, Type<'a, C>> {
+        self.ctx
+            .ty(Type::from_arrows(&self.ctx.arena, self.ctx.arrow(dom, cod)))
+    }
+
+    fn constrain(&self, s: &'a Bdd<'a, C, Type<'a, C>>, t: &'a Bdd<'a, C, Type<'a, C>>) {
+        self.constraints.borrow_mut().push((s, t));
+    }
+
+    /// Applies the solutions found so far to the type.
+    pub fn resolve(&self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> &'a Bdd<'a, C, Type<'a, C>> {
+        self.solutions
+            .borrow()
+            .iter()
+            .fold(ty, |ty, solution| solution.apply(&self.ctx.arena, ty))
+    }
+
+    /// Solves the pending constraints, keeping the first solution. Later constraints aren't
+    /// checked against the others, so inference is incomplete: when tallying finds several
+    /// solutions at a `let`, an expression can be rejected because the first one, which may
+    /// make a variable empty, conflicts with how the variable is used afterwards.
+    fn solve(&self) -> Result<(), TypeError> {
+        let arena = &self.ctx.arena;
+        let constraints: Vec<_> = self
+            .constraints
+            .take()
+            .into_iter()
+            .map(|(s, t)| (self.resolve(s), self.resolve(t)))
+            .collect();
+        if constraints.is_empty() {
+            return Ok(());
+        }
+        let vars = self.vars.borrow().clone();
+        let flexible = |var: &C::Var| vars.contains(var);
+        let solution = Tally::new(&self.solver, &flexible)
+            .solve(&constraints)
+            .into_iter()
+            .next()
+            .ok_or(TypeError::Unsatisfiable)?;
+        debug_assert!(constraints.iter().all(|(s, t)| {
+            self.solver
+                .is_subtype(solution.apply(arena, s), solution.apply(arena, t))
+        }));
+        // The variables made by tallying are as flexible as those they solve.
+        let known: Vec<_> = constraints
+            .iter()
+            .flat_map(|(s, t)| [s.free_vars(), t.free_vars()])
+            .flatten()
+            .collect();
+        let mut flexible = self.vars.borrow_mut();
+        for ty in constraints.iter().flat_map(|(s, t)| [s, t]) {
+            for var in solution.apply(arena, ty).free_vars() {
+                if !known.contains(&var) && !flexible.contains(var) {
+                    flexible.push(var.clone());
+                }
+            }
+        }
+        drop(flexible);
+        self.solutions.borrow_mut().push(solution);
+        Ok(())
+    }
+}
@@ -1,17 +1,53 @@
-mod bdd;
-mod ty;
+pub mod bdd;
+pub mod binary;
+pub mod canon;
+pub mod compat;
+pub mod defs;
+pub mod gradual;
+pub mod id;
+pub mod infer;
+pub mod narrow;
+pub mod pattern;
+#[cfg(feature = "json-schema")]
+pub mod schema;
+pub mod scheme;
+#[cfg(feature = "serde")]
+pub mod serial;
+pub mod simplify;
+pub mod store;
+pub mod ty;
+pub mod typescript;
+pub mod value;
 
 use bumpalo::Bump;
-use std::marker::PhantomData;
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+};
 use ty::TyConfig;
 
-use crate::bdd::{Arrow, Bdd, Openness, Product, Record, Refr, TyAtom, Type};
+use crate::{
+    bdd::{
+        Arrow, Basic, Bdd, Intervals, Openness, Product, Record, Refr, Solver, TyAtom, Type, Var,
+    },
+    compat::Compat,
+    defs::Defs,
+    id::{Interner, TypeKey},
+    narrow::{Narrowed, Step, Test},
+    pattern::{Branches, Pattern},
+    scheme::Scheme,
+    value::Value,
+};
 
 pub struct Context<C>
 where
     C: TyConfig,
 {
     arena: Bump,
+    /// The number of fresh variables made so far.
+    fresh: Cell<u64>,
+    /// The keys of the types of the context.
+    keys: RefCell<Interner<C>>,
     _c: PhantomData<C>,
 }
 impl<'a, C> Context<C>
@@ -22,6 +58,8 @@ where
     pub fn new() -> Self {
         Self {
             arena: Bump::new(),
+            fresh: Cell::new(0),
+            keys: RefCell::new(Interner::new()),
             _c: PhantomData,
         }
     }
@@ -30,35 +68,68 @@ where
         Bdd::top(&self.arena)
     }
 
-    pub fn bot(&'a self) -> &'a Bdd<'a, C, Type<C>> {
+    pub fn bot(&'a self) -> &'a Bdd<'a, C, Type<'a, C>> {
         Bdd::bot(&self.arena)
     }
 
-    pub fn var(&'a self, var: C::Var) -> &'a Bdd<'a, C, C::Var> {
-        Bdd::atom(&self.arena, self.arena.alloc(var))
+    pub fn var(&'a self, var: C::Var) -> &'a Bdd<'a, C, Var<C>> {
+        Bdd::atom(&self.arena, self.arena.alloc(Var::Named(var)))
     }
 
-    pub fn basic(&'a self, basic: C::Basic) -> &'a Bdd<'a, C, C::Basic> {
-        Bdd::atom(&self.arena, self.arena.alloc(basic))
+    /// A variable distinct from every other variable made by the context, named after `hint`.
+    /// Tallying and the instantiation of schemes make their variables here too.
+    pub fn fresh_var(&'a self, hint: &str) -> C::Var {
+        let index = self.fresh.get();
+        self.fresh.set(index + 1);
+        C::fresh_var(index, hint)
+    }
+
+    /// A simpler type equivalent to `ty` once all its variables are quantified, as for the type
+    /// inferred for a term.
+    pub fn simplify(&'a self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> &'a Bdd<'a, C, Type<'a, C>> {
+        let defs = Defs::new();
+        Scheme::generalize(&self.arena, ty, [])
+            .simplify(&self.solver(&defs))
+            .body
+    }
+
+    pub fn dynamic(&'a self) -> &'a Bdd<'a, C, Type<'a, C>> {
+        gradual::dynamic(&self.arena)
+    }
+
+    pub fn basic(&'a self, basic: C::Basic) -> &'a Bdd<'a, C, Basic<C>> {
+        Bdd::atom(&self.arena, self.arena.alloc(Basic::All(basic)))
+    }
+
+    pub fn literal(&'a self, literal: C::Literal) -> &'a Bdd<'a, C, Basic<C>> {
+        Bdd::atom(&self.arena, self.arena.alloc(Basic::Literal(literal)))
+    }
+
+    pub fn interval(&'a self, lo: C::Int, hi: C::Int) -> &'a Intervals<'a, C> {
+        Intervals::range(&self.arena, lo, hi)
     }
 
     pub fn product(
         &'a self,
-        l: &'a Bdd<'a, C, Type<C>>,
-        r: &'a Bdd<'a, C, Type<C>>,
-    ) -> &'a Bdd<'a, C, Product<C, Type<'a, C>>> {
+        l: &'a Bdd<'a, C, Type<'a, C>>,
+        r: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> &'a Bdd<'a, C, Product<'a, C, Type<'a, C>>> {
         Bdd::atom(&self.arena, self.arena.alloc(Product(l, r)))
     }
 
     pub fn arrow(
         &'a self,
-        l: &'a Bdd<'a, C, Type<C>>,
-        r: &'a Bdd<'a, C, Type<C>>,
-    ) -> &'a Bdd<'a, C, Arrow<C, Type<'a, C>>> {
+        l: &'a Bdd<'a, C, Type<'a, C>>,
+        r: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> &'a Bdd<'a, C, Arrow<'a, C, Type<'a, C>>> {
         Bdd::atom(&self.arena, self.arena.alloc(Arrow(l, r)))
     }
 
-    pub fn record<I>(&'a self, open: Openness, props: I) -> &'a Bdd<'a, C, Record<C, Type<'a, C>>>
+    pub fn record<I>(
+        &'a self,
+        open: Openness,
+        props: I,
+    ) -> &'a Bdd<'a, C, Record<'a, C, Type<'a, C>>>
     where
         I: IntoIterator<Item = (C::Prop, &'a Bdd<'a, C, Type<'a, C>>)>,
     {
@@ -108,6 +179,123 @@ where
             .unwrap_or_else(|| Bdd::top(&self.arena))
     }
 
+    pub fn diff<T: TyAtom>(
+        &'a self,
+        t1: &'a Bdd<'a, C, T>,
+        t2: &'a Bdd<'a, C, T>,
+    ) -> &'a Bdd<'a, C, T> {
+        Bdd::diff(&self.arena, t1, t2)
+    }
+
+    pub fn ty(&'a self, ty: Type<'a, C>) -> &'a Bdd<'a, C, Type<'a, C>> {
+        Bdd::atom(&self.arena, self.arena.alloc(ty))
+    }
+
+    /// The key of a type, to use it as the key of a map or set. The key of each node allocated
+    /// in the context is computed once and shared by all the types containing it. Nodes
+    /// allocated elsewhere may be freed, so they're keyed again by each call.
+    pub fn key(&'a self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> TypeKey {
+        self.keys.borrow_mut().key(&self.arena, ty)
+    }
+
+    /// A solver for types referencing the named types defined in `defs`. The checks on the
+    /// context itself treat every named type as opaque.
+    pub fn solver<'d>(&'a self, defs: &'d Defs<'a, C>) -> Solver<'d, 'a, C> {
+        Solver::new(self, defs)
+    }
+
+    pub fn is_empty(&'a self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> bool {
+        self.solver(&Defs::new()).is_empty(ty)
+    }
+
+    pub fn is_subtype(
+        &'a self,
+        t1: &'a Bdd<'a, C, Type<'a, C>>,
+        t2: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> bool {
+        self.solver(&Defs::new()).is_subtype(t1, t2)
+    }
+
+    pub fn is_consistent_subtype(
+        &'a self,
+        t1: &'a Bdd<'a, C, Type<'a, C>>,
+        t2: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> bool {
+        gradual::is_consistent_subtype(&self.solver(&Defs::new()), t1, t2)
+    }
+
+    /// The compatibility of the `new` version of a type with the `old` one.
+    pub fn check_compat(
+        &'a self,
+        old: &'a Bdd<'a, C, Type<'a, C>>,
+        new: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> Compat<'a, C> {
+        compat::check_compat(&self.solver(&Defs::new()), old, new)
+    }
+
+    /// Whether a runtime value belongs to a type.
+    pub fn check_value(&'a self, value: &Value<C>, ty: &'a Bdd<'a, C, Type<'a, C>>) -> bool {
+        value::check_value(&self.solver(&Defs::new()), value, ty)
+    }
+
+    /// The path to the part of a runtime value that makes it not belong to a type, if any.
+    pub fn value_mismatch(
+        &'a self,
+        value: &Value<C>,
+        ty: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> Option<Vec<Step<C>>> {
+        value::value_mismatch(&self.solver(&Defs::new()), value, ty)
+    }
+
+    pub fn pat_wildcard(&'a self) -> &'a Pattern<'a, C> {
+        self.arena.alloc(Pattern::Wildcard)
+    }
+
+    pub fn pat_basic(&'a self, basic: C::Basic) -> &'a Pattern<'a, C> {
+        self.arena.alloc(Pattern::Basic(Basic::All(basic)))
+    }
+
+    pub fn pat_literal(&'a self, literal: C::Literal) -> &'a Pattern<'a, C> {
+        self.arena.alloc(Pattern::Basic(Basic::Literal(literal)))
+    }
+
+    pub fn pat_pair(&'a self, l: &'a Pattern<'a, C>, r: &'a Pattern<'a, C>) -> &'a Pattern<'a, C> {
+        self.arena.alloc(Pattern::Pair(l, r))
+    }
+
+    pub fn pat_record<I>(&'a self, open: Openness, fields: I) -> &'a Pattern<'a, C>
+    where
+        I: IntoIterator<Item = (C::Prop, &'a Pattern<'a, C>)>,
+    {
+        self.arena.alloc(Pattern::Record {
+            fields: bumpalo::collections::Vec::from_iter_in(fields, &self.arena),
+            open,
+        })
+    }
+
+    pub fn pat_test(&'a self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> &'a Pattern<'a, C> {
+        self.arena.alloc(Pattern::Test(ty))
+    }
+
+    pub fn accepted(&'a self, pattern: &'a Pattern<'a, C>) -> &'a Bdd<'a, C, Type<'a, C>> {
+        pattern.accepted(&self.arena)
+    }
+
+    pub fn branches<I>(&'a self, ty: &'a Bdd<'a, C, Type<'a, C>>, patterns: I) -> Branches<'a, C>
+    where
+        I: IntoIterator<Item = &'a Pattern<'a, C>>,
+    {
+        Branches::check(&self.solver(&Defs::new()), ty, patterns)
+    }
+
+    pub fn narrow(
+        &'a self,
+        ty: &'a Bdd<'a, C, Type<'a, C>>,
+        test: &Test<'a, C>,
+    ) -> Narrowed<'a, C> {
+        test.narrow(&self.arena, ty)
+    }
+
     /*
     pub fn top(&'a self) -> &'a Ty<'a, C> {
         self.arena.alloc(Ty::Top)
@@ -185,13 +373,25 @@ where
 mod tests {
     use crate::{
         Context,
-        bdd::{Bdd, TyAtom, Type},
+        bdd::{
+            Basic, Bdd, BddFold, Openness, Polarity, Product, Tally, TyAtom, Type, TypeVisitor, Var,
+        },
+        canon::Canonicalizer,
+        defs::{Defs, Variance},
+        gradual,
+        id::TypeKey,
+        infer::{Expr, Infer, TypeError},
+        narrow::{Step, Test},
+        scheme::{Quantified, Scheme},
         ty::TyConfig,
     };
 
+    use std::collections::HashMap;
+
     impl TyAtom for String {}
 
-    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct TestName(String);
     impl From<&str> for TestName {
         fn from(value: &str) -> Self {
@@ -199,7 +399,8 @@ mod tests {
         }
     }
 
-    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct TestBasic(String);
     impl TyAtom for TestBasic {}
     impl From<&str> for TestBasic {
@@ -208,7 +409,17 @@ mod tests {
         }
     }
 
-    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    enum TestLiteral {
+        Int(i64),
+        Str(String),
+        Bool(bool),
+    }
+    impl TyAtom for TestLiteral {}
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct TestVar(String);
     impl TyAtom for TestVar {}
     impl From<&str> for TestVar {
@@ -217,16 +428,206 @@ mod tests {
         }
     }
 
-    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct TestProp(String);
+    impl From<&str> for TestProp {
+        fn from(value: &str) -> Self {
+            TestProp(value.to_string())
+        }
+    }
+
+    macro_rules! impl_string_atom {
+        ($($atom:ident),*) => {
+            $(impl std::fmt::Display for $atom {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.write_str(&self.0)
+                }
+            }
+            impl std::str::FromStr for $atom {
+                type Err = std::convert::Infallible;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    Ok(Self(s.to_string()))
+                }
+            })*
+        };
+    }
+    impl_string_atom!(TestName, TestBasic, TestVar, TestProp);
+
+    impl std::fmt::Display for TestLiteral {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                TestLiteral::Int(i) => write!(f, "int:{i}"),
+                TestLiteral::Str(s) => write!(f, "str:{s}"),
+                TestLiteral::Bool(b) => write!(f, "bool:{b}"),
+            }
+        }
+    }
+    impl std::str::FromStr for TestLiteral {
+        type Err = ();
 
-    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.split_once(':').ok_or(())? {
+                ("int", i) => i.parse().map(TestLiteral::Int).map_err(|_| ()),
+                ("str", s) => Ok(TestLiteral::Str(s.to_string())),
+                ("bool", b) => b.parse().map(TestLiteral::Bool).map_err(|_| ()),
+                _ => Err(()),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
     struct TestConfig {}
     impl TyConfig for TestConfig {
         type TyName = TestName;
         type Basic = TestBasic;
+        type Literal = TestLiteral;
+        type Int = i64;
+        type Var = TestVar;
+        type Prop = TestProp;
+
+        fn literal_basic(literal: &TestLiteral) -> TestBasic {
+            match literal {
+                TestLiteral::Int(_) => "Int".into(),
+                TestLiteral::Str(_) => "String".into(),
+                TestLiteral::Bool(_) => "Boolean".into(),
+            }
+        }
+
+        fn fresh_var(index: u64, hint: &str) -> TestVar {
+            TestVar(format!("{hint}#{index}"))
+        }
+
+        const INTERVALS: bool = true;
+
+        fn int_basic(basic: &TestBasic) -> bool {
+            basic.0 == "Int"
+        }
+
+        fn literal_int(literal: &TestLiteral) -> Option<i64> {
+            match literal {
+                TestLiteral::Int(i) => Some(*i),
+                _ => None,
+            }
+        }
+
+        fn basic_literals(basic: &TestBasic) -> Option<Vec<TestLiteral>> {
+            (basic.0 == "Boolean").then(|| vec![TestLiteral::Bool(true), TestLiteral::Bool(false)])
+        }
+    }
+
+    impl crate::typescript::TsConfig for TestConfig {
+        fn basic_ts(basic: &TestBasic) -> String {
+            match basic.0.as_str() {
+                "Nil" => "null",
+                "Boolean" => "boolean",
+                "String" => "string",
+                _ => "number",
+            }
+            .to_string()
+        }
+
+        fn literal_ts(literal: &TestLiteral) -> String {
+            match literal {
+                TestLiteral::Int(i) => i.to_string(),
+                TestLiteral::Str(s) => crate::typescript::string_ts(s),
+                TestLiteral::Bool(b) => b.to_string(),
+            }
+        }
+    }
+
+    #[cfg(feature = "json-schema")]
+    impl crate::schema::SchemaConfig for TestConfig {
+        fn basic_schema(basic: &TestBasic) -> serde_json::Value {
+            let ty = match basic.0.as_str() {
+                "Boolean" => "boolean",
+                "String" => "string",
+                "Nil" => "null",
+                _ => "number",
+            };
+            serde_json::json!({ "type": ty })
+        }
+
+        fn literal_schema(literal: &TestLiteral) -> serde_json::Value {
+            match literal {
+                TestLiteral::Int(i) => serde_json::json!({ "const": i }),
+                TestLiteral::Str(s) => serde_json::json!({ "const": s }),
+                TestLiteral::Bool(b) => serde_json::json!({ "const": b }),
+            }
+        }
+
+        fn schema_basic(name: &str) -> Option<TestBasic> {
+            let basic = match name {
+                "null" => "Nil",
+                "boolean" => "Boolean",
+                "string" => "String",
+                "number" => "Number",
+                _ => return None,
+            };
+            Some(TestBasic(basic.into()))
+        }
+
+        fn schema_literal(value: &serde_json::Value) -> Option<TestLiteral> {
+            match value {
+                serde_json::Value::String(s) => Some(TestLiteral::Str(s.clone())),
+                serde_json::Value::Bool(b) => Some(TestLiteral::Bool(*b)),
+                _ => None,
+            }
+        }
+    }
+
+    /// The test configuration without intervals, where integers are the `Int` basic.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct NoInts;
+    impl TyConfig for NoInts {
+        type TyName = TestName;
+        type Basic = TestBasic;
+        type Literal = TestLiteral;
+        type Int = u8;
         type Var = TestVar;
         type Prop = TestProp;
+
+        fn literal_basic(literal: &TestLiteral) -> TestBasic {
+            TestConfig::literal_basic(literal)
+        }
+
+        fn fresh_var(index: u64, hint: &str) -> TestVar {
+            TestConfig::fresh_var(index, hint)
+        }
+
+        fn int_basic(basic: &TestBasic) -> bool {
+            TestConfig::int_basic(basic)
+        }
+
+        fn literal_int(literal: &TestLiteral) -> Option<u8> {
+            TestConfig::literal_int(literal).and_then(|i| i.try_into().ok())
+        }
+    }
+
+    #[cfg(feature = "json-schema")]
+    impl crate::schema::SchemaConfig for NoInts {
+        fn basic_schema(basic: &TestBasic) -> serde_json::Value {
+            TestConfig::basic_schema(basic)
+        }
+
+        fn literal_schema(literal: &TestLiteral) -> serde_json::Value {
+            TestConfig::literal_schema(literal)
+        }
+
+        fn schema_basic(name: &str) -> Option<TestBasic> {
+            match name {
+                "integer" => Some("Int".into()),
+                name => TestConfig::schema_basic(name),
+            }
+        }
+
+        fn schema_literal(value: &serde_json::Value) -> Option<TestLiteral> {
+            match value.as_i64() {
+                Some(i) => Some(TestLiteral::Int(i)),
+                None => TestConfig::schema_literal(value),
+            }
+        }
     }
 
     #[test]
@@ -260,9 +661,12 @@ mod tests {
         let t1 = ctx.var("T1".into());
 
         let ty_int = Bdd::map_atoms(&ctx.arena, int, &|basic| -> &Type<'_, TestConfig> {
-            ctx.arena.alloc(Type::from_basics(&ctx.arena, Bdd::atom))
+            ctx.arena
+                .alloc(Type::from_basics(&ctx.arena, Bdd::atom(&ctx.arena, basic)))
         });
 
+        println!("int {ty_int:#?}, var {t1:#?}");
+
         /*
         println!("not boolean: {:#?}", ctx.not(boolean));
 
@@ -273,4 +677,2031 @@ mod tests {
 
         //println!("union {:#?}", ctx.inter([int, t1]));
     }
+
+    fn lit_ty<'a>(
+        ctx: &'a Context<TestConfig>,
+        literal: TestLiteral,
+    ) -> &'a Bdd<'a, TestConfig, Type<'a, TestConfig>> {
+        ctx.ty(Type::from_basics(&ctx.arena, ctx.literal(literal)))
+    }
+
+    fn basic<'a>(
+        ctx: &'a Context<TestConfig>,
+        name: &str,
+    ) -> &'a Bdd<'a, TestConfig, Type<'a, TestConfig>> {
+        ctx.ty(Type::from_basics(&ctx.arena, ctx.basic(name.into())))
+    }
+
+    fn pair<'a>(
+        ctx: &'a Context<TestConfig>,
+        l: &'a Bdd<'a, TestConfig, Type<'a, TestConfig>>,
+        r: &'a Bdd<'a, TestConfig, Type<'a, TestConfig>>,
+    ) -> &'a Bdd<'a, TestConfig, Type<'a, TestConfig>> {
+        ctx.ty(Type::from_products(&ctx.arena, ctx.product(l, r)))
+    }
+
+    fn arrow<'a>(
+        ctx: &'a Context<TestConfig>,
+        dom: &'a Bdd<'a, TestConfig, Type<'a, TestConfig>>,
+        cod: &'a Bdd<'a, TestConfig, Type<'a, TestConfig>>,
+    ) -> &'a Bdd<'a, TestConfig, Type<'a, TestConfig>> {
+        ctx.ty(Type::from_arrows(&ctx.arena, ctx.arrow(dom, cod)))
+    }
+
+    fn list<'a>(
+        ctx: &'a Context<TestConfig>,
+        ty: &'a Bdd<'a, TestConfig, Type<'a, TestConfig>>,
+    ) -> &'a Bdd<'a, TestConfig, Type<'a, TestConfig>> {
+        ctx.ty(Type::from_refrs(&ctx.arena, ctx.refr("List".into(), [ty])))
+    }
+
+    fn ints<'a>(
+        ctx: &'a Context<TestConfig>,
+        lo: i64,
+        hi: i64,
+    ) -> &'a Bdd<'a, TestConfig, Type<'a, TestConfig>> {
+        ctx.ty(Type::from_ints(&ctx.arena, ctx.interval(lo, hi)))
+    }
+
+    fn record<'a>(
+        ctx: &'a Context<TestConfig>,
+        open: Openness,
+        fields: Vec<(&str, &'a Bdd<'a, TestConfig, Type<'a, TestConfig>>)>,
+    ) -> &'a Bdd<'a, TestConfig, Type<'a, TestConfig>> {
+        ctx.ty(Type::from_records(
+            &ctx.arena,
+            ctx.record(open, fields.into_iter().map(|(p, ty)| (p.into(), ty))),
+        ))
+    }
+
+    #[test]
+    fn negation() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = ctx.basic("Int".into());
+        let boolean = ctx.basic("Boolean".into());
+
+        assert_eq!(*ctx.not(ctx.not(int)), *int);
+        assert_eq!(*ctx.inter([int, ctx.not(int)]), Bdd::Bot);
+        assert_eq!(
+            *ctx.not(ctx.union([int, boolean])),
+            *ctx.inter([ctx.not(int), ctx.not(boolean)])
+        );
+    }
+
+    #[test]
+    fn emptiness() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let int_or_bool = ctx.union([int, boolean]);
+
+        assert!(ctx.is_empty(ctx.inter([int, boolean])));
+        assert!(!ctx.is_empty(ctx.inter([int_or_bool, ctx.not(boolean)])));
+        assert!(ctx.is_subtype(int, int_or_bool));
+        assert!(!ctx.is_subtype(int_or_bool, int));
+
+        // A type rebuilt from the atoms of a basic BDD is the type of those basics.
+        let basics = ctx.basic("Int".into());
+        let rebuilt = Bdd::map_atoms(&ctx.arena, basics, &|basic| -> &Type<'_, TestConfig> {
+            ctx.arena
+                .alloc(Type::from_basics(&ctx.arena, Bdd::atom(&ctx.arena, basic)))
+        });
+        assert!(ctx.is_subtype(rebuilt, int) && ctx.is_subtype(int, rebuilt));
+        assert!(matches!(ctx.var("T1".into()), Bdd::Atom { .. }));
+
+        assert!(ctx.is_subtype(pair(&ctx, int, int), pair(&ctx, int_or_bool, ctx.top())));
+        assert!(ctx.is_subtype(
+            pair(&ctx, int_or_bool, int),
+            ctx.union([pair(&ctx, int, int), pair(&ctx, boolean, int)])
+        ));
+        assert!(!ctx.is_subtype(
+            pair(&ctx, int_or_bool, int_or_bool),
+            ctx.union([pair(&ctx, int, int), pair(&ctx, boolean, boolean)])
+        ));
+        assert!(ctx.is_empty(pair(&ctx, int, ctx.bot())));
+
+        assert!(ctx.is_subtype(arrow(&ctx, int_or_bool, int), arrow(&ctx, int, int_or_bool)));
+        assert!(!ctx.is_subtype(arrow(&ctx, int, int), arrow(&ctx, int_or_bool, int)));
+        assert!(ctx.is_subtype(
+            ctx.inter([arrow(&ctx, int, int), arrow(&ctx, boolean, boolean)]),
+            arrow(&ctx, int_or_bool, int_or_bool)
+        ));
+        assert!(!ctx.is_subtype(
+            ctx.inter([arrow(&ctx, int, int), arrow(&ctx, boolean, boolean)]),
+            arrow(&ctx, int_or_bool, int)
+        ));
+
+        let closed_xy = record(&ctx, Openness::Closed, vec![("x", int), ("y", boolean)]);
+        let open_x = record(&ctx, Openness::Open, vec![("x", int)]);
+        let closed_x = record(&ctx, Openness::Closed, vec![("x", int)]);
+        assert!(ctx.is_subtype(closed_xy, open_x));
+        assert!(!ctx.is_subtype(open_x, closed_xy));
+        assert!(!ctx.is_subtype(closed_xy, closed_x));
+        assert!(ctx.is_subtype(closed_x, open_x));
+        assert!(ctx.is_empty(ctx.inter([closed_x, closed_xy])));
+        assert!(!ctx.is_empty(ctx.diff(open_x, closed_x)));
+    }
+
+    #[test]
+    fn match_branches() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let int_or_bool = ctx.union([int, boolean]);
+        let pair = ctx.ty(Type::from_products(
+            &ctx.arena,
+            ctx.product(int_or_bool, int_or_bool),
+        ));
+
+        let int_left = ctx.pat_pair(ctx.pat_basic("Int".into()), ctx.pat_wildcard());
+        let bool_right = ctx.pat_pair(ctx.pat_wildcard(), ctx.pat_test(boolean));
+        let any_pair = ctx.pat_pair(ctx.pat_wildcard(), ctx.pat_wildcard());
+
+        let branches = ctx.branches(pair, [int_left, bool_right]);
+        assert!(!branches.exhaustive);
+        assert!(branches.redundant.is_empty());
+        assert!(ctx.is_subtype(
+            branches.narrowed[1],
+            ctx.ty(Type::from_products(
+                &ctx.arena,
+                ctx.product(boolean, boolean)
+            ))
+        ));
+        assert!(ctx.is_subtype(
+            branches.residual,
+            ctx.ty(Type::from_products(&ctx.arena, ctx.product(boolean, int)))
+        ));
+
+        let branches = ctx.branches(pair, [any_pair, int_left, bool_right]);
+        assert!(branches.exhaustive);
+        assert_eq!(branches.redundant, vec![1, 2]);
+
+        let has_x = ctx.pat_record(Openness::Open, [("x".into(), ctx.pat_test(int))]);
+        let record = ctx.ty(Type::from_records(
+            &ctx.arena,
+            ctx.record(Openness::Closed, [("x".into(), int_or_bool)]),
+        ));
+        let branches = ctx.branches(record, [has_x, ctx.pat_wildcard()]);
+        assert!(branches.exhaustive);
+        assert!(branches.redundant.is_empty());
+        assert!(ctx.is_subtype(
+            branches.narrowed[1],
+            ctx.ty(Type::from_records(
+                &ctx.arena,
+                ctx.record(Openness::Open, [("x".into(), boolean)]),
+            ))
+        ));
+    }
+
+    #[test]
+    fn narrowing() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let circle_tag = basic(&ctx, "Circle");
+        let square_tag = basic(&ctx, "Square");
+        let int_or_bool = ctx.union([int, boolean]);
+
+        let (then_ty, else_ty) = ctx.narrow(
+            int_or_bool,
+            &Test {
+                path: vec![],
+                ty: int,
+            },
+        );
+        assert!(ctx.is_subtype(then_ty, int) && ctx.is_subtype(int, then_ty));
+        assert!(ctx.is_subtype(else_ty, boolean) && ctx.is_subtype(boolean, else_ty));
+
+        let circle = record(
+            &ctx,
+            Openness::Closed,
+            vec![("kind", circle_tag), ("radius", int)],
+        );
+        let square = record(
+            &ctx,
+            Openness::Closed,
+            vec![("kind", square_tag), ("side", int)],
+        );
+        let shape = ctx.union([circle, square]);
+
+        let (then_ty, else_ty) = ctx.narrow(
+            shape,
+            &Test {
+                path: vec![Step::Field("kind".into())],
+                ty: circle_tag,
+            },
+        );
+        assert!(ctx.is_subtype(then_ty, circle) && ctx.is_subtype(circle, then_ty));
+        assert!(ctx.is_subtype(else_ty, square) && ctx.is_subtype(square, else_ty));
+
+        let (then_ty, else_ty) = ctx.narrow(
+            ctx.union([pair(&ctx, shape, int), boolean]),
+            &Test {
+                path: vec![Step::Left, Step::Field("kind".into())],
+                ty: square_tag,
+            },
+        );
+        assert!(ctx.is_subtype(then_ty, pair(&ctx, square, int)));
+        assert!(ctx.is_subtype(pair(&ctx, square, int), then_ty));
+        assert!(ctx.is_subtype(else_ty, pair(&ctx, circle, int)));
+        assert!(ctx.is_subtype(pair(&ctx, circle, int), else_ty));
+    }
+
+    #[test]
+    fn literals() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let lit = |literal| ctx.ty(Type::from_basics(&ctx.arena, ctx.literal(literal)));
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let zero = lit(TestLiteral::Int(0));
+        let one = lit(TestLiteral::Int(1));
+        let yes = lit(TestLiteral::Bool(true));
+        let no = lit(TestLiteral::Bool(false));
+
+        assert!(ctx.is_subtype(zero, int));
+        assert!(!ctx.is_subtype(int, zero));
+        assert!(ctx.is_empty(ctx.inter([zero, one])));
+        assert!(ctx.is_empty(ctx.inter([zero, boolean])));
+        assert!(!ctx.is_empty(ctx.inter([zero, int])));
+
+        let nonzero = ctx.diff(int, zero);
+        assert!(ctx.is_subtype(one, nonzero));
+        assert!(!ctx.is_subtype(zero, nonzero));
+        assert!(!ctx.is_empty(nonzero));
+        assert!(ctx.is_subtype(int, ctx.union([nonzero, zero])));
+
+        assert!(ctx.is_subtype(boolean, ctx.union([yes, no])));
+        assert!(ctx.is_empty(ctx.diff(boolean, ctx.union([yes, no]))));
+        assert!(!ctx.is_empty(ctx.diff(int, ctx.union([zero, one]))));
+
+        let branches = ctx.branches(
+            boolean,
+            [
+                ctx.pat_literal(TestLiteral::Bool(true)),
+                ctx.pat_literal(TestLiteral::Bool(false)),
+            ],
+        );
+        assert!(branches.exhaustive);
+
+        let tagged = |tag: &str, ty| {
+            ctx.ty(Type::from_records(
+                &ctx.arena,
+                ctx.record(
+                    Openness::Closed,
+                    [
+                        ("kind".into(), lit(TestLiteral::Str(tag.into()))),
+                        ("value".into(), ty),
+                    ],
+                ),
+            ))
+        };
+        let (then_ty, else_ty) = ctx.narrow(
+            ctx.union([tagged("int", int), tagged("bool", boolean)]),
+            &Test {
+                path: vec![Step::Field("kind".into())],
+                ty: lit(TestLiteral::Str("int".into())),
+            },
+        );
+        assert!(ctx.is_subtype(then_ty, tagged("int", int)));
+        assert!(ctx.is_subtype(else_ty, tagged("bool", boolean)));
+    }
+
+    #[test]
+    fn intervals() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let byte = ints(&ctx, 0, 255);
+        let nat = ints(&ctx, 0, i64::MAX);
+        let any_int = ints(&ctx, i64::MIN, i64::MAX);
+
+        assert!(ctx.is_subtype(byte, nat));
+        assert!(!ctx.is_subtype(nat, byte));
+        assert!(ctx.is_subtype(ctx.union([ints(&ctx, 0, 9), ints(&ctx, 10, 255)]), byte));
+        assert!(ctx.is_subtype(byte, ctx.union([ints(&ctx, 0, 9), ints(&ctx, 10, 255)])));
+        assert!(ctx.is_empty(ctx.inter([ints(&ctx, 0, 9), ints(&ctx, 10, 255)])));
+        assert!(ctx.is_subtype(any_int, ctx.union([ints(&ctx, i64::MIN, -1), nat])));
+
+        // Intervals are integers, as are the integer literals.
+        let int = basic(&ctx, "Int");
+        assert!(ctx.is_subtype(byte, int));
+        assert!(ctx.is_subtype(int, any_int) && ctx.is_subtype(any_int, int));
+        assert!(!ctx.is_subtype(int, byte));
+        let lit = |i| lit_ty(&ctx, TestLiteral::Int(i));
+        assert!(ctx.is_subtype(lit(7), byte));
+        assert!(!ctx.is_empty(ctx.inter([lit(255), byte])));
+        assert!(ctx.is_empty(ctx.inter([lit(256), byte])));
+        assert!(ctx.is_subtype(ints(&ctx, 7, 7), lit(7)));
+        assert!(ctx.is_subtype(
+            byte,
+            ctx.union([ints(&ctx, 0, 6), lit(7), ints(&ctx, 8, 255)])
+        ));
+        assert!(ctx.is_empty(ctx.inter([byte, basic(&ctx, "String")])));
+        let not_int = ctx.not(int);
+        assert!(ctx.is_empty(ctx.inter([not_int, byte])));
+        assert!(!ctx.is_empty(ctx.inter([not_int, basic(&ctx, "String")])));
+
+        let flat = Bdd::flatten(&ctx.arena, ctx.diff(byte, ints(&ctx, 10, 19)));
+        assert_eq!(flat.ints.ranges.as_slice(), &[(0, 9), (20, 255)]);
+        let flat = Bdd::flatten(&ctx.arena, ctx.not(nat));
+        assert_eq!(flat.ints.ranges.as_slice(), &[(i64::MIN, -1)]);
+
+        // Without intervals, every interval is empty.
+        let ctx: Context<NoInts> = Context::new();
+        let byte = ctx.ty(Type::from_ints(&ctx.arena, ctx.interval(0, 255)));
+        assert!(ctx.is_empty(byte));
+        let int = ctx.ty(Type::from_basics(&ctx.arena, ctx.basic("Int".into())));
+        assert!(!ctx.is_empty(int));
+    }
+
+    #[test]
+    fn variables() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+        let b = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("b".into())));
+
+        assert!(ctx.is_subtype(a, ctx.union([a, int])));
+        assert!(!ctx.is_empty(ctx.inter([a, int])));
+        assert!(ctx.is_empty(ctx.inter([a, ctx.not(a)])));
+        assert!(!ctx.is_subtype(int, a));
+        assert!(!ctx.is_subtype(a, b));
+        assert!(ctx.is_subtype(ctx.inter([a, b]), a));
+        assert!(ctx.is_subtype(pair(&ctx, a, int), pair(&ctx, ctx.union([a, b]), ctx.top())));
+        assert!(!ctx.is_subtype(pair(&ctx, a, int), pair(&ctx, b, int)));
+
+        let substituted = Bdd::subst(&ctx.arena, pair(&ctx, a, b), &|var| {
+            (*var == crate::bdd::Var::Named("a".into())).then_some(int)
+        });
+        assert!(ctx.is_subtype(substituted, pair(&ctx, int, b)));
+        assert!(ctx.is_subtype(pair(&ctx, int, b), substituted));
+
+        let fresh = ctx.fresh_var("a");
+        assert_ne!(fresh, "a".into());
+        assert_ne!(fresh, ctx.fresh_var("a"));
+        let fresh = ctx.ty(Type::from_vars(&ctx.arena, ctx.var(fresh)));
+        assert!(!ctx.is_subtype(fresh, a));
+
+        // Variables are eliminated clause by clause, so many of them don't blow up.
+        let many: Vec<_> = (0..70)
+            .map(|i| {
+                ctx.ty(Type::from_vars(
+                    &ctx.arena,
+                    ctx.var(format!("v{i}").as_str().into()),
+                ))
+            })
+            .collect();
+        assert!(!ctx.is_empty(ctx.inter(many.iter().copied())));
+        assert!(ctx.is_empty(ctx.inter(many.iter().copied().chain([ctx.not(many[69])]))));
+        assert!(ctx.is_subtype(
+            ctx.inter(many.iter().copied()),
+            ctx.union(many.iter().copied())
+        ));
+        assert!(!ctx.is_subtype(ctx.union(many.iter().copied()), many[0]));
+    }
+
+    #[test]
+    fn operators() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let int_or_bool = ctx.union([int, boolean]);
+        let equiv = |t1, t2| ctx.is_subtype(t1, t2) && ctx.is_subtype(t2, t1);
+
+        let defs = Defs::new();
+        let solver = ctx.solver(&defs);
+
+        let overloaded = ctx.inter([arrow(&ctx, int, int), arrow(&ctx, boolean, boolean)]);
+        assert!(equiv(Bdd::domain(&solver, overloaded), int_or_bool));
+        assert!(equiv(Bdd::apply(&solver, overloaded, int), int));
+        assert!(equiv(
+            Bdd::apply(&solver, overloaded, int_or_bool),
+            int_or_bool
+        ));
+
+        let pairs = ctx.union([pair(&ctx, int, boolean), pair(&ctx, boolean, int)]);
+        assert!(equiv(Bdd::proj_left(&solver, pairs), int_or_bool));
+        assert!(equiv(
+            Bdd::proj_right(&solver, ctx.diff(pairs, pair(&ctx, int, ctx.top()))),
+            int
+        ));
+
+        let vars: Vec<_> = (0..70)
+            .map(|i| {
+                ctx.ty(Type::from_vars(
+                    &ctx.arena,
+                    ctx.var(format!("v{i}").as_str().into()),
+                ))
+            })
+            .collect();
+        let arrows = ctx.inter(vars.iter().map(|&var| arrow(&ctx, var, int)));
+        assert!(equiv(Bdd::apply(&solver, arrows, boolean), int));
+        let escaped = vars.iter().map(|&var| ctx.not(pair(&ctx, var, var)));
+        let pairs = ctx.inter(escaped.chain([pair(&ctx, int, boolean)]));
+        assert!(equiv(Bdd::proj_left(&solver, pairs), int));
+        assert!(equiv(Bdd::proj_right(&solver, pairs), boolean));
+    }
+
+    #[test]
+    fn gradual_types() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let dynamic = ctx.dynamic();
+        let equiv = |t1, t2| ctx.is_subtype(t1, t2) && ctx.is_subtype(t2, t1);
+
+        assert!(ctx.is_consistent_subtype(dynamic, int));
+        assert!(ctx.is_consistent_subtype(int, dynamic));
+        assert!(ctx.is_consistent_subtype(arrow(&ctx, dynamic, int), arrow(&ctx, boolean, int)));
+        assert!(!ctx.is_consistent_subtype(arrow(&ctx, int, int), arrow(&ctx, boolean, dynamic)));
+        assert!(ctx.is_consistent_subtype(ctx.inter([dynamic, int]), boolean));
+        assert!(!ctx.is_consistent_subtype(int, ctx.inter([dynamic, boolean])));
+        assert!(!ctx.is_consistent_subtype(ctx.union([dynamic, int]), boolean));
+
+        let nil = basic(&ctx, "Nil");
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+        let named = |name: &str, args: Vec<_>| {
+            ctx.ty(Type::from_refrs(&ctx.arena, ctx.refr(name.into(), args)))
+        };
+        let mut defs = Defs::new();
+        defs.define("Sink".into(), vec!["a".into()], arrow(&ctx, a, nil));
+        defs.define(
+            "Cell".into(),
+            vec!["a".into()],
+            pair(&ctx, a, arrow(&ctx, a, nil)),
+        );
+        let solver = ctx.solver(&defs);
+        let upper = |ty| gradual::materialize(&solver, ty, true);
+        let lower = |ty| gradual::materialize(&solver, ty, false);
+        let same = |t1, t2| solver.is_subtype(t1, t2) && solver.is_subtype(t2, t1);
+        assert!(equiv(upper(ctx.not(dynamic)), ctx.top()));
+        assert!(equiv(lower(ctx.not(dynamic)), ctx.bot()));
+        assert!(equiv(
+            upper(arrow(&ctx, dynamic, int)),
+            arrow(&ctx, ctx.bot(), int)
+        ));
+
+        assert!(
+            ctx.is_consistent_subtype(boolean, gradual::domain(&solver, arrow(&ctx, dynamic, int)))
+        );
+        assert!(equiv(
+            gradual::apply(&solver, arrow(&ctx, dynamic, int), boolean),
+            int
+        ));
+        let result = gradual::apply(&solver, arrow(&ctx, int, dynamic), int);
+        assert!(equiv(lower(result), ctx.bot()));
+        assert!(equiv(upper(result), ctx.top()));
+        let left = gradual::proj_left(&solver, pair(&ctx, ctx.inter([dynamic, int]), boolean));
+        assert!(equiv(lower(left), ctx.bot()));
+        assert!(equiv(upper(left), int));
+
+        // Arguments of references follow the variance of the parameter, and `?` is kept where
+        // it is invariant.
+        let sink = |ty| named("Sink", vec![ty]);
+        let cell = |ty| named("Cell", vec![ty]);
+        assert!(same(upper(sink(dynamic)), sink(ctx.bot())));
+        assert!(same(lower(sink(dynamic)), sink(ctx.top())));
+        assert!(same(
+            upper(ctx.not(sink(dynamic))),
+            ctx.not(sink(ctx.top()))
+        ));
+        assert!(same(upper(cell(dynamic)), cell(dynamic)));
+    }
+
+    #[test]
+    fn named_types() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let nil = basic(&ctx, "Nil");
+        let truth = ctx.ty(Type::from_basics(
+            &ctx.arena,
+            ctx.literal(TestLiteral::Bool(true)),
+        ));
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+        let named = |name: &str, args: Vec<_>| {
+            ctx.ty(Type::from_refrs(&ctx.arena, ctx.refr(name.into(), args)))
+        };
+        let func = |ty| named("Func", vec![ty]);
+
+        let mut defs = Defs::new();
+        defs.define(
+            "List".into(),
+            vec!["a".into()],
+            ctx.union([nil, pair(&ctx, a, list(&ctx, a))]),
+        );
+        defs.define("Func".into(), vec!["a".into()], arrow(&ctx, a, int));
+        defs.define("Cell".into(), vec!["a".into()], pair(&ctx, a, func(a)));
+        defs.define("Const".into(), vec!["a".into()], int);
+        defs.define("Loop".into(), vec![], named("Loop", vec![]));
+        let variances = |name: &str| defs.get(&name.into()).unwrap().variances.clone();
+        assert_eq!(variances("List"), [Variance::Covariant]);
+        assert_eq!(variances("Func"), [Variance::Contravariant]);
+        assert_eq!(variances("Cell"), [Variance::Invariant]);
+        assert_eq!(variances("Const"), [Variance::Bivariant]);
+
+        let solver = ctx.solver(&defs);
+        assert!(solver.is_subtype(list(&ctx, truth), list(&ctx, boolean)));
+        assert!(!solver.is_subtype(list(&ctx, boolean), list(&ctx, truth)));
+        assert!(solver.is_subtype(func(boolean), func(truth)));
+        assert!(!solver.is_subtype(func(truth), func(boolean)));
+        assert!(!solver.is_subtype(named("Cell", vec![truth]), named("Cell", vec![boolean])));
+        assert!(solver.is_subtype(named("Const", vec![int]), named("Const", vec![boolean])));
+
+        let unfolded = ctx.union([nil, pair(&ctx, int, list(&ctx, int))]);
+        assert!(solver.is_subtype(list(&ctx, int), unfolded));
+        assert!(solver.is_subtype(unfolded, list(&ctx, int)));
+        assert!(solver.is_empty(ctx.inter([list(&ctx, int), int])));
+        assert!(solver.is_empty(named("Loop", vec![])));
+
+        // Without definitions, named types are opaque.
+        assert!(ctx.is_subtype(list(&ctx, int), list(&ctx, int)));
+        assert!(!ctx.is_subtype(list(&ctx, truth), list(&ctx, boolean)));
+        assert!(!ctx.is_empty(named("Loop", vec![])));
+    }
+
+    #[test]
+    fn opaque_types() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let string = basic(&ctx, "String");
+        let truth = ctx.ty(Type::from_basics(
+            &ctx.arena,
+            ctx.literal(TestLiteral::Bool(true)),
+        ));
+        let boolean = basic(&ctx, "Boolean");
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+        let named = |name: &str, args: Vec<_>| {
+            ctx.ty(Type::from_refrs(&ctx.arena, ctx.refr(name.into(), args)))
+        };
+        let user_id = named("UserId", vec![]);
+        let email = named("Email", vec![]);
+        let tagged = |ty| named("Tagged", vec![ty]);
+
+        let mut defs = Defs::new();
+        defs.define_opaque("UserId".into(), vec![], int);
+        defs.define_opaque("Email".into(), vec![], string);
+        defs.define_opaque("Tagged".into(), vec!["a".into()], a);
+        defs.define("Alias".into(), vec![], int);
+
+        let solver = ctx.solver(&defs);
+        assert!(!solver.is_subtype(user_id, int));
+        assert!(!solver.is_subtype(int, user_id));
+        assert!(!solver.is_empty(user_id));
+        assert!(!solver.is_empty(ctx.inter([user_id, email])));
+        assert!(solver.is_subtype(named("Alias", vec![]), int));
+        assert!(solver.is_subtype(tagged(truth), tagged(boolean)));
+        assert!(!solver.is_subtype(tagged(boolean), tagged(truth)));
+
+        let refr = ctx.refr("UserId".into(), []);
+        let Bdd::Atom { atom, .. } = refr else {
+            unreachable!()
+        };
+        assert_eq!(defs.unfold(&ctx.arena, atom), Some(int));
+
+        let solver = ctx.solver(&defs).reveal("UserId".into());
+        assert!(solver.is_subtype(user_id, int));
+        assert!(solver.is_subtype(int, user_id));
+        assert!(!solver.is_subtype(email, string));
+    }
+
+    #[test]
+    fn tallying() {
+        let ctx: Context<TestConfig> = Context::new();
+        let defs = Defs::new();
+        let solver = ctx.solver(&defs);
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+        let b = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("b".into())));
+
+        let flexible = |var: &TestVar| *var == "a".into() || *var == "b".into();
+        let tally = Tally::new(&solver, &flexible);
+        let problem = [
+            (arrow(&ctx, int, int), arrow(&ctx, a, b)),
+            (int, a),
+            (pair(&ctx, b, b), pair(&ctx, boolean, boolean)),
+        ];
+        assert!(tally.solve(&problem).is_empty());
+        let problem = [(arrow(&ctx, int, int), arrow(&ctx, a, b))];
+        let solutions = tally.solve(&problem);
+        assert!(!solutions.is_empty());
+        for solution in &solutions {
+            let target = solution.apply(&ctx.arena, arrow(&ctx, a, b));
+            assert!(ctx.is_subtype(arrow(&ctx, int, int), target));
+        }
+
+        let rigid = |_: &TestVar| false;
+        let tally = Tally::new(&solver, &rigid);
+        assert!(tally.solve(&problem).is_empty());
+        assert_eq!(tally.solve(&[(a, ctx.union([a, b]))]).len(), 1);
+
+        let many: Vec<_> = (0..70)
+            .map(|i| {
+                ctx.ty(Type::from_vars(
+                    &ctx.arena,
+                    ctx.var(format!("v{i}").as_str().into()),
+                ))
+            })
+            .collect();
+        let all = ctx.inter(many.iter().copied());
+        assert_eq!(tally.solve(&[(all, many[69])]).len(), 1);
+        assert!(tally.solve(&[(many[69], all)]).is_empty());
+    }
+
+    #[test]
+    fn schemes() {
+        let ctx: Context<TestConfig> = Context::new();
+        let defs = Defs::new();
+        let solver = ctx.solver(&defs);
+
+        let int = basic(&ctx, "Int");
+        let float = basic(&ctx, "Float");
+        let string = basic(&ctx, "String");
+        let number = ctx.union([int, float]);
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+        let b = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("b".into())));
+
+        let identity = Scheme {
+            quantified: vec![Quantified::new(&ctx.arena, "a".into())],
+            body: arrow(&ctx, a, a),
+        };
+        let numeric = Scheme {
+            quantified: vec![Quantified::new(&ctx.arena, "a".into()).with_upper(number)],
+            body: arrow(&ctx, a, a),
+        };
+        let on_ints = Scheme::mono(arrow(&ctx, int, int));
+        let on_strings = Scheme::mono(arrow(&ctx, string, string));
+
+        // Instances get their variables from the context.
+        let (instance, vars) = identity.instantiate(&ctx);
+        assert_eq!(vars.len(), 1);
+        assert!(vars[0] != "a".into() && vars[0] != ctx.fresh_var("a"));
+        assert!(!ctx.is_subtype(instance, arrow(&ctx, a, a)));
+        assert!(identity.subsumes(&solver, &on_ints));
+        assert!(!on_ints.subsumes(&solver, &identity));
+        assert!(numeric.subsumes(&solver, &on_ints));
+        assert!(!numeric.subsumes(&solver, &on_strings));
+        assert!(identity.subsumes(&solver, &numeric));
+        assert!(!numeric.subsumes(&solver, &identity));
+
+        let generalized = Scheme::generalize(&ctx.arena, arrow(&ctx, a, b), [b]);
+        assert_eq!(generalized.vars().collect::<Vec<_>>(), [&"a".into()]);
+
+        // Bounds mentioning other quantified variables keep the bounds of those too.
+        let nested = Scheme {
+            quantified: vec![
+                Quantified::new(&ctx.arena, "b".into()).with_upper(a),
+                Quantified::new(&ctx.arena, "a".into()).with_upper(number),
+            ],
+            body: b,
+        };
+        let (instance, _) = nested.instantiate(&ctx);
+        assert!(ctx.is_subtype(instance, number));
+        assert!(!ctx.is_empty(instance));
+        let cyclic = Scheme {
+            quantified: vec![
+                Quantified::new(&ctx.arena, "a".into()).with_upper(ctx.union([b, int])),
+                Quantified::new(&ctx.arena, "b".into()).with_upper(ctx.union([a, float])),
+            ],
+            body: a,
+        };
+        let (instance, vars) = cyclic.instantiate(&ctx);
+        let fresh: Vec<_> = vars
+            .into_iter()
+            .map(|var| ctx.ty(Type::from_vars(&ctx.arena, ctx.var(var))))
+            .collect();
+        assert!(ctx.is_subtype(instance, ctx.inter([fresh[0], ctx.union([fresh[1], int])])));
+        assert!(!ctx.is_empty(instance));
+    }
+
+    #[test]
+    fn polarities() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let nil = basic(&ctx, "Nil");
+        let var = |name: &str| ctx.ty(Type::from_vars(&ctx.arena, ctx.var(name.into())));
+        let (a, b, c, d) = (var("a"), var("b"), var("c"), var("d"));
+
+        let ty = ctx.inter([
+            arrow(&ctx, ctx.inter([a, int]), ctx.union([a, b])),
+            ctx.not(arrow(&ctx, c, int)),
+            list(&ctx, d),
+        ]);
+        let free: Vec<&TestVar> = ty.free_vars();
+        assert_eq!(free, [&"a".into(), &"b".into(), &"c".into(), &"d".into()]);
+
+        let mut defs = Defs::new();
+        let polarities = ty.polarities(&defs);
+        assert_eq!(polarities[&TestVar::from("a")], Polarity::Both);
+        assert_eq!(polarities[&TestVar::from("b")], Polarity::Positive);
+        assert_eq!(polarities[&TestVar::from("c")], Polarity::Positive);
+        assert_eq!(polarities[&TestVar::from("d")], Polarity::Both);
+
+        defs.define(
+            "List".into(),
+            vec!["a".into()],
+            ctx.union([nil, pair(&ctx, a, list(&ctx, a))]),
+        );
+        assert_eq!(
+            ty.polarities(&defs)[&TestVar::from("d")],
+            Polarity::Positive
+        );
+        let polarities = ctx
+            .not(arrow(&ctx, ctx.inter([a, int]), ctx.union([a, b])))
+            .polarities(&defs);
+        assert_eq!(polarities[&TestVar::from("b")], Polarity::Negative);
+        assert!(polarities[&TestVar::from("a")].is_negative());
+    }
+
+    #[test]
+    fn dnf_clauses() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let var = |name: &str| ctx.ty(Type::from_vars(&ctx.arena, ctx.var(name.into())));
+
+        let ty = ctx.union([
+            ctx.inter([arrow(&ctx, int, int), ctx.not(arrow(&ctx, boolean, int))]),
+            ctx.diff(var("a"), arrow(&ctx, int, boolean)),
+            arrow(&ctx, boolean, boolean),
+        ]);
+        let clauses: Vec<_> = ty.dnf().collect();
+        assert!(clauses.len() >= 3);
+        let rebuilt = clauses.iter().fold(ctx.bot(), |acc, (pos, neg)| {
+            let clause = pos.iter().fold(ctx.top(), |acc, atom| {
+                Bdd::inter(&ctx.arena, acc, Bdd::atom(&ctx.arena, atom))
+            });
+            let clause = neg.iter().fold(clause, |acc, atom| {
+                Bdd::diff(&ctx.arena, acc, Bdd::atom(&ctx.arena, atom))
+            });
+            Bdd::union(&ctx.arena, acc, clause)
+        });
+        assert!(ctx.is_subtype(ty, rebuilt) && ctx.is_subtype(rebuilt, ty));
+
+        assert_eq!(ctx.bot().dnf().count(), 0);
+        assert_eq!(ctx.top().dnf().collect::<Vec<_>>(), [(vec![], vec![])]);
+    }
+
+    #[test]
+    fn folds_and_visitors() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let string = basic(&ctx, "String");
+        let boolean = basic(&ctx, "Boolean");
+        let var = |name: &str| ctx.ty(Type::from_vars(&ctx.arena, ctx.var(name.into())));
+
+        // The same pair is shared by both sides of the arrow and by the union.
+        let shared = pair(&ctx, var("a"), string);
+        let ty = ctx.union([arrow(&ctx, shared, shared), shared]);
+
+        struct Products(usize);
+        impl<'a> TypeVisitor<'a, TestConfig> for Products {
+            fn visit_product(
+                &mut self,
+                _: &'a Product<'a, TestConfig, Type<'a, TestConfig>>,
+            ) -> bool {
+                self.0 += 1;
+                true
+            }
+        }
+        let mut products = Products(0);
+        ty.visit(&mut products);
+        assert_eq!(products.0, 1);
+
+        // Replaces `a` with `Boolean` and `String` with `Nil`.
+        struct Replace;
+        impl<'a> BddFold<'a, TestConfig> for Replace {
+            fn fold_var(
+                &mut self,
+                arena: &'a bumpalo::Bump,
+                var: &'a Var<TestConfig>,
+            ) -> &'a Bdd<'a, TestConfig, Type<'a, TestConfig>> {
+                let ty = match var {
+                    Var::Named(TestVar(name)) if name == "a" => Type::from_basics(
+                        arena,
+                        Bdd::atom(arena, arena.alloc(Basic::All("Boolean".into()))),
+                    ),
+                    _ => Type::from_vars(arena, Bdd::atom(arena, var)),
+                };
+                Bdd::atom(arena, arena.alloc(ty))
+            }
+
+            fn fold_basic(
+                &mut self,
+                arena: &'a bumpalo::Bump,
+                basic: &'a Basic<TestConfig>,
+            ) -> &'a Bdd<'a, TestConfig, Basic<TestConfig>> {
+                match basic {
+                    Basic::All(TestBasic(name)) if name == "String" => {
+                        Bdd::atom(arena, arena.alloc(Basic::All("Nil".into())))
+                    }
+                    _ => Bdd::atom(arena, basic),
+                }
+            }
+        }
+        let folded = Bdd::fold(&ctx.arena, ty, &mut Replace);
+        let nil = basic(&ctx, "Nil");
+        let expected = pair(&ctx, boolean, nil);
+        let expected = ctx.union([arrow(&ctx, expected, expected), expected]);
+        assert!(ctx.is_subtype(folded, expected) && ctx.is_subtype(expected, folded));
+
+        struct Identity;
+        impl<'a> BddFold<'a, TestConfig> for Identity {}
+        let unchanged = Bdd::fold(&ctx.arena, ty, &mut Identity);
+        assert!(ctx.is_subtype(unchanged, ty) && ctx.is_subtype(ty, unchanged));
+    }
+
+    #[test]
+    fn type_keys() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+
+        let a = ctx.key(pair(&ctx, int, boolean));
+        let b = ctx.key(pair(&ctx, basic(&ctx, "Int"), basic(&ctx, "Boolean")));
+        let c = ctx.key(pair(&ctx, boolean, int));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(ctx.key(int), ctx.key(boolean));
+
+        let cache: HashMap<TypeKey, &str> =
+            HashMap::from([(a, "int, boolean"), (c, "boolean, int")]);
+        assert_eq!(cache[&ctx.key(pair(&ctx, int, boolean))], "int, boolean");
+
+        // Shared nodes are keyed once, so deeply shared types are keyed quickly.
+        let mut ty = int;
+        for _ in 0..200 {
+            ty = ctx.union([pair(&ctx, ty, ty), boolean]);
+        }
+        let mut same = int;
+        for _ in 0..200 {
+            same = ctx.union([pair(&ctx, same, same), boolean]);
+        }
+        assert_eq!(ctx.key(ty), ctx.key(same));
+        assert_ne!(ctx.key(ty), ctx.key(pair(&ctx, ty, ty)));
+
+        // Types of other arenas are keyed by structure, even once their addresses are reused.
+        let mut arena = bumpalo::Bump::with_capacity(1 << 12);
+        for name in ["String", "Boolean", "String"] {
+            arena.reset();
+            let basics = Bdd::atom(&arena, arena.alloc(Basic::All(name.into())));
+            let ty = Bdd::atom(&arena, arena.alloc(Type::from_basics(&arena, basics)));
+            assert_eq!(ctx.key(ty), ctx.key(basic(&ctx, name)));
+        }
+    }
+
+    #[test]
+    fn canonical_forms() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let nil = basic(&ctx, "Nil");
+        let var = |name: &str| ctx.ty(Type::from_vars(&ctx.arena, ctx.var(name.into())));
+
+        let defs = Defs::new();
+        let solver = ctx.solver(&defs);
+        let canon = Canonicalizer::new(&solver);
+
+        let t1 = ctx.union([
+            pair(&ctx, int, boolean),
+            pair(&ctx, int, nil),
+            arrow(&ctx, int, int),
+        ]);
+        let t2 = ctx.union([
+            arrow(&ctx, int, int),
+            pair(&ctx, int, ctx.union([nil, boolean])),
+        ]);
+        assert_ne!(*t1, *t2);
+        assert_eq!(*canon.canonicalize(t1), *canon.canonicalize(t2));
+        assert_ne!(
+            *canon.canonicalize(t1),
+            *canon.canonicalize(pair(&ctx, int, int))
+        );
+
+        let a = var("a");
+        assert_eq!(
+            *canon.canonicalize(ctx.union([ctx.inter([a, int]), ctx.diff(int, a)])),
+            *canon.canonicalize(int)
+        );
+        assert_eq!(
+            *canon.canonicalize(ctx.union([a, int])),
+            *canon.canonicalize(ctx.union([int, ctx.inter([a, ctx.not(int)])]))
+        );
+        assert_eq!(*canon.canonicalize(ctx.union([a, ctx.not(a)])), Bdd::Top);
+        assert_eq!(*canon.canonicalize(pair(&ctx, int, ctx.bot())), Bdd::Bot);
+
+        // Canonical forms don't depend on earlier calls.
+        let canon2 = Canonicalizer::new(&solver);
+        assert_eq!(*canon2.canonicalize(t2), *canon.canonicalize(t1));
+
+        let string = basic(&ctx, "String");
+        let yes = lit_ty(&ctx, TestLiteral::Bool(true));
+        let no = lit_ty(&ctx, TestLiteral::Bool(false));
+        let hello = lit_ty(&ctx, TestLiteral::Str("hello".into()));
+        assert_eq!(
+            *canon.canonicalize(ctx.union([yes, no, string])),
+            *canon.canonicalize(ctx.union([boolean, string]))
+        );
+        assert_eq!(
+            *canon.canonicalize(ctx.diff(ctx.union([string, nil]), ctx.union([hello, nil]))),
+            *canon.canonicalize(ctx.inter([string, ctx.not(hello)]))
+        );
+        assert_eq!(
+            *canon.canonicalize(ctx.diff(ctx.not(nil), ctx.diff(boolean, no))),
+            *canon.canonicalize(ctx.union([ctx.not(ctx.union([nil, boolean])), no]))
+        );
+
+        let int_or_bool = ctx.union([int, boolean]);
+        assert_eq!(
+            *canon.canonicalize(ctx.union([
+                record(&ctx, Openness::Open, vec![("x", int)]),
+                record(&ctx, Openness::Open, vec![("x", boolean)]),
+            ])),
+            *canon.canonicalize(record(&ctx, Openness::Open, vec![("x", int_or_bool)]))
+        );
+        assert_eq!(
+            *canon.canonicalize(ctx.union([
+                record(&ctx, Openness::Open, vec![]),
+                record(&ctx, Openness::Open, vec![("x", ctx.top())]),
+            ])),
+            *canon.canonicalize(record(&ctx, Openness::Open, vec![]))
+        );
+        assert_eq!(
+            *canon.canonicalize(ctx.union([
+                record(&ctx, Openness::Closed, vec![("x", int), ("y", boolean)]),
+                record(&ctx, Openness::Closed, vec![("x", int)]),
+            ])),
+            *canon.canonicalize(ctx.diff(
+                ctx.union([
+                    record(&ctx, Openness::Closed, vec![("x", int)]),
+                    record(&ctx, Openness::Closed, vec![("x", int), ("y", int_or_bool)]),
+                ]),
+                record(&ctx, Openness::Open, vec![("y", int)]),
+            ))
+        );
+        assert_ne!(
+            *canon.canonicalize(record(&ctx, Openness::Closed, vec![("x", int)])),
+            *canon.canonicalize(record(&ctx, Openness::Open, vec![("x", int)]))
+        );
+
+        assert_eq!(
+            *canon.canonicalize(ctx.inter([arrow(&ctx, int, int), arrow(&ctx, boolean, int)])),
+            *canon.canonicalize(arrow(&ctx, int_or_bool, int))
+        );
+        assert_eq!(
+            *canon.canonicalize(
+                ctx.inter([arrow(&ctx, int_or_bool, int_or_bool), arrow(&ctx, int, int)])
+            ),
+            *canon.canonicalize(
+                ctx.inter([arrow(&ctx, int, int), arrow(&ctx, boolean, int_or_bool)])
+            )
+        );
+
+        // Negated arrows aren't made unique: a function from integers to integers that isn't
+        // from integers to booleans is one that doesn't diverge on every integer, but the two
+        // descriptions stay different.
+        let returns_int = ctx.diff(arrow(&ctx, int, int), arrow(&ctx, int, boolean));
+        let terminates = ctx.diff(arrow(&ctx, int, int), arrow(&ctx, int, ctx.bot()));
+        assert!(ctx.is_subtype(returns_int, terminates) && ctx.is_subtype(terminates, returns_int));
+        assert_ne!(
+            *canon.canonicalize(returns_int),
+            *canon.canonicalize(terminates)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialization() {
+        use crate::{
+            serial::{DecodeError, Encoder, Interface},
+            ty::{self, Ty},
+        };
+
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let var = |name: &str| ctx.ty(Type::from_vars(&ctx.arena, ctx.var(name.into())));
+        let lit = |lit| ctx.ty(Type::from_basics(&ctx.arena, ctx.literal(lit)));
+        let ints = ctx.ty(Type::from_ints(&ctx.arena, ctx.interval(0, 9)));
+
+        let shared = pair(
+            &ctx,
+            var("a"),
+            ctx.union([int, lit(TestLiteral::Str("s".into()))]),
+        );
+        let t1 = ctx.union([arrow(&ctx, shared, shared), ctx.not(list(&ctx, ints))]);
+        let t2 = ctx.ty(Type::from_records(
+            &ctx.arena,
+            ctx.record(Openness::Open, [("x".into(), shared)]),
+        ));
+
+        let mut encoder = Encoder::new();
+        assert_eq!(encoder.add_type(t1), 0);
+        assert_eq!(encoder.add_type(t2), 1);
+        let leaf: &Ty<TestConfig> = ctx.arena.alloc(Ty::Var("a".into()));
+        let not: &Ty<TestConfig> = ctx.arena.alloc(Ty::Not(leaf));
+        assert_eq!(
+            encoder.add_ty(ctx.arena.alloc(Ty::Arrow(ty::Arrow(leaf, not)))),
+            0
+        );
+        let json = serde_json::to_string(&encoder.finish()).unwrap();
+        let interface: Interface<TestConfig> = serde_json::from_str(&json).unwrap();
+
+        let fresh: Context<TestConfig> = Context::new();
+        let decoded = interface.decode(&fresh).unwrap();
+        assert_eq!(*decoded.types[0], *t1);
+        assert_eq!(*decoded.types[1], *t2);
+        let Ty::Arrow(ty::Arrow(Ty::Var(TestVar(a)), Ty::Not(inner))) = decoded.tys[0] else {
+            panic!("not an arrow");
+        };
+        assert_eq!(a, "a");
+        let Ty::Arrow(ty::Arrow(leaf, _)) = decoded.tys[0] else {
+            unreachable!()
+        };
+        assert!(std::ptr::eq(*leaf, *inner));
+        // The pair shared by both types is decoded once.
+        let Bdd::Atom { atom, .. } = decoded.types[1] else {
+            panic!("not an atom");
+        };
+        let Bdd::Atom { atom: record, .. } = atom.records else {
+            panic!("not a record");
+        };
+        let mut pairs = Vec::new();
+        decoded.types[0].for_each_atom(&mut |ty| {
+            ty.arrows
+                .for_each_atom(&mut |arrow| pairs.extend([arrow.0, arrow.1]))
+        });
+        assert!(pairs.iter().all(|p| std::ptr::eq(*p, record.map[0].1)));
+
+        // So is an atom shared by two BDDs.
+        let f = ctx.arrow(int, int);
+        let g = ctx.arrow(ints, int);
+        let arrows = |bdd| ctx.ty(Type::from_arrows(&ctx.arena, bdd));
+        let mut encoder = Encoder::new();
+        encoder.add_type(arrows(ctx.union([f, g])));
+        encoder.add_type(arrows(ctx.diff(f, g)));
+        let decoded = encoder.finish().decode(&fresh).unwrap();
+        let mut atoms = Vec::new();
+        for ty in &decoded.types {
+            ty.for_each_atom(&mut |ty| ty.arrows.for_each_atom(&mut |arrow| atoms.push(arrow)));
+        }
+        assert_eq!(atoms.len(), 4);
+        for a in &atoms {
+            assert!(atoms.iter().all(|b| (**a == **b) == std::ptr::eq(*a, *b)));
+        }
+
+        let bad: Interface<TestConfig> = serde_json::from_str(
+            r#"{"nodes":[{"Atom":{"atom":0,"pos":0,"lu":0,"neg":0}}],"types":[0],"tys":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(bad.decode(&fresh).err(), Some(DecodeError::BadIndex(0)));
+        let bad: Interface<TestConfig> =
+            serde_json::from_str(r#"{"nodes":["Top",{"Product":[0,0]}],"types":[1],"tys":[]}"#)
+                .unwrap();
+        assert_eq!(bad.decode(&fresh).err(), Some(DecodeError::BadKind(1)));
+        assert_eq!(
+            DecodeError::BadKind(1).to_string(),
+            "node 1 isn't of the expected kind"
+        );
+    }
+
+    #[test]
+    fn interface_files() {
+        use crate::binary::{FormatError, InterfaceFile, write_defs};
+
+        let ctx: Context<TestConfig> = Context::new();
+
+        let nil = lit_ty(&ctx, TestLiteral::Str("nil".into()));
+        let int = basic(&ctx, "Int");
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+        let refr = |name: &str, args: Vec<_>| {
+            ctx.ty(Type::from_refrs(&ctx.arena, ctx.refr(name.into(), args)))
+        };
+        let ints = ctx.ty(Type::from_ints(&ctx.arena, ctx.interval(-5, 5)));
+        let point = ctx.ty(Type::from_records(
+            &ctx.arena,
+            ctx.record(Openness::Closed, [("x".into(), ints), ("y".into(), ints)]),
+        ));
+
+        let mut defs = Defs::new();
+        let list_body = ctx.union([nil, pair(&ctx, a, refr("List", vec![a]))]);
+        defs.define("List".into(), vec!["a".into()], list_body);
+        defs.define_opaque("Point".into(), vec![], point);
+        defs.define("Ints".into(), vec![], refr("List", vec![int]));
+        let bytes = write_defs(&defs);
+
+        let file = InterfaceFile::<TestConfig>::parse(&bytes).unwrap();
+        let names = file.names().unwrap();
+        assert_eq!(names, ["Ints".into(), "List".into(), "Point".into()]);
+
+        let fresh: Context<TestConfig> = Context::new();
+        let mut loaded = Defs::new();
+        assert!(file.load(&fresh, &mut loaded, &"List".into()).unwrap());
+        assert!(!file.load(&fresh, &mut loaded, &"Tree".into()).unwrap());
+        assert_eq!(loaded.iter().count(), 1);
+        let list = loaded.get(&"List".into()).unwrap();
+        assert_eq!(*list.body, *list_body);
+        assert_eq!(list.params, [TestVar::from("a")]);
+
+        file.load_all(&fresh, &mut loaded).unwrap();
+        let point_def = loaded.get(&"Point".into()).unwrap();
+        assert!(point_def.opaque);
+        assert_eq!(*point_def.body, *point);
+        let list = loaded.get(&"List".into()).unwrap();
+        assert_eq!(list.variances, [Variance::Covariant]);
+
+        // An atom shared by two BDDs is loaded once.
+        let (f, g) = (ctx.arrow(int, int), ctx.arrow(ints, int));
+        let arrows = |bdd| ctx.ty(Type::from_arrows(&ctx.arena, bdd));
+        let mut defs = Defs::new();
+        let body = pair(&ctx, arrows(ctx.union([f, g])), arrows(ctx.diff(f, g)));
+        defs.define("Fns".into(), vec![], body);
+        let bytes = write_defs(&defs);
+        let file = InterfaceFile::<TestConfig>::parse(&bytes).unwrap();
+        file.load_all(&fresh, &mut loaded).unwrap();
+        let mut atoms = Vec::new();
+        loaded
+            .get(&"Fns".into())
+            .unwrap()
+            .body
+            .for_each_atom(&mut |ty| {
+                ty.products.for_each_atom(&mut |Product(l, r)| {
+                    for side in [l, r] {
+                        side.for_each_atom(&mut |ty| {
+                            ty.arrows.for_each_atom(&mut |arrow| atoms.push(arrow))
+                        });
+                    }
+                })
+            });
+        assert_eq!(atoms.len(), 4);
+        for a in &atoms {
+            assert!(atoms.iter().all(|b| (**a == **b) == std::ptr::eq(*a, *b)));
+        }
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(
+            InterfaceFile::<TestConfig>::parse(&bad).err(),
+            Some(FormatError::BadMagic)
+        );
+        let mut bad = bytes.clone();
+        bad[4] = 99;
+        assert_eq!(
+            InterfaceFile::<TestConfig>::parse(&bad).err(),
+            Some(FormatError::UnsupportedVersion(99))
+        );
+        assert_eq!(
+            InterfaceFile::<TestConfig>::parse(&bytes[..bytes.len() - 1]).err(),
+            Some(FormatError::Truncated)
+        );
+        assert_eq!(
+            FormatError::UnsupportedVersion(99).to_string(),
+            "unsupported interface file version 99"
+        );
+    }
+
+    #[cfg(feature = "json-schema")]
+    #[test]
+    fn json_schema_export() {
+        use crate::schema::{SchemaError, to_schema};
+        use serde_json::json;
+
+        let ctx: Context<TestConfig> = Context::new();
+
+        let nil = basic(&ctx, "Nil");
+        let string = basic(&ctx, "String");
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+        let ints = ctx.ty(Type::from_ints(&ctx.arena, ctx.interval(0, 100)));
+
+        let mut defs = Defs::new();
+        defs.define(
+            "List".into(),
+            vec!["a".into()],
+            ctx.union([nil, pair(&ctx, a, list(&ctx, a))]),
+        );
+
+        let ty = ctx.union([
+            record(
+                &ctx,
+                Openness::Closed,
+                vec![("name", string), ("tags", list(&ctx, string))],
+            ),
+            ctx.diff(
+                record(&ctx, Openness::Open, vec![("id", ints)]),
+                record(
+                    &ctx,
+                    Openness::Open,
+                    vec![("id", lit_ty(&ctx, TestLiteral::Int(0)))],
+                ),
+            ),
+        ]);
+        let schema = to_schema(&ctx, &defs, ty).unwrap();
+        assert_eq!(
+            schema,
+            json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "anyOf": [
+                    {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "tags": { "$ref": "#/$defs/List_0" },
+                        },
+                        "required": ["name", "tags"],
+                        "additionalProperties": false,
+                    },
+                    {
+                        "allOf": [
+                            {
+                                "not": {
+                                    "type": "object",
+                                    "properties": { "id": { "const": 0 } },
+                                    "required": ["id"],
+                                    "additionalProperties": true,
+                                },
+                            },
+                            {
+                                "type": "object",
+                                "properties": { "id": { "type": "integer", "minimum": 0, "maximum": 100 } },
+                                "required": ["id"],
+                                "additionalProperties": true,
+                            },
+                        ],
+                    },
+                ],
+                "$defs": {
+                    "List_0": {
+                        "anyOf": [
+                            { "type": "null" },
+                            {
+                                "type": "array",
+                                "prefixItems": [{ "type": "string" }, { "$ref": "#/$defs/List_0" }],
+                                "minItems": 2,
+                                "items": false,
+                            },
+                        ],
+                    },
+                },
+            })
+        );
+
+        assert_eq!(
+            to_schema(&ctx, &defs, pair(&ctx, a, nil)),
+            Err(SchemaError::Var("TestVar(\"a\")".into()))
+        );
+        assert_eq!(
+            to_schema(&ctx, &Defs::new(), list(&ctx, nil)),
+            Err(SchemaError::Undefined("List".into()))
+        );
+    }
+
+    #[cfg(feature = "json-schema")]
+    #[test]
+    fn json_schema_import() {
+        use crate::schema::{ImportError, from_schema, to_schema};
+        use serde_json::json;
+
+        let ctx: Context<TestConfig> = Context::new();
+
+        let nil = basic(&ctx, "Nil");
+        let string = basic(&ctx, "String");
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+
+        let mut defs = Defs::new();
+        defs.define(
+            "List".into(),
+            vec!["a".into()],
+            ctx.union([nil, pair(&ctx, a, list(&ctx, a))]),
+        );
+
+        // Exported types are imported back as equivalent ones.
+        let ty = ctx.union([
+            record(&ctx, Openness::Closed, vec![("tags", list(&ctx, string))]),
+            ctx.diff(
+                pair(&ctx, ints(&ctx, 0, 9), nil),
+                pair(&ctx, ints(&ctx, 5, 5), ctx.top()),
+            ),
+        ]);
+        let schema = to_schema(&ctx, &defs, ty).unwrap();
+        let imported = from_schema(&ctx, &mut defs, &schema).unwrap();
+        let solver = ctx.solver(&defs);
+        assert!(solver.is_subtype(ty, imported) && solver.is_subtype(imported, ty));
+
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "age": { "type": "integer", "minimum": 0, "exclusiveMaximum": 150 },
+                "kind": { "enum": ["user", "admin"] },
+                "name": { "not": { "type": ["null", "number"] } },
+            },
+            "required": ["age", "kind", "name"],
+            "additionalProperties": false,
+        });
+        let imported = from_schema(&ctx, &mut defs, &schema).unwrap();
+        let expected = record(
+            &ctx,
+            Openness::Closed,
+            vec![
+                ("age", ints(&ctx, 0, 149)),
+                (
+                    "kind",
+                    ctx.union([
+                        lit_ty(&ctx, TestLiteral::Str("user".into())),
+                        lit_ty(&ctx, TestLiteral::Str("admin".into())),
+                    ]),
+                ),
+                (
+                    "name",
+                    ctx.not(ctx.union([
+                        nil,
+                        basic(&ctx, "Number"),
+                        ints(&ctx, i64::MIN, i64::MAX),
+                    ])),
+                ),
+            ],
+        );
+        let solver = ctx.solver(&defs);
+        assert!(solver.is_subtype(expected, imported) && solver.is_subtype(imported, expected));
+
+        // Keywords of a kind don't constrain the other kinds.
+        let imported = from_schema(&ctx, &mut defs, &json!({ "minimum": 0 })).unwrap();
+        assert!(ctx.is_subtype(string, imported));
+        assert!(!ctx.is_subtype(ints(&ctx, -1, -1), imported));
+
+        // Optional properties may be absent.
+        let schema = json!({ "type": "object", "properties": { "x": { "type": "string" } } });
+        let imported = from_schema(&ctx, &mut defs, &schema).unwrap();
+        assert!(ctx.is_subtype(record(&ctx, Openness::Closed, vec![]), imported));
+        assert!(ctx.is_subtype(record(&ctx, Openness::Open, vec![("x", string)]), imported));
+        assert!(!ctx.is_subtype(record(&ctx, Openness::Open, vec![("x", nil)]), imported));
+        let schema = json!({
+            "type": "object",
+            "properties": { "x": { "type": "string" }, "y": { "type": "null" } },
+            "required": ["x"],
+            "additionalProperties": false,
+        });
+        let imported = from_schema(&ctx, &mut defs, &schema).unwrap();
+        let expected = ctx.union([
+            record(&ctx, Openness::Closed, vec![("x", string)]),
+            record(&ctx, Openness::Closed, vec![("x", string), ("y", nil)]),
+        ]);
+        assert!(ctx.is_subtype(expected, imported) && ctx.is_subtype(imported, expected));
+
+        // Tuples are nested pairs.
+        let schema = json!({
+            "type": "array",
+            "prefixItems": [{ "type": "null" }, { "type": "string" }, { "type": "null" }],
+            "items": false,
+            "minItems": 3,
+        });
+        let imported = from_schema(&ctx, &mut defs, &schema).unwrap();
+        let expected = pair(&ctx, nil, pair(&ctx, string, nil));
+        assert!(ctx.is_subtype(expected, imported) && ctx.is_subtype(imported, expected));
+        assert_eq!(
+            from_schema(&ctx, &mut defs, &json!({ "prefixItems": [true] })),
+            Err(ImportError::Unsupported("prefixItems".into()))
+        );
+
+        // Arrays of unknown length aren't tuples.
+        let unsupported = |schema, keyword: &str| {
+            assert_eq!(
+                from_schema(&ctx, &mut Defs::new(), &schema),
+                Err(ImportError::Unsupported(keyword.into()))
+            );
+        };
+        unsupported(
+            json!({ "type": "array", "items": { "type": "string" } }),
+            "items",
+        );
+        unsupported(
+            json!({ "prefixItems": [true, true], "minItems": 2 }),
+            "prefixItems",
+        );
+        unsupported(
+            json!({ "prefixItems": [true, true], "items": false }),
+            "prefixItems",
+        );
+        unsupported(
+            json!({ "prefixItems": [true, true], "items": false, "minItems": 1 }),
+            "minItems",
+        );
+        unsupported(json!({ "type": "array", "maxItems": 2 }), "maxItems");
+        let schema = json!({ "type": "array", "minItems": 2, "maxItems": 2 });
+        let imported = from_schema(&ctx, &mut defs, &schema).unwrap();
+        let all = pair(&ctx, ctx.top(), ctx.top());
+        assert!(ctx.is_subtype(all, imported) && ctx.is_subtype(imported, all));
+        assert_eq!(
+            from_schema(&ctx, &mut defs, &json!({ "$ref": "#/$defs/Missing" })),
+            Err(ImportError::BadRef("#/$defs/Missing".into()))
+        );
+        assert_eq!(
+            from_schema(
+                &ctx,
+                &mut defs,
+                &json!({ "type": "string", "pattern": "^a" })
+            ),
+            Err(ImportError::Unsupported("pattern".into()))
+        );
+
+        // Without intervals, integers are the integer basic, and ranges of them are unsupported.
+        let ctx: Context<NoInts> = Context::new();
+        let mut defs = Defs::new();
+        let int = ctx.ty(Type::from_basics(&ctx.arena, ctx.basic("Int".into())));
+        let imported = from_schema(&ctx, &mut defs, &json!({ "type": "integer" })).unwrap();
+        assert!(ctx.is_subtype(int, imported) && ctx.is_subtype(imported, int));
+        let imported = from_schema(&ctx, &mut defs, &json!({ "enum": [1, 2] })).unwrap();
+        let one = ctx.ty(Type::from_basics(
+            &ctx.arena,
+            ctx.literal(TestLiteral::Int(1)),
+        ));
+        assert!(ctx.is_subtype(one, imported) && ctx.is_subtype(imported, int));
+        assert!(!ctx.is_subtype(int, imported));
+        let schema = json!({ "type": "integer", "minimum": 0 });
+        assert_eq!(
+            from_schema(&ctx, &mut defs, &schema),
+            Err(ImportError::Unsupported("minimum".into()))
+        );
+    }
+
+    #[test]
+    fn compatibility() {
+        use crate::compat::{Change, Witnesses};
+        use crate::narrow::Step;
+
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let string = basic(&ctx, "String");
+        let lit = |s: &str| lit_ty(&ctx, TestLiteral::Str(s.into()));
+        let field = |p: &str| vec![Step::Field(p.into())];
+
+        let user = record(&ctx, Openness::Open, vec![("id", int), ("name", string)]);
+        let compat = ctx.check_compat(user, user);
+        assert!(compat.backward && compat.forward && compat.changes.is_empty());
+
+        // Removing a required field only breaks old readers.
+        let compat = ctx.check_compat(user, record(&ctx, Openness::Open, vec![("id", int)]));
+        assert!(compat.backward && !compat.forward);
+        assert!(matches!(
+            compat.changes.as_slice(),
+            [Change::RemovedField(path, prop, Witnesses { lost: None, gained: Some(_) })]
+                if path.is_empty() && *prop == "name".into()
+        ));
+
+        // Removing a field from a closed record also breaks old data, which has the field.
+        let old = record(&ctx, Openness::Closed, vec![("x", int), ("y", int)]);
+        let new = record(&ctx, Openness::Closed, vec![("x", int)]);
+        let compat = ctx.check_compat(old, new);
+        assert!(!compat.backward && !compat.forward);
+        match compat.changes.as_slice() {
+            [
+                Change::RemovedField(
+                    _,
+                    prop,
+                    Witnesses {
+                        lost: Some(lost),
+                        gained: Some(gained),
+                    },
+                ),
+            ] => {
+                assert_eq!(*prop, "y".into());
+                assert!(ctx.is_subtype(lost, old) && ctx.is_subtype(old, lost));
+                assert!(ctx.is_subtype(gained, new) && ctx.is_subtype(new, gained));
+            }
+            changes => panic!("unexpected changes {changes:?}"),
+        }
+
+        // Narrowing a field and closing the record break old data.
+        let role = ctx.union([lit("user"), lit("admin")]);
+        let old = record(&ctx, Openness::Open, vec![("id", int), ("role", role)]);
+        let new = record(
+            &ctx,
+            Openness::Closed,
+            vec![("id", int), ("role", lit("user"))],
+        );
+        let compat = ctx.check_compat(old, new);
+        assert!(!compat.backward && compat.forward);
+        assert_eq!(compat.changes.len(), 2);
+        assert!(compat.changes.iter().all(|change| change.breaks_backward()));
+        match &compat.changes[0] {
+            Change::Narrowed(path, lost) => {
+                assert_eq!(*path, field("role"));
+                assert!(ctx.is_subtype(*lost, lit("admin")) && ctx.is_subtype(lit("admin"), *lost))
+            }
+            _ => unreachable!(),
+        }
+        assert!(matches!(&compat.changes[1], Change::Closed(path, _) if path.is_empty()));
+
+        // The changes break a direction exactly when it doesn't hold.
+        let versions = [
+            user,
+            record(&ctx, Openness::Closed, vec![("id", int)]),
+            record(&ctx, Openness::Closed, vec![("id", int), ("name", string)]),
+            record(&ctx, Openness::Open, vec![("id", ctx.union([int, string]))]),
+            ctx.union([record(&ctx, Openness::Closed, vec![("id", int)]), string]),
+            record(
+                &ctx,
+                Openness::Closed,
+                vec![("id", ctx.union([int, string]))],
+            ),
+            ctx.union([
+                record(&ctx, Openness::Closed, vec![("id", int), ("name", int)]),
+                record(&ctx, Openness::Closed, vec![("id", string)]),
+            ]),
+        ];
+        for old in versions {
+            for new in versions {
+                let compat = ctx.check_compat(old, new);
+                let changes = &compat.changes;
+                assert_eq!(
+                    compat.backward,
+                    !changes.iter().any(Change::breaks_backward)
+                );
+                assert_eq!(compat.forward, !changes.iter().any(Change::breaks_forward));
+            }
+        }
+
+        // Each case of a union is compared with the most similar one.
+        let circle = |r| {
+            record(
+                &ctx,
+                Openness::Closed,
+                vec![("kind", lit("circle")), ("r", r)],
+            )
+        };
+        let square = record(
+            &ctx,
+            Openness::Closed,
+            vec![("kind", lit("square")), ("side", int)],
+        );
+        let compat = ctx.check_compat(
+            ctx.union([circle(int), square]),
+            ctx.union([circle(ctx.union([int, string])), square, string]),
+        );
+        assert!(compat.backward && !compat.forward);
+        assert_eq!(compat.changes.len(), 2);
+        assert!(matches!(&compat.changes[0], Change::Widened(path, _) if *path == field("r")));
+        assert!(matches!(&compat.changes[1], Change::Widened(path, _) if path.is_empty()));
+    }
+
+    #[test]
+    fn typescript_declarations() {
+        use crate::typescript::{Warning, emit_declarations, emit_type};
+
+        let ctx: Context<TestConfig> = Context::new();
+
+        let nil = basic(&ctx, "Nil");
+        let string = basic(&ctx, "String");
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+        let user_id = ctx.ty(Type::from_refrs(&ctx.arena, ctx.refr("UserId".into(), [])));
+
+        let mut defs = Defs::new();
+        defs.define(
+            "List".into(),
+            vec!["a".into()],
+            ctx.union([nil, pair(&ctx, a, list(&ctx, a))]),
+        );
+        defs.define_opaque("UserId".into(), vec![], string);
+        defs.define(
+            "User".into(),
+            vec![],
+            record(
+                &ctx,
+                Openness::Closed,
+                vec![
+                    ("id", user_id),
+                    ("first-name", string),
+                    ("tags", list(&ctx, string)),
+                    ("onChange", ctx.union([nil, arrow(&ctx, string, nil)])),
+                ],
+            ),
+        );
+        let emitted = emit_declarations(&ctx, &defs);
+        assert_eq!(
+            emitted.code,
+            "export type List<a> = null | [a, List<a>];\n\
+             export type User = { id: UserId; \"first-name\": string; tags: List<string>; \
+             onChange: null | ((arg: string) => null) };\n\
+             export type UserId = { readonly __brand: \"UserId\" };\n"
+        );
+        // TypeScript object types admit other properties, so closing the record is lost.
+        assert!(matches!(
+            emitted.warnings.as_slice(),
+            [Warning::Closed(code)] if code.starts_with("{ id: UserId;")
+        ));
+
+        // Property names and string literals are escaped as in JSON.
+        let quoted = record(
+            &ctx,
+            Openness::Open,
+            vec![(
+                "say \"hi\"\n",
+                lit_ty(&ctx, TestLiteral::Str("\\\u{1}".into())),
+            )],
+        );
+        assert_eq!(
+            emit_type(&ctx, quoted).code,
+            r#"{ "say \"hi\"\n": "\\\u0001"; [key: string]: unknown }"#
+        );
+
+        // Negations are dropped, and bounded integers widened, with warnings.
+        let emitted = emit_type(
+            &ctx,
+            ctx.union([
+                ctx.diff(string, lit_ty(&ctx, TestLiteral::Str("".into()))),
+                ints(&ctx, 0, 9),
+                record(&ctx, Openness::Open, vec![("x", ints(&ctx, 1, 1))]),
+            ]),
+        );
+        assert_eq!(
+            emitted.code,
+            "string | number | { x: 1; [key: string]: unknown }"
+        );
+        assert_eq!(
+            emitted.warnings,
+            vec![
+                Warning::Negation("\"\"".into()),
+                Warning::Interval("0..=9".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn value_membership() {
+        use crate::narrow::Step;
+        use crate::value::{self, Value};
+
+        let ctx: Context<TestConfig> = Context::new();
+
+        let nil = basic(&ctx, "Nil");
+        let string = basic(&ctx, "String");
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var("a".into())));
+        let str_value = |s: &str| Value::Basic(TestLiteral::Str(s.into()));
+        let end = || Value::Int(0);
+        let record_value = |fields: Vec<(&str, Value<TestConfig>)>| {
+            Value::Record(fields.into_iter().map(|(p, v)| (p.into(), v)).collect())
+        };
+
+        let user = record(
+            &ctx,
+            Openness::Closed,
+            vec![
+                (
+                    "name",
+                    ctx.diff(string, lit_ty(&ctx, TestLiteral::Str("".into()))),
+                ),
+                ("age", ints(&ctx, 0, 150)),
+                ("greet", arrow(&ctx, nil, string)),
+            ],
+        );
+        let alice = record_value(vec![
+            ("name", str_value("alice")),
+            ("age", Value::Int(30)),
+            ("greet", Value::Function),
+        ]);
+        assert!(ctx.check_value(&alice, user));
+        assert_eq!(ctx.value_mismatch(&alice, user), None);
+
+        // Negations and closed records are honored, and the failing part is reported.
+        let anonymous = record_value(vec![
+            ("name", str_value("")),
+            ("age", Value::Int(30)),
+            ("greet", Value::Function),
+        ]);
+        assert!(!ctx.check_value(&anonymous, user));
+        assert_eq!(
+            ctx.value_mismatch(&anonymous, user),
+            Some(vec![Step::Field("name".into())])
+        );
+        let extra = record_value(vec![
+            ("name", str_value("bob")),
+            ("age", Value::Int(30)),
+            ("greet", Value::Function),
+            ("admin", Value::Basic(TestLiteral::Bool(true))),
+        ]);
+        assert!(!ctx.check_value(&extra, user));
+        assert_eq!(ctx.value_mismatch(&extra, user), Some(vec![]));
+        assert!(ctx.check_value(
+            &extra,
+            record(&ctx, Openness::Open, vec![("age", ints(&ctx, 18, 150))])
+        ));
+
+        // References are unfolded with the definitions of the solver.
+        let mut defs = Defs::new();
+        defs.define(
+            "List".into(),
+            vec!["a".into()],
+            ctx.union([ints(&ctx, 0, 0), pair(&ctx, a, list(&ctx, a))]),
+        );
+        let solver = ctx.solver(&defs);
+        let cons = |head, tail| Value::Pair(Box::new(head), Box::new(tail));
+        let names = cons(str_value("x"), cons(str_value("y"), end()));
+        assert!(value::check_value(&solver, &names, list(&ctx, string)));
+        let mixed = cons(str_value("x"), cons(Value::Int(1), end()));
+        assert!(!value::check_value(&solver, &mixed, list(&ctx, string)));
+        assert_eq!(
+            value::value_mismatch(&solver, &mixed, list(&ctx, string)),
+            Some(vec![Step::Right, Step::Left])
+        );
+        assert!(!ctx.check_value(&names, list(&ctx, string)));
+        assert!(ctx.check_value(&mixed, ctx.dynamic()));
+
+        // Without intervals, integers belong to the integer basic and to their literal.
+        let ctx: Context<NoInts> = Context::new();
+        let int = ctx.ty(Type::from_basics(&ctx.arena, ctx.basic("Int".into())));
+        let seven = ctx.ty(Type::from_basics(
+            &ctx.arena,
+            ctx.literal(TestLiteral::Int(7)),
+        ));
+        assert!(ctx.check_value(&Value::Int(7), int));
+        assert!(ctx.check_value(&Value::Int(7), seven));
+        assert!(!ctx.check_value(&Value::Int(8), seven));
+        assert!(!ctx.check_value(&Value::Int(7), ctx.diff(int, seven)));
+        assert!(ctx.check_value(&Value::Basic(TestLiteral::Int(7)), int));
+        let string = ctx.ty(Type::from_basics(&ctx.arena, ctx.basic("String".into())));
+        assert!(!ctx.check_value(&Value::Int(7), string));
+    }
+
+    #[test]
+    fn shared_store() {
+        use crate::store::{SharedStore, StoreError};
+
+        fn assert_shareable<T: Send + Sync>() {}
+        assert_shareable::<SharedStore<TestConfig>>();
+
+        let store: SharedStore<TestConfig> = SharedStore::new();
+        let build = |store: &SharedStore<TestConfig>, flip: bool| {
+            let int = store.basic("Int".into());
+            let string = store.basic("String".into());
+            let members = if flip { [string, int] } else { [int, string] };
+            let field = store.union(members)?;
+            let user = store.record(Openness::Open, [("id".into(), field)])?;
+            store.arrow(user, store.product(int, store.not(string)?)?)
+        };
+
+        // Threads building the same types get the same identities.
+        let ids: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|i| {
+                    let (store, build) = (&store, &build);
+                    scope.spawn(move || build(store, i % 2 == 0).unwrap())
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert!(ids.iter().all(|&id| id == ids[0]));
+
+        let int = store.basic("Int".into());
+        let narrow = store.record(Openness::Open, [("id".into(), int)]).unwrap();
+        let int_or_string = store.union([int, store.basic("String".into())]).unwrap();
+        let wide = store
+            .record(Openness::Open, [("id".into(), int_or_string)])
+            .unwrap();
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (
+                            store.is_subtype(narrow, wide),
+                            store.is_subtype(wide, narrow),
+                            store.is_empty(store.diff(narrow, wide).unwrap()),
+                        )
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert!(
+            results
+                .iter()
+                .all(|&r| r == (Ok(true), Ok(false), Ok(true)))
+        );
+
+        // Identities of another store are rejected.
+        let other: SharedStore<TestConfig> = SharedStore::new();
+        let foreign = other.record(Openness::Open, []).unwrap();
+        assert_eq!(
+            store.is_subtype(narrow, foreign),
+            Err(StoreError::Foreign(foreign))
+        );
+        assert_eq!(
+            store.product(int, foreign),
+            Err(StoreError::Foreign(foreign))
+        );
+        assert!(store.union([int, foreign]).is_err());
+        assert!(store.refr("List".into(), [foreign]).is_err());
+        assert!(store.define("Foreign".into(), vec![], foreign).is_err());
+
+        // References unfold once their types are defined, and the results cached before are
+        // dropped.
+        let nil = store.basic("Nil".into());
+        let list = |ty| store.refr("List".into(), [ty]).unwrap();
+        let one = store.product(int, nil).unwrap();
+        assert_eq!(store.is_subtype(one, list(int)), Ok(false));
+        let a = store.var("a".into());
+        let cons = store.product(a, list(a)).unwrap();
+        store
+            .define(
+                "List".into(),
+                vec!["a".into()],
+                store.union([nil, cons]).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(store.is_subtype(one, list(int)), Ok(true));
+        assert_eq!(store.is_subtype(list(int), list(int_or_string)), Ok(true));
+        assert_eq!(store.is_subtype(list(int_or_string), list(int)), Ok(false));
+
+        // Types materialized in a context behave as those built there, with the definitions.
+        let ctx: Context<TestConfig> = Context::new();
+        let defs = store.defs(&ctx);
+        let materialized = store.materialize(&ctx, list(int)).unwrap();
+        let nonempty = pair(&ctx, basic(&ctx, "Int"), ctx.top());
+        let expected = ctx.union([basic(&ctx, "Nil"), nonempty]);
+        assert!(ctx.solver(&defs).is_subtype(materialized, expected));
+        assert!(!ctx.is_subtype(materialized, expected));
+
+        let materialized = store.materialize(&ctx, ids[0]).unwrap();
+        let int = basic(&ctx, "Int");
+        let string = basic(&ctx, "String");
+        let user = ctx.ty(Type::from_records(
+            &ctx.arena,
+            ctx.record(Openness::Open, [("id".into(), ctx.union([int, string]))]),
+        ));
+        let pair = ctx.ty(Type::from_products(
+            &ctx.arena,
+            ctx.product(int, ctx.not(string)),
+        ));
+        let expected = ctx.ty(Type::from_arrows(&ctx.arena, ctx.arrow(user, pair)));
+        assert!(ctx.is_subtype(materialized, expected) && ctx.is_subtype(expected, materialized));
+    }
+
+    #[test]
+    fn simplification() {
+        let ctx: Context<TestConfig> = Context::new();
+
+        let int = basic(&ctx, "Int");
+        let var = |name: &str| ctx.ty(Type::from_vars(&ctx.arena, ctx.var(name.into())));
+        let (a, b) = (var("a"), var("b"));
+        let equiv = |t1, t2| ctx.is_subtype(t1, t2) && ctx.is_subtype(t2, t1);
+
+        let noisy = arrow(&ctx, ctx.inter([a, int]), ctx.union([a, int]));
+        assert!(equiv(ctx.simplify(noisy), arrow(&ctx, int, int)));
+
+        let polar = arrow(&ctx, a, pair(&ctx, b, int));
+        assert!(equiv(
+            ctx.simplify(polar),
+            arrow(&ctx, ctx.top(), pair(&ctx, ctx.bot(), int))
+        ));
+
+        let merged = ctx.simplify(arrow(&ctx, ctx.inter([a, b]), ctx.union([a, b])));
+        let free = merged.free_vars();
+        assert_eq!(free.len(), 1);
+        let c = ctx.ty(Type::from_vars(&ctx.arena, ctx.var(free[0].clone())));
+        assert!(equiv(merged, arrow(&ctx, c, c)));
+
+        let identity = arrow(&ctx, a, a);
+        assert!(equiv(ctx.simplify(identity), identity));
+    }
+
+    #[test]
+    fn inference() {
+        let ctx: Context<TestConfig> = Context::new();
+        let defs = Defs::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let equiv = |t1, t2| ctx.is_subtype(t1, t2) && ctx.is_subtype(t2, t1);
+        let infer = |expr| Infer::new(&ctx, &defs).infer_closed(&expr);
+
+        let var = |name: &str| Expr::Var(name.into());
+        let lambda = |param: &str, body| Expr::Lambda(param.into(), Box::new(body));
+        let app = |f, a| Expr::App(Box::new(f), Box::new(a));
+        let one = || {
+            Expr::Const(ctx.ty(Type::from_basics(
+                &ctx.arena,
+                ctx.literal(TestLiteral::Int(1)),
+            )))
+        };
+        let yes = || {
+            Expr::Const(ctx.ty(Type::from_basics(
+                &ctx.arena,
+                ctx.literal(TestLiteral::Bool(true)),
+            )))
+        };
+
+        let identity = infer(lambda("x", var("x"))).unwrap();
+        let free = identity.free_vars();
+        assert_eq!(free.len(), 1);
+        let a = ctx.ty(Type::from_vars(&ctx.arena, ctx.var(free[0].clone())));
+        assert!(equiv(identity, arrow(&ctx, a, a)));
+
+        let polymorphic = Expr::Let(
+            "id".into(),
+            Box::new(lambda("x", var("x"))),
+            Box::new(Expr::Pair(
+                Box::new(app(var("id"), one())),
+                Box::new(app(var("id"), yes())),
+            )),
+        );
+        assert!(ctx.is_subtype(infer(polymorphic).unwrap(), pair(&ctx, int, boolean)));
+
+        let field = app(
+            lambda("r", Expr::Field(Box::new(var("r")), "l".into())),
+            Expr::Record(vec![("l".into(), one()), ("m".into(), yes())]),
+        );
+        assert!(ctx.is_subtype(infer(field).unwrap(), int));
+        let missing = app(
+            lambda("r", Expr::Field(Box::new(var("r")), "l".into())),
+            Expr::Record(vec![("m".into(), yes())]),
+        );
+        assert_eq!(infer(missing), Err(TypeError::Unsatisfiable));
+
+        let first = Expr::Fst(Box::new(Expr::Pair(Box::new(one()), Box::new(yes()))));
+        assert!(ctx.is_subtype(infer(first).unwrap(), int));
+
+        let to_int = lambda(
+            "x",
+            Expr::If {
+                scrutinee: Box::new(var("x")),
+                test: int,
+                then: Box::new(var("x")),
+                otherwise: Box::new(one()),
+            },
+        );
+        assert!(ctx.is_subtype(infer(app(to_int, yes())).unwrap(), int));
+
+        let annotated = Expr::Annot(Box::new(lambda("x", var("x"))), arrow(&ctx, int, int));
+        assert!(equiv(infer(annotated).unwrap(), arrow(&ctx, int, int)));
+        let wrong = Expr::Annot(Box::new(lambda("x", yes())), arrow(&ctx, int, int));
+        assert_eq!(infer(wrong), Err(TypeError::Unsatisfiable));
+
+        let either = |arg| {
+            let test = Expr::Annot(
+                Box::new(Expr::Pair(Box::new(var("x")), Box::new(var("x")))),
+                ctx.union([pair(&ctx, int, ctx.top()), pair(&ctx, ctx.top(), boolean)]),
+            );
+            let body = Expr::Let("_".into(), Box::new(test), Box::new(var("x")));
+            app(lambda("x", body), arg)
+        };
+        // Typable with `x: Int`, but the first solution at the `let` makes `x` empty, and the
+        // others aren't tried.
+        assert_eq!(infer(either(one())), Err(TypeError::Unsatisfiable));
+
+        assert_eq!(infer(app(one(), one())), Err(TypeError::Unsatisfiable));
+        assert_eq!(infer(var("y")), Err(TypeError::Unbound("y".into())));
+        assert_eq!(
+            TypeError::Unbound("y".into()).to_string(),
+            "unbound variable `y`"
+        );
+    }
+
+    #[test]
+    fn overloaded_inference() {
+        let ctx: Context<TestConfig> = Context::new();
+        let defs = Defs::new();
+
+        let int = basic(&ctx, "Int");
+        let boolean = basic(&ctx, "Boolean");
+        let string = basic(&ctx, "String");
+        let equiv = |t1, t2| ctx.is_subtype(t1, t2) && ctx.is_subtype(t2, t1);
+        let infer = |expr| Infer::new(&ctx, &defs).infer_closed(&expr);
+
+        let var = |name: &str| Expr::Var(name.into());
+        let app = |f, a| Expr::App(Box::new(f), Box::new(a));
+        let body = || Expr::If {
+            scrutinee: Box::new(var("x")),
+            test: int,
+            then: Box::new(app(Expr::Const(arrow(&ctx, int, int)), var("x"))),
+            otherwise: Box::new(app(Expr::Const(arrow(&ctx, boolean, boolean)), var("x"))),
+        };
+        let neg = |domains| Expr::Overloaded("x".into(), domains, Box::new(body()));
+
+        let expected = ctx.inter([arrow(&ctx, int, int), arrow(&ctx, boolean, boolean)]);
+        assert!(equiv(infer(neg(vec![int, boolean])).unwrap(), expected));
+        assert_eq!(infer(neg(vec![int, string])), Err(TypeError::Unsatisfiable));
+
+        let applied = app(neg(vec![int, boolean]), Expr::Const(boolean));
+        assert!(equiv(infer(applied).unwrap(), boolean));
+
+        let checked = Expr::Annot(
+            Box::new(Expr::Lambda("x".into(), Box::new(body()))),
+            expected,
+        );
+        assert!(equiv(infer(checked).unwrap(), expected));
+    }
 }
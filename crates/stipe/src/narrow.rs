@@ -0,0 +1,102 @@
+use crate::{
+    bdd::{Bdd, Openness, Product, Record, Type},
+    ty::TyConfig,
+};
+
+/// A step from a value to one of its parts.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Step<C>
+where
+    C: TyConfig,
+{
+    Left,
+    Right,
+    Field(C::Prop),
+}
+impl<C> Clone for Step<C>
+where
+    C: TyConfig,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Self::Left => Self::Left,
+            Self::Right => Self::Right,
+            Self::Field(prop) => Self::Field(prop.clone()),
+        }
+    }
+}
+
+/// The types of a tested variable when the test succeeds and when it fails.
+pub type Narrowed<'a, C> = (&'a Bdd<'a, C, Type<'a, C>>, &'a Bdd<'a, C, Type<'a, C>>);
+
+/// A type test `x.p1.p2 is ty` on an occurrence of a variable `x`, where `path` lists the steps
+/// `p1, p2` from the variable to the tested value.
+#[derive(Debug)]
+pub struct Test<'a, C>
+where
+    C: TyConfig,
+{
+    pub path: Vec<Step<C>>,
+    pub ty: &'a Bdd<'a, C, Type<'a, C>>,
+}
+impl<'a, C> Test<'a, C>
+where
+    C: TyConfig,
+{
+    /// The type of the values whose part at the end of the path has type `ty`.
+    pub fn embed(
+        &self,
+        arena: &'a bumpalo::Bump,
+        ty: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> &'a Bdd<'a, C, Type<'a, C>> {
+        self.path.iter().rev().fold(ty, |ty, step| {
+            let outer = match step {
+                Step::Left => Type::from_products(
+                    arena,
+                    Bdd::atom(arena, arena.alloc(Product(ty, Bdd::top(arena)))),
+                ),
+                Step::Right => Type::from_products(
+                    arena,
+                    Bdd::atom(arena, arena.alloc(Product(Bdd::top(arena), ty))),
+                ),
+                Step::Field(prop) => Type::from_records(
+                    arena,
+                    Bdd::atom(
+                        arena,
+                        arena.alloc(Record {
+                            map: bumpalo::collections::Vec::from_iter_in(
+                                [(prop.clone(), ty)],
+                                arena,
+                            ),
+                            open: Openness::Open,
+                        }),
+                    ),
+                ),
+            };
+            Bdd::atom(arena, arena.alloc(outer))
+        })
+    }
+
+    /// Splits `ty`, the type of the tested variable, into its type when the test succeeds and
+    /// its type when the test fails. The path must exist in both cases, so values without it
+    /// are excluded from both.
+    pub fn narrow(
+        &self,
+        arena: &'a bumpalo::Bump,
+        ty: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> Narrowed<'a, C> {
+        if self.path.is_empty() {
+            return (
+                Bdd::inter(arena, ty, self.ty),
+                Bdd::diff(arena, ty, self.ty),
+            );
+        }
+        let then_ty = Bdd::inter(arena, ty, self.embed(arena, self.ty));
+        let else_ty = Bdd::diff(
+            arena,
+            Bdd::inter(arena, ty, self.embed(arena, Bdd::top(arena))),
+            then_ty,
+        );
+        (then_ty, else_ty)
+    }
+}
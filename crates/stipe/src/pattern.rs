@@ -0,0 +1,104 @@
+use crate::{
+    bdd::{Basic, Bdd, Openness, Product, Record, Solver, Type},
+    ty::TyConfig,
+};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Pattern<'a, C>
+where
+    C: TyConfig,
+{
+    Wildcard,
+    Basic(Basic<C>),
+    Pair(&'a Pattern<'a, C>, &'a Pattern<'a, C>),
+    Record {
+        fields: bumpalo::collections::Vec<'a, (C::Prop, &'a Pattern<'a, C>)>,
+        open: Openness,
+    },
+    Test(&'a Bdd<'a, C, Type<'a, C>>),
+}
+impl<'a, C> Pattern<'a, C>
+where
+    C: TyConfig,
+{
+    /// The type of the values matched by the pattern, `⟦p⟧`.
+    pub fn accepted(&'a self, arena: &'a bumpalo::Bump) -> &'a Bdd<'a, C, Type<'a, C>> {
+        let ty = match self {
+            Self::Wildcard => return Bdd::top(arena),
+            Self::Test(ty) => return ty,
+            Self::Basic(basic) => Type::from_basics(arena, Bdd::atom(arena, basic)),
+            Self::Pair(l, r) => Type::from_products(
+                arena,
+                Bdd::atom(
+                    arena,
+                    arena.alloc(Product(l.accepted(arena), r.accepted(arena))),
+                ),
+            ),
+            Self::Record { fields, open } => Type::from_records(
+                arena,
+                Bdd::atom(
+                    arena,
+                    arena.alloc(Record {
+                        map: bumpalo::collections::Vec::from_iter_in(
+                            fields
+                                .iter()
+                                .map(|(prop, pat)| (prop.clone(), pat.accepted(arena))),
+                            arena,
+                        ),
+                        open: *open,
+                    }),
+                ),
+            ),
+        };
+        Bdd::atom(arena, arena.alloc(ty))
+    }
+}
+
+/// The result of checking the branches of a `match` against the type of its scrutinee.
+#[derive(Debug)]
+pub struct Branches<'a, C>
+where
+    C: TyConfig,
+{
+    /// For each branch, the scrutinee type narrowed to the values that reach it: the values
+    /// accepted by its pattern and by none of the previous ones.
+    pub narrowed: Vec<&'a Bdd<'a, C, Type<'a, C>>>,
+    /// The values of the scrutinee type that no branch matches.
+    pub residual: &'a Bdd<'a, C, Type<'a, C>>,
+    /// The indices of the branches that no value can reach.
+    pub redundant: Vec<usize>,
+    pub exhaustive: bool,
+}
+impl<'a, C> Branches<'a, C>
+where
+    C: TyConfig,
+{
+    pub fn check<I>(
+        solver: &Solver<'_, 'a, C>,
+        ty: &'a Bdd<'a, C, Type<'a, C>>,
+        patterns: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = &'a Pattern<'a, C>>,
+    {
+        let arena = solver.arena;
+        let mut residual = ty;
+        let mut narrowed = Vec::new();
+        let mut redundant = Vec::new();
+        for (i, pattern) in patterns.into_iter().enumerate() {
+            let accepted = pattern.accepted(arena);
+            let branch = Bdd::inter(arena, residual, accepted);
+            if solver.is_empty(branch) {
+                redundant.push(i);
+            }
+            narrowed.push(branch);
+            residual = Bdd::diff(arena, residual, accepted);
+        }
+        Self {
+            narrowed,
+            residual,
+            redundant,
+            exhaustive: solver.is_empty(residual),
+        }
+    }
+}
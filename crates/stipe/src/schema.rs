@@ -0,0 +1,654 @@
+//! Export of types to JSON Schema (draft 2020-12), and import back. Records become objects, with
+//! `additionalProperties` following their `Openness`, products become arrays of two items, and
+//! references become `$ref`s to entries of `$defs`. The boolean combinations of the BDDs become
+//! `anyOf`, `allOf` and `not`. Functions have no JSON values, so arrows are exported as `false`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    str::FromStr,
+};
+
+use serde_json::{Map, Value, json};
+
+use crate::{
+    Context,
+    bdd::{
+        Basic, Bdd, Bound, Intervals, Openness, Product, Record, Refr, Solver, TyAtom, Type, Var,
+    },
+    defs::Defs,
+    ty::TyConfig,
+};
+
+/// The number of instances of a generic type after which exporting its references fails, since
+/// polymorphic recursion would otherwise make infinitely many of them.
+const MAX_INSTANCES: usize = 64;
+
+/// A configuration whose types can be exported to JSON Schema, with the schemas of its basics.
+pub trait SchemaConfig: TyConfig<TyName: Display, Prop: Display, Int: Into<i128>> {
+    /// The schema of the values of a basic type.
+    fn basic_schema(basic: &Self::Basic) -> Value;
+
+    /// The schema of a single literal value.
+    fn literal_schema(literal: &Self::Literal) -> Value;
+
+    /// The schema of all the values of basic types. By default, any JSON scalar.
+    fn basics_schema() -> Value {
+        json!({ "type": ["null", "boolean", "number", "string"] })
+    }
+
+    /// The basic type of the JSON values of a `type` keyword: `null`, `boolean`, `number` or
+    /// `string`. With `TyConfig::INTERVALS`, integers are read as intervals. Otherwise, they're
+    /// the basic for `integer`, which must satisfy `TyConfig::int_basic`. Either way, they join
+    /// the basic for `number`.
+    fn schema_basic(_name: &str) -> Option<Self::Basic> {
+        None
+    }
+
+    /// The literal of a JSON value in a `const` or `enum` keyword. Integers are only read here
+    /// without `TyConfig::INTERVALS`.
+    fn schema_literal(_value: &Value) -> Option<Self::Literal> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    /// A type variable, which has no schema, occurs in the type.
+    Var(String),
+    /// A referenced type isn't defined.
+    Undefined(String),
+    /// A generic type is referenced with ever new arguments.
+    Unbounded(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    /// A keyword, or a use of one, that has no counterpart in types.
+    Unsupported(String),
+    /// A `$ref` that isn't to an entry of the root's `$defs`.
+    BadRef(String),
+    /// A keyword whose value isn't of the expected shape.
+    Malformed(String),
+}
+
+/// The schema of `ty`, whose references are to the types of `defs`. The schemas of the
+/// referenced types (one per distinct list of arguments) are put in `$defs`.
+pub fn to_schema<'a, C>(
+    ctx: &'a Context<C>,
+    defs: &Defs<'a, C>,
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+) -> Result<Value, SchemaError>
+where
+    C: SchemaConfig,
+{
+    let mut exporter = Exporter {
+        solver: ctx.solver(defs),
+        instances: HashMap::new(),
+        schemas: Map::new(),
+    };
+    let schema = exporter.ty(ty)?;
+    let mut root = match schema {
+        Value::Object(object) => object,
+        schema => Map::from_iter([("allOf".to_string(), json!([schema]))]),
+    };
+    root.insert(
+        "$schema".to_string(),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    if !exporter.schemas.is_empty() {
+        root.insert("$defs".to_string(), Value::Object(exporter.schemas));
+    }
+    Ok(Value::Object(root))
+}
+
+/// An exported reference, with its key in `$defs`.
+type Instance<'a, C> = (&'a Refr<'a, C, Type<'a, C>>, String);
+
+struct Exporter<'d, 'a, C>
+where
+    C: TyConfig,
+{
+    solver: Solver<'d, 'a, C>,
+    /// The instances of each referenced type exported so far, with their keys in `$defs`.
+    instances: HashMap<&'a C::TyName, Vec<Instance<'a, C>>>,
+    schemas: Map<String, Value>,
+}
+impl<'d, 'a, C> Exporter<'d, 'a, C>
+where
+    C: SchemaConfig,
+{
+    fn ty(&mut self, bdd: &'a Bdd<'a, C, Type<'a, C>>) -> Result<Value, SchemaError> {
+        self.bdd(bdd, &Value::Bool(true), Self::type_atom)
+    }
+
+    /// The schema of a BDD, where `top` is the schema of all the values of its kind.
+    fn bdd<T>(
+        &mut self,
+        bdd: &'a Bdd<'a, C, T>,
+        top: &Value,
+        atom: fn(&mut Self, &'a T) -> Result<Value, SchemaError>,
+    ) -> Result<Value, SchemaError>
+    where
+        T: TyAtom,
+    {
+        match bdd {
+            Bdd::Top => Ok(top.clone()),
+            Bdd::Bot => Ok(Value::Bool(false)),
+            Bdd::Atom {
+                atom: a,
+                pos,
+                lu,
+                neg,
+                ..
+            } => {
+                let a = atom(self, a)?;
+                let neg = match neg {
+                    Bdd::Bot => Value::Bool(false),
+                    neg => all_of([top.clone(), not(a.clone()), self.bdd(neg, top, atom)?]),
+                };
+                // The atom is already within `top`.
+                Ok(any_of([
+                    all_of([a, self.bdd(pos, &Value::Bool(true), atom)?]),
+                    self.bdd(lu, top, atom)?,
+                    neg,
+                ]))
+            }
+        }
+    }
+
+    fn type_atom(&mut self, ty: &'a Type<'a, C>) -> Result<Value, SchemaError> {
+        Ok(any_of([
+            self.bdd(ty.vars, &Value::Bool(true), |_, var| match var {
+                Var::Dyn => Ok(Value::Bool(true)),
+                Var::Named(var) => Err(SchemaError::Var(format!("{var:?}"))),
+            })?,
+            self.bdd(ty.basics, &C::basics_schema(), |_, basic| {
+                Ok(match basic {
+                    Basic::All(basic) => C::basic_schema(basic),
+                    Basic::Literal(literal) => C::literal_schema(literal),
+                })
+            })?,
+            ints(ty.ints),
+            self.bdd(
+                ty.products,
+                &json!({ "type": "array", "minItems": 2, "maxItems": 2 }),
+                Self::product,
+            )?,
+            self.bdd(ty.records, &json!({ "type": "object" }), Self::record)?,
+            self.bdd(ty.refrs, &Value::Bool(true), Self::refr)?,
+        ]))
+    }
+
+    fn product(
+        &mut self,
+        Product(l, r): &'a Product<'a, C, Type<'a, C>>,
+    ) -> Result<Value, SchemaError> {
+        Ok(json!({
+            "type": "array",
+            "prefixItems": [self.ty(l)?, self.ty(r)?],
+            "minItems": 2,
+            "items": false,
+        }))
+    }
+
+    fn record(&mut self, record: &'a Record<'a, C, Type<'a, C>>) -> Result<Value, SchemaError> {
+        let mut properties = Map::new();
+        for (prop, ty) in record.map.iter() {
+            properties.insert(prop.to_string(), self.ty(ty)?);
+        }
+        let required: Vec<_> = record
+            .map
+            .iter()
+            .map(|(prop, _)| prop.to_string())
+            .collect();
+        Ok(json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": record.open == Openness::Open,
+        }))
+    }
+
+    fn refr(&mut self, refr: &'a Refr<'a, C, Type<'a, C>>) -> Result<Value, SchemaError> {
+        // Instances with equivalent arguments share their schema.
+        let solver = &self.solver;
+        let instances = self.instances.entry(&refr.id).or_default();
+        let found = instances.iter().find(|(instance, _)| {
+            instance
+                .args
+                .iter()
+                .zip(&refr.args)
+                .all(|(t1, t2)| solver.is_subtype(t1, t2) && solver.is_subtype(t2, t1))
+        });
+        if let Some((_, key)) = found {
+            return Ok(json!({ "$ref": format!("#/$defs/{key}") }));
+        }
+        if instances.len() == MAX_INSTANCES {
+            return Err(SchemaError::Unbounded(refr.id.to_string()));
+        }
+        let key = if refr.args.is_empty() {
+            refr.id.to_string()
+        } else {
+            format!("{}_{}", refr.id, instances.len())
+        };
+        let body = solver
+            .defs
+            .unfold(solver.arena, refr)
+            .ok_or_else(|| SchemaError::Undefined(refr.id.to_string()))?;
+        instances.push((refr, key.clone()));
+        let schema = self.ty(body)?;
+        self.schemas.insert(key.clone(), schema);
+        Ok(json!({ "$ref": format!("#/$defs/{key}") }))
+    }
+}
+
+fn ints<C>(ints: &Intervals<'_, C>) -> Value
+where
+    C: SchemaConfig,
+{
+    any_of(ints.ranges.iter().map(|(lo, hi)| {
+        if lo == hi {
+            return json!({ "const": number((*lo).into()) });
+        }
+        let mut range = Map::from_iter([("type".to_string(), json!("integer"))]);
+        if *lo != C::Int::MIN {
+            range.insert("minimum".to_string(), number((*lo).into()));
+        }
+        if *hi != C::Int::MAX {
+            range.insert("maximum".to_string(), number((*hi).into()));
+        }
+        Value::Object(range)
+    }))
+}
+
+fn number(int: i128) -> Value {
+    serde_json::Number::from_i128(int).map_or_else(|| json!(int as f64), Value::Number)
+}
+
+fn any_of(schemas: impl IntoIterator<Item = Value>) -> Value {
+    let mut members = Vec::new();
+    for schema in schemas {
+        match schema {
+            Value::Bool(true) => return Value::Bool(true),
+            Value::Bool(false) => {}
+            schema => members.push(schema),
+        }
+    }
+    match members.len() {
+        0 => Value::Bool(false),
+        1 => members.pop().unwrap(),
+        _ => json!({ "anyOf": members }),
+    }
+}
+
+fn all_of(schemas: impl IntoIterator<Item = Value>) -> Value {
+    let mut members = Vec::new();
+    for schema in schemas {
+        match schema {
+            Value::Bool(false) => return Value::Bool(false),
+            Value::Bool(true) => {}
+            schema => members.push(schema),
+        }
+    }
+    match members.len() {
+        0 => Value::Bool(true),
+        1 => members.pop().unwrap(),
+        _ => json!({ "allOf": members }),
+    }
+}
+
+fn not(schema: Value) -> Value {
+    match schema {
+        Value::Bool(b) => Value::Bool(!b),
+        schema => json!({ "not": schema }),
+    }
+}
+
+/// The type of the values matching `schema`. Each entry of the root's `$defs` that is referenced
+/// is defined in `defs`, without parameters, under its key. Arrays are read as tuples of at
+/// least two items, encoded as nested pairs, so their length must be known: `prefixItems` gives
+/// the types of the items, and must come with `"items": false` and a `minItems` of its length.
+/// Other arrays are pairs: `"type": "array"` stands for all of them, and `items` or a length
+/// other than 2 is unsupported. A closed object is a union with a record for each set of its
+/// optional properties, so it should only have a few of them. The bounds of numbers only
+/// constrain integers, and need `TyConfig::INTERVALS`: without it, integers are the basic for
+/// `integer` and their ranges are unsupported.
+pub fn from_schema<'a, C>(
+    ctx: &'a Context<C>,
+    defs: &mut Defs<'a, C>,
+    schema: &Value,
+) -> Result<&'a Bdd<'a, C, Type<'a, C>>, ImportError>
+where
+    C: SchemaConfig<TyName: FromStr, Prop: FromStr, Int: TryFrom<i128>>,
+{
+    let empty = Map::new();
+    let root = match schema.get("$defs") {
+        None => &empty,
+        Some(Value::Object(root)) => root,
+        Some(_) => return Err(ImportError::Malformed("$defs".to_string())),
+    };
+    let mut importer = Importer {
+        ctx,
+        defs,
+        root,
+        defined: HashSet::new(),
+    };
+    importer.schema(schema)
+}
+
+struct Importer<'d, 'r, 'a, C>
+where
+    C: TyConfig,
+{
+    ctx: &'a Context<C>,
+    defs: &'d mut Defs<'a, C>,
+    root: &'r Map<String, Value>,
+    /// The entries of `$defs` defined (or being defined) so far.
+    defined: HashSet<&'r str>,
+}
+impl<'d, 'r, 'a, C> Importer<'d, 'r, 'a, C>
+where
+    C: SchemaConfig<TyName: FromStr, Prop: FromStr, Int: TryFrom<i128>>,
+{
+    fn schema(&mut self, schema: &Value) -> Result<&'a Bdd<'a, C, Type<'a, C>>, ImportError> {
+        let ctx = self.ctx;
+        let object = match schema {
+            Value::Bool(true) => return Ok(ctx.top()),
+            Value::Bool(false) => return Ok(ctx.bot()),
+            Value::Object(object) => object,
+            _ => return Err(ImportError::Malformed("schema".to_string())),
+        };
+
+        let mut constraints = Vec::new();
+        let mut ints = None;
+        let mut tuple = None;
+        let (mut items, mut min_items, mut max_items) = (None, None, None);
+        let mut record = false;
+        for (keyword, value) in object {
+            match keyword.as_str() {
+                "type" => constraints.push(self.types(value)?),
+                "const" => constraints.push(self.value(value)?),
+                "enum" => {
+                    let values = array(keyword, value)?;
+                    let values = values.iter().map(|value| self.value(value));
+                    constraints.push(ctx.union(values.collect::<Result<Vec<_>, _>>()?));
+                }
+                "anyOf" | "allOf" => {
+                    let members = array(keyword, value)?;
+                    let members = members.iter().map(|member| self.schema(member));
+                    let members = members.collect::<Result<Vec<_>, _>>()?;
+                    constraints.push(match keyword.as_str() {
+                        "anyOf" => ctx.union(members),
+                        _ => ctx.inter(members),
+                    });
+                }
+                "not" => constraints.push(ctx.not(self.schema(value)?)),
+                "$ref" => constraints.push(self.refr(value)?),
+                "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" => {
+                    // Ranges of integers are intervals, so they need the interval component.
+                    if !C::INTERVALS {
+                        return Err(ImportError::Unsupported(keyword.clone()));
+                    }
+                    let bound = value
+                        .as_i64()
+                        .map(i128::from)
+                        .or_else(|| value.as_u64().map(i128::from))
+                        .ok_or_else(|| ImportError::Unsupported(keyword.clone()))?;
+                    let (lo, hi) = ints.get_or_insert((i128::MIN, i128::MAX));
+                    match keyword.as_str() {
+                        "minimum" => *lo = (*lo).max(bound),
+                        "maximum" => *hi = (*hi).min(bound),
+                        "exclusiveMinimum" => *lo = (*lo).max(bound + 1),
+                        _ => *hi = (*hi).min(bound - 1),
+                    }
+                }
+                "prefixItems" => {
+                    let items = array(keyword, value)?;
+                    if items.len() < 2 {
+                        return Err(ImportError::Unsupported(keyword.clone()));
+                    }
+                    let items = items.iter().map(|item| self.schema(item));
+                    tuple = Some(items.collect::<Result<Vec<_>, _>>()?);
+                }
+                "properties" | "required" | "additionalProperties" => record = true,
+                "items" => items = Some(value),
+                "minItems" | "maxItems" => {
+                    let bound = value
+                        .as_u64()
+                        .ok_or_else(|| ImportError::Malformed(keyword.clone()))?;
+                    match keyword.as_str() {
+                        "minItems" => min_items = Some(bound),
+                        _ => max_items = Some(bound),
+                    }
+                }
+                "$schema" | "$defs" | "$comment" | "title" | "description" | "default"
+                | "examples" | "deprecated" | "readOnly" | "writeOnly" => {}
+                _ => return Err(ImportError::Unsupported(keyword.clone())),
+            }
+        }
+
+        // The keywords of a kind of values don't constrain the other kinds.
+        let arena = &ctx.arena;
+        if let Some((lo, hi)) = ints {
+            let all = all_ints(ctx).expect("intervals have all the integers");
+            let ints = match (
+                C::Int::try_from(lo.max((C::Int::MIN).into())),
+                C::Int::try_from(hi.min((C::Int::MAX).into())),
+            ) {
+                (Ok(lo), Ok(hi)) if lo <= hi => {
+                    ctx.ty(Type::from_ints(arena, ctx.interval(lo, hi)))
+                }
+                _ => ctx.bot(),
+            };
+            constraints.push(ctx.union([ints, ctx.not(all)]));
+        }
+        // Arrays must have a known length: that of `prefixItems`, without other items, or two.
+        let length = tuple.as_ref().map_or(2, Vec::len) as u64;
+        if tuple.is_some() && items != Some(&Value::Bool(false)) {
+            return Err(ImportError::Unsupported("prefixItems".to_string()));
+        }
+        if tuple.is_none() && items.is_some() {
+            return Err(ImportError::Unsupported("items".to_string()));
+        }
+        let unknown_length = match (&tuple, min_items, max_items) {
+            (_, Some(bound), _) if bound != length => Some("minItems"),
+            (_, _, Some(bound)) if bound != length => Some("maxItems"),
+            // `prefixItems` alone also allows fewer items.
+            (Some(_), None, _) => Some("prefixItems"),
+            (None, Some(_), None) => Some("minItems"),
+            (None, None, Some(_)) => Some("maxItems"),
+            _ => None,
+        };
+        if let Some(keyword) = unknown_length {
+            return Err(ImportError::Unsupported(keyword.to_string()));
+        }
+        // Tuples are nested pairs: `[a, b, c]` is `(a, (b, c))`.
+        let pair = |l, r| ctx.ty(Type::from_products(arena, ctx.product(l, r)));
+        let tuple = tuple.and_then(|items| items.into_iter().rev().reduce(|r, l| pair(l, r)));
+        if let Some(tuple) = tuple {
+            let all = pair(ctx.top(), ctx.top());
+            constraints.push(ctx.union([tuple, ctx.not(all)]));
+        }
+        if record {
+            let all = ctx.ty(Type::from_records(arena, ctx.record(Openness::Open, [])));
+            constraints.push(ctx.union([self.record(object)?, ctx.not(all)]));
+        }
+        Ok(ctx.inter(constraints))
+    }
+
+    fn types(&mut self, value: &Value) -> Result<&'a Bdd<'a, C, Type<'a, C>>, ImportError> {
+        let ctx = self.ctx;
+        let arena = &ctx.arena;
+        let names = match value {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Option<_>>()
+                .ok_or_else(|| ImportError::Malformed("type".to_string()))?,
+            _ => return Err(ImportError::Malformed("type".to_string())),
+        };
+        let mut types = Vec::new();
+        for name in names {
+            match name {
+                "object" => {
+                    types.push(ctx.ty(Type::from_records(arena, ctx.record(Openness::Open, []))))
+                }
+                "array" => types.push(ctx.ty(Type::from_products(
+                    arena,
+                    ctx.product(ctx.top(), ctx.top()),
+                ))),
+                "integer" => types.push(
+                    all_ints(ctx)
+                        .ok_or_else(|| ImportError::Unsupported("type integer".to_string()))?,
+                ),
+                name => {
+                    if name == "number" {
+                        types.extend(all_ints(ctx));
+                    }
+                    let basic = C::schema_basic(name)
+                        .ok_or_else(|| ImportError::Unsupported(format!("type {name}")))?;
+                    types.push(ctx.ty(Type::from_basics(arena, ctx.basic(basic))));
+                }
+            }
+        }
+        Ok(ctx.union(types))
+    }
+
+    fn value(&mut self, value: &Value) -> Result<&'a Bdd<'a, C, Type<'a, C>>, ImportError> {
+        let ctx = self.ctx;
+        let int = value
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| value.as_u64().map(i128::from))
+            .filter(|_| C::INTERVALS);
+        if let Some(int) = int {
+            return Ok(match C::Int::try_from(int) {
+                Ok(int) => ctx.ty(Type::from_ints(&ctx.arena, ctx.interval(int, int))),
+                Err(_) => ctx.bot(),
+            });
+        }
+        let literal = C::schema_literal(value)
+            .ok_or_else(|| ImportError::Unsupported(format!("value {value}")))?;
+        Ok(ctx.ty(Type::from_basics(&ctx.arena, ctx.literal(literal))))
+    }
+
+    fn record(
+        &mut self,
+        object: &Map<String, Value>,
+    ) -> Result<&'a Bdd<'a, C, Type<'a, C>>, ImportError> {
+        let ctx = self.ctx;
+        let empty = Map::new();
+        let properties = match object.get("properties") {
+            None => &empty,
+            Some(Value::Object(properties)) => properties,
+            Some(_) => return Err(ImportError::Malformed("properties".to_string())),
+        };
+        let required = match object.get("required") {
+            None => &Vec::new(),
+            Some(value) => array("required", value)?,
+        };
+        let required = required
+            .iter()
+            .map(|prop| prop.as_str())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| ImportError::Malformed("required".to_string()))?;
+        let open = match object.get("additionalProperties") {
+            None | Some(Value::Bool(true)) => Openness::Open,
+            Some(Value::Bool(false)) => Openness::Closed,
+            Some(_) => return Err(ImportError::Unsupported("additionalProperties".to_string())),
+        };
+
+        let mut fields = Vec::new();
+        for prop in &required {
+            let ty = match properties.get(*prop) {
+                Some(schema) => self.schema(schema)?,
+                None => ctx.top(),
+            };
+            fields.push((parse_prop(prop)?, ty));
+        }
+        let mut optional = Vec::new();
+        for (prop, schema) in properties {
+            if !required.contains(&prop.as_str()) {
+                optional.push((parse_prop(prop)?, self.schema(schema)?));
+            }
+        }
+
+        let arena = &ctx.arena;
+        let records = match open {
+            // An optional property is either absent or of its type.
+            Openness::Open => {
+                let record = ctx.ty(Type::from_records(arena, ctx.record(open, fields)));
+                let optional = optional.into_iter().map(|(prop, ty)| {
+                    let other = ctx.record(open, [(prop, ctx.not(ty))]);
+                    ctx.not(ctx.ty(Type::from_records(arena, other)))
+                });
+                ctx.inter(std::iter::once(record).chain(optional))
+            }
+            // Closed records list their properties, so there is one record for each set of
+            // optional properties.
+            Openness::Closed => {
+                let records = optional.into_iter().fold(vec![fields], |records, field| {
+                    records
+                        .into_iter()
+                        .flat_map(|fields| {
+                            let mut with = fields.clone();
+                            with.push(field.clone());
+                            [fields, with]
+                        })
+                        .collect()
+                });
+                let records = records.into_iter().map(|fields| ctx.record(open, fields));
+                ctx.ty(Type::from_records(arena, ctx.union(records)))
+            }
+        };
+        Ok(records)
+    }
+
+    fn refr(&mut self, value: &Value) -> Result<&'a Bdd<'a, C, Type<'a, C>>, ImportError> {
+        let ctx = self.ctx;
+        let target = value
+            .as_str()
+            .ok_or_else(|| ImportError::Malformed("$ref".to_string()))?;
+        let bad_ref = || ImportError::BadRef(target.to_string());
+        let (key, schema) = target
+            .strip_prefix("#/$defs/")
+            .and_then(|key| self.root.get_key_value(key))
+            .ok_or_else(bad_ref)?;
+        let name: C::TyName = key.parse().map_err(|_| bad_ref())?;
+        if self.defined.insert(key) {
+            let body = self.schema(schema)?;
+            self.defs.define(name.clone(), Vec::new(), body);
+        }
+        Ok(ctx.ty(Type::from_refrs(&ctx.arena, ctx.refr(name, []))))
+    }
+}
+
+/// The type of all the integers: the whole interval with `TyConfig::INTERVALS`, and otherwise the
+/// basic for `integer`, if there is one.
+fn all_ints<C>(ctx: &Context<C>) -> Option<&Bdd<'_, C, Type<'_, C>>>
+where
+    C: SchemaConfig,
+{
+    if C::INTERVALS {
+        let all = ctx.interval(C::Int::MIN, C::Int::MAX);
+        return Some(ctx.ty(Type::from_ints(&ctx.arena, all)));
+    }
+    let basic = C::schema_basic("integer").filter(C::int_basic)?;
+    Some(ctx.ty(Type::from_basics(&ctx.arena, ctx.basic(basic))))
+}
+
+fn parse_prop<P>(prop: &str) -> Result<P, ImportError>
+where
+    P: FromStr,
+{
+    prop.parse()
+        .map_err(|_| ImportError::Malformed(format!("property {prop}")))
+}
+
+fn array<'v>(keyword: &str, value: &'v Value) -> Result<&'v Vec<Value>, ImportError> {
+    value
+        .as_array()
+        .ok_or_else(|| ImportError::Malformed(keyword.to_string()))
+}
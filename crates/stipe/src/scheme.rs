@@ -0,0 +1,177 @@
+//! Type schemes `∀a₁ ≥ l₁ ≤ u₁, …. t`, quantifying over variables within bounds. A bounded
+//! variable `a` is encoded as `l ∨ (a' ∧ u)` for some unbounded `a'`, which stands for every type
+//! between `l` and `u`.
+
+use crate::{
+    Context,
+    bdd::{Bdd, Solver, Tally, Type, Var},
+    ty::TyConfig,
+};
+
+/// A quantified variable and its bounds.
+#[derive(Debug)]
+pub struct Quantified<'a, C>
+where
+    C: TyConfig,
+{
+    pub var: C::Var,
+    pub lower: &'a Bdd<'a, C, Type<'a, C>>,
+    pub upper: &'a Bdd<'a, C, Type<'a, C>>,
+}
+impl<'a, C> Clone for Quantified<'a, C>
+where
+    C: TyConfig,
+{
+    fn clone(&self) -> Self {
+        Self {
+            var: self.var.clone(),
+            lower: self.lower,
+            upper: self.upper,
+        }
+    }
+}
+impl<'a, C> Quantified<'a, C>
+where
+    C: TyConfig,
+{
+    pub fn new(arena: &'a bumpalo::Bump, var: C::Var) -> Self {
+        Self {
+            var,
+            lower: Bdd::bot(arena),
+            upper: Bdd::top(arena),
+        }
+    }
+
+    pub fn with_lower(self, lower: &'a Bdd<'a, C, Type<'a, C>>) -> Self {
+        Self { lower, ..self }
+    }
+
+    pub fn with_upper(self, upper: &'a Bdd<'a, C, Type<'a, C>>) -> Self {
+        Self { upper, ..self }
+    }
+}
+
+#[derive(Debug)]
+pub struct Scheme<'a, C>
+where
+    C: TyConfig,
+{
+    /// The quantified variables, whose bounds may mention each other.
+    pub quantified: Vec<Quantified<'a, C>>,
+    pub body: &'a Bdd<'a, C, Type<'a, C>>,
+}
+impl<'a, C> Scheme<'a, C>
+where
+    C: TyConfig,
+{
+    /// A scheme without quantified variables.
+    pub fn mono(body: &'a Bdd<'a, C, Type<'a, C>>) -> Self {
+        Self {
+            quantified: Vec::new(),
+            body,
+        }
+    }
+
+    /// Quantifies over the variables of `ty` that aren't free in the types of `env`.
+    pub fn generalize<I>(arena: &'a bumpalo::Bump, ty: &'a Bdd<'a, C, Type<'a, C>>, env: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Bdd<'a, C, Type<'a, C>>>,
+    {
+        let monomorphic: Vec<_> = env.into_iter().flat_map(|ty| ty.free_vars()).collect();
+        Self {
+            quantified: ty
+                .free_vars()
+                .into_iter()
+                .filter(|var| !monomorphic.contains(var))
+                .map(|var| Quantified::new(arena, var.clone()))
+                .collect(),
+            body: ty,
+        }
+    }
+
+    /// The quantified variables of the scheme.
+    pub fn vars(&self) -> impl Iterator<Item = &C::Var> {
+        self.quantified.iter().map(|q| &q.var)
+    }
+
+    /// The body of the scheme with the quantified variables replaced by fresh ones made by
+    /// `ctx`, within their bounds. The fresh variables are returned too.
+    pub fn instantiate(&self, ctx: &'a Context<C>) -> (&'a Bdd<'a, C, Type<'a, C>>, Vec<C::Var>) {
+        let arena = &ctx.arena;
+        let vars: Vec<_> = self
+            .vars()
+            .map(|var| ctx.fresh_var(&format!("{var:?}")))
+            .collect();
+        let mut encoded = vec![None; vars.len()];
+        for i in 0..vars.len() {
+            self.encode(arena, &vars, i, &mut encoded, &mut Vec::new());
+        }
+        let body = Bdd::subst(arena, self.body, &|var| match var {
+            Var::Named(var) => self.vars().position(|v| v == var).and_then(|i| encoded[i]),
+            Var::Dyn => None,
+        });
+        (body, vars)
+    }
+
+    /// Computes the encoding `l ∨ (a' ∧ u)` of the `i`th quantified variable, with `vars[i]` as
+    /// `a'`. The quantified variables mentioned by the bounds are replaced by their encodings,
+    /// which are computed first, so that their own bounds apply too. Those whose encoding is
+    /// `pending`, as the bounds mention each other in a cycle, are replaced by their fresh
+    /// variable.
+    fn encode(
+        &self,
+        arena: &'a bumpalo::Bump,
+        vars: &[C::Var],
+        i: usize,
+        encoded: &mut [Option<&'a Bdd<'a, C, Type<'a, C>>>],
+        pending: &mut Vec<usize>,
+    ) {
+        if encoded[i].is_some() {
+            return;
+        }
+        pending.push(i);
+        let Quantified { lower, upper, .. } = self.quantified[i];
+        for var in lower.free_vars().into_iter().chain(upper.free_vars()) {
+            if let Some(j) = self.vars().position(|v| v == var)
+                && !pending.contains(&j)
+            {
+                self.encode(arena, vars, j, encoded, pending);
+            }
+        }
+        pending.pop();
+        let encoded_var = |j: usize| encoded[j].unwrap_or_else(|| var_ty(arena, vars[j].clone()));
+        let subst = |ty| {
+            Bdd::subst(arena, ty, &|var| match var {
+                Var::Named(var) => self.vars().position(|v| v == var).map(encoded_var),
+                Var::Dyn => None,
+            })
+        };
+        let var = var_ty(arena, vars[i].clone());
+        let ty = Bdd::union(arena, subst(lower), Bdd::inter(arena, var, subst(upper)));
+        encoded[i] = Some(ty);
+    }
+
+    /// Whether `self` is at least as general as `other`: some instance of `self` is a subtype of
+    /// every instance of `other`, whose quantified variables are kept rigid.
+    pub fn subsumes(&self, solver: &Solver<'_, 'a, C>, other: &Self) -> bool {
+        let (specific, _) = other.instantiate(solver.ctx);
+        let (general, flexible) = self.instantiate(solver.ctx);
+        let is_flexible = |var: &C::Var| flexible.contains(var);
+        !Tally::new(solver, &is_flexible)
+            .solve(&[(general, specific)])
+            .is_empty()
+    }
+}
+
+fn var_ty<'a, C>(arena: &'a bumpalo::Bump, var: C::Var) -> &'a Bdd<'a, C, Type<'a, C>>
+where
+    C: TyConfig,
+{
+    Bdd::atom(
+        arena,
+        arena.alloc(Type::from_vars(
+            arena,
+            Bdd::atom(arena, arena.alloc(Var::Named(var))),
+        )),
+    )
+}
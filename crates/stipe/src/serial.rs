@@ -0,0 +1,669 @@
+//! Serialization of types, to cache them across runs. Types are DAGs allocated in the arena of a
+//! `Context`, so they're flattened into an `Interface`: a table of nodes referring to each other
+//! by index, where each node shared by several types (or several times in one type) appears once.
+//! An `Interface` is serialized with serde, and decoded back into the arena of any `Context`. The
+//! atoms of the `TyConfig` are serialized with their own impls.
+
+use std::{collections::HashMap, marker::PhantomData, ptr};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    Context,
+    bdd::{self, Arrow, Basic, Bdd, Intervals, Product, Record, Refr, TyAtom, Type, Var},
+    ty::{self, Ty, TyConfig},
+};
+
+/// A configuration whose atoms can be serialized.
+pub trait SerialConfig:
+    TyConfig<
+        TyName: Serialize + DeserializeOwned,
+        Basic: Serialize + DeserializeOwned,
+        Literal: Serialize + DeserializeOwned,
+        Int: Serialize + DeserializeOwned,
+        Var: Serialize + DeserializeOwned,
+        Prop: Serialize + DeserializeOwned,
+    >
+{
+}
+impl<C> SerialConfig for C where
+    C: TyConfig<
+            TyName: Serialize + DeserializeOwned,
+            Basic: Serialize + DeserializeOwned,
+            Literal: Serialize + DeserializeOwned,
+            Int: Serialize + DeserializeOwned,
+            Var: Serialize + DeserializeOwned,
+            Prop: Serialize + DeserializeOwned,
+        >
+{
+}
+
+/// A node of an `Interface`. The nodes of BDDs are shared by all the kinds of atoms, the kind of
+/// a node being the one expected where it's referenced.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "C: SerialConfig")]
+enum Node<C>
+where
+    C: TyConfig,
+{
+    Bot,
+    Top,
+    Atom {
+        atom: u32,
+        pos: u32,
+        lu: u32,
+        neg: u32,
+    },
+    Type {
+        vars: u32,
+        basics: u32,
+        ints: u32,
+        products: u32,
+        arrows: u32,
+        records: u32,
+        refrs: u32,
+    },
+    Dyn,
+    Var(C::Var),
+    Basic(C::Basic),
+    Literal(C::Literal),
+    Ints(Vec<(C::Int, C::Int)>),
+    Product(u32, u32),
+    Arrow(u32, u32),
+    Record {
+        fields: Vec<(C::Prop, u32)>,
+        open: bool,
+    },
+    Refr {
+        id: C::TyName,
+        args: Vec<u32>,
+    },
+    TyTop,
+    TyBot,
+    TyVar(C::Var),
+    TyBasic(C::Basic),
+    TyProduct(u32, u32),
+    TyArrow(u32, u32),
+    TyRecord {
+        fields: Vec<(C::Prop, u32)>,
+        open: bool,
+    },
+    TyUnion(Vec<u32>),
+    TyInter(Vec<u32>),
+    TyNot(u32),
+    TyRef {
+        id: C::TyName,
+        args: Vec<u32>,
+    },
+}
+impl<C> Node<C>
+where
+    C: TyConfig,
+{
+    /// The indices of the nodes this one refers to.
+    fn children(&self) -> Vec<u32> {
+        match self {
+            Node::Atom { atom, pos, lu, neg } => vec![*atom, *pos, *lu, *neg],
+            Node::Type {
+                vars,
+                basics,
+                ints,
+                products,
+                arrows,
+                records,
+                refrs,
+            } => vec![*vars, *basics, *ints, *products, *arrows, *records, *refrs],
+            Node::Product(l, r)
+            | Node::Arrow(l, r)
+            | Node::TyProduct(l, r)
+            | Node::TyArrow(l, r) => vec![*l, *r],
+            Node::Record { fields, .. } | Node::TyRecord { fields, .. } => {
+                fields.iter().map(|(_, ty)| *ty).collect()
+            }
+            Node::Refr { args, .. }
+            | Node::TyRef { args, .. }
+            | Node::TyUnion(args)
+            | Node::TyInter(args) => args.clone(),
+            Node::TyNot(ty) => vec![*ty],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Types flattened into a table of nodes, whose roots are `bdd::Type`s and `ty::Ty`s in the order
+/// they were added to the `Encoder`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "C: SerialConfig")]
+pub struct Interface<C>
+where
+    C: TyConfig,
+{
+    nodes: Vec<Node<C>>,
+    types: Vec<u32>,
+    tys: Vec<u32>,
+}
+
+/// The types of an `Interface`, decoded into the arena of a `Context`.
+pub struct Decoded<'a, C>
+where
+    C: TyConfig,
+{
+    pub types: Vec<&'a Bdd<'a, C, Type<'a, C>>>,
+    pub tys: Vec<&'a Ty<'a, C>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// A node refers to a node that doesn't exist or doesn't come before it.
+    BadIndex(u32),
+    /// A node isn't of the kind expected where it's referenced.
+    BadKind(u32),
+}
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadIndex(index) => write!(f, "node {index} is missing or referenced too early"),
+            Self::BadKind(index) => write!(f, "node {index} isn't of the expected kind"),
+        }
+    }
+}
+impl std::error::Error for DecodeError {}
+
+/// Flattens types into an `Interface`.
+pub struct Encoder<'a, C>
+where
+    C: TyConfig,
+{
+    interface: Interface<C>,
+    /// The indices of the nodes already encoded, by address.
+    seen: HashMap<*const (), u32>,
+    bot: Option<u32>,
+    top: Option<u32>,
+    _a: PhantomData<&'a ()>,
+}
+impl<'a, C> Encoder<'a, C>
+where
+    C: SerialConfig,
+{
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            interface: Interface {
+                nodes: Vec::new(),
+                types: Vec::new(),
+                tys: Vec::new(),
+            },
+            seen: HashMap::new(),
+            bot: None,
+            top: None,
+            _a: PhantomData,
+        }
+    }
+
+    /// Adds a type, returning its position among the decoded `types`.
+    pub fn add_type(&mut self, ty: &'a Bdd<'a, C, Type<'a, C>>) -> usize {
+        let index = self.bdd(ty, Self::type_atom);
+        self.interface.types.push(index);
+        self.interface.types.len() - 1
+    }
+
+    /// Adds a type expression, returning its position among the decoded `tys`.
+    pub fn add_ty(&mut self, ty: &'a Ty<'a, C>) -> usize {
+        let index = self.ty(ty);
+        self.interface.tys.push(index);
+        self.interface.tys.len() - 1
+    }
+
+    pub fn finish(self) -> Interface<C> {
+        self.interface
+    }
+
+    fn push(&mut self, node: Node<C>) -> u32 {
+        self.interface.nodes.push(node);
+        (self.interface.nodes.len() - 1) as u32
+    }
+
+    /// The index of the node for `value`, encoded by `encode` (after the nodes it refers to) if
+    /// it hasn't been yet.
+    fn shared<X>(&mut self, value: &'a X, encode: impl FnOnce(&mut Self) -> Node<C>) -> u32 {
+        let key = ptr::from_ref(value).cast();
+        if let Some(index) = self.seen.get(&key) {
+            return *index;
+        }
+        let node = encode(self);
+        let index = self.push(node);
+        self.seen.insert(key, index);
+        index
+    }
+
+    fn bdd<T>(&mut self, bdd: &'a Bdd<'a, C, T>, atom: fn(&mut Self, &'a T) -> u32) -> u32
+    where
+        T: TyAtom,
+    {
+        match bdd {
+            Bdd::Bot => match self.bot {
+                Some(index) => index,
+                None => {
+                    let index = self.push(Node::Bot);
+                    *self.bot.insert(index)
+                }
+            },
+            Bdd::Top => match self.top {
+                Some(index) => index,
+                None => {
+                    let index = self.push(Node::Top);
+                    *self.top.insert(index)
+                }
+            },
+            Bdd::Atom {
+                atom: a,
+                pos,
+                lu,
+                neg,
+                ..
+            } => self.shared(bdd, |this| Node::Atom {
+                atom: atom(this, a),
+                pos: this.bdd(pos, atom),
+                lu: this.bdd(lu, atom),
+                neg: this.bdd(neg, atom),
+            }),
+        }
+    }
+
+    fn type_atom(&mut self, ty: &'a Type<'a, C>) -> u32 {
+        self.shared(ty, |this| Node::Type {
+            vars: this.bdd(ty.vars, |this, var| {
+                this.shared(var, |_| match var {
+                    Var::Dyn => Node::Dyn,
+                    Var::Named(var) => Node::Var(var.clone()),
+                })
+            }),
+            basics: this.bdd(ty.basics, |this, basic| {
+                this.shared(basic, |_| match basic {
+                    Basic::All(basic) => Node::Basic(basic.clone()),
+                    Basic::Literal(literal) => Node::Literal(literal.clone()),
+                })
+            }),
+            ints: this.shared(ty.ints, |_| Node::Ints(ty.ints.ranges.to_vec())),
+            products: this.bdd(ty.products, |this, product| {
+                this.shared(product, |this| {
+                    Node::Product(
+                        this.bdd(product.0, Self::type_atom),
+                        this.bdd(product.1, Self::type_atom),
+                    )
+                })
+            }),
+            arrows: this.bdd(ty.arrows, |this, arrow| {
+                this.shared(arrow, |this| {
+                    Node::Arrow(
+                        this.bdd(arrow.0, Self::type_atom),
+                        this.bdd(arrow.1, Self::type_atom),
+                    )
+                })
+            }),
+            records: this.bdd(ty.records, |this, record| {
+                this.shared(record, |this| Node::Record {
+                    fields: record
+                        .map
+                        .iter()
+                        .map(|(prop, ty)| (prop.clone(), this.bdd(ty, Self::type_atom)))
+                        .collect(),
+                    open: record.open == bdd::Openness::Open,
+                })
+            }),
+            refrs: this.bdd(ty.refrs, |this, refr| {
+                this.shared(refr, |this| Node::Refr {
+                    id: refr.id.clone(),
+                    args: refr
+                        .args
+                        .iter()
+                        .map(|arg| this.bdd(arg, Self::type_atom))
+                        .collect(),
+                })
+            }),
+        })
+    }
+
+    fn ty(&mut self, ty: &'a Ty<'a, C>) -> u32 {
+        self.shared(ty, |this| match ty {
+            Ty::Top => Node::TyTop,
+            Ty::Bot => Node::TyBot,
+            Ty::Var(var) => Node::TyVar(var.clone()),
+            Ty::Basic(basic) => Node::TyBasic(basic.clone()),
+            Ty::Product(ty::Product(l, r)) => Node::TyProduct(this.ty(l), this.ty(r)),
+            Ty::Arrow(ty::Arrow(d, c)) => Node::TyArrow(this.ty(d), this.ty(c)),
+            Ty::Record(record) => Node::TyRecord {
+                fields: record
+                    .map
+                    .iter()
+                    .map(|(prop, ty)| (prop.clone(), this.ty(ty)))
+                    .collect(),
+                open: matches!(record.open, ty::Openness::Open),
+            },
+            Ty::Union(members) => Node::TyUnion(members.iter().map(|ty| this.ty(ty)).collect()),
+            Ty::Inter(members) => Node::TyInter(members.iter().map(|ty| this.ty(ty)).collect()),
+            Ty::Not(ty) => Node::TyNot(this.ty(ty)),
+            Ty::Ref(refr) => Node::TyRef {
+                id: refr.id.clone(),
+                args: refr.args.iter().map(|arg| this.ty(arg)).collect(),
+            },
+        })
+    }
+}
+
+impl<C> Interface<C>
+where
+    C: SerialConfig,
+{
+    /// Allocates the types in the arena of `ctx`, with the same sharing as when they were
+    /// encoded.
+    pub fn decode<'a>(&self, ctx: &'a Context<C>) -> Result<Decoded<'a, C>, DecodeError> {
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let Some(child) = node.children().into_iter().find(|c| *c as usize >= index) {
+                return Err(DecodeError::BadIndex(child));
+            }
+        }
+        if let Some(root) = self
+            .types
+            .iter()
+            .chain(&self.tys)
+            .find(|r| **r as usize >= self.nodes.len())
+        {
+            return Err(DecodeError::BadIndex(*root));
+        }
+        let mut decoder = Decoder {
+            nodes: &self.nodes,
+            arena: &ctx.arena,
+            types: HashMap::new(),
+            type_atoms: HashMap::new(),
+            vars: HashMap::new(),
+            var_atoms: HashMap::new(),
+            basics: HashMap::new(),
+            basic_atoms: HashMap::new(),
+            ints: HashMap::new(),
+            products: HashMap::new(),
+            product_atoms: HashMap::new(),
+            arrows: HashMap::new(),
+            arrow_atoms: HashMap::new(),
+            records: HashMap::new(),
+            record_atoms: HashMap::new(),
+            refrs: HashMap::new(),
+            refr_atoms: HashMap::new(),
+            tys: HashMap::new(),
+        };
+        Ok(Decoded {
+            types: self
+                .types
+                .iter()
+                .map(|index| decoder.bdd(*index, |d| &mut d.types, Decoder::type_atom))
+                .collect::<Result<_, _>>()?,
+            tys: self
+                .tys
+                .iter()
+                .map(|index| decoder.ty(*index))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// The BDDs of some kind already decoded, by index.
+type Memo<'a, C, T> = HashMap<u32, &'a Bdd<'a, C, T>>;
+
+/// The atoms of some kind already decoded, by index.
+type Atoms<'a, T> = HashMap<u32, &'a T>;
+
+struct Decoder<'i, 'a, C>
+where
+    C: TyConfig,
+{
+    nodes: &'i [Node<C>],
+    arena: &'a bumpalo::Bump,
+    types: Memo<'a, C, Type<'a, C>>,
+    type_atoms: Atoms<'a, Type<'a, C>>,
+    vars: Memo<'a, C, Var<C>>,
+    var_atoms: Atoms<'a, Var<C>>,
+    basics: Memo<'a, C, Basic<C>>,
+    basic_atoms: Atoms<'a, Basic<C>>,
+    ints: Atoms<'a, Intervals<'a, C>>,
+    products: Memo<'a, C, Product<'a, C, Type<'a, C>>>,
+    product_atoms: Atoms<'a, Product<'a, C, Type<'a, C>>>,
+    arrows: Memo<'a, C, Arrow<'a, C, Type<'a, C>>>,
+    arrow_atoms: Atoms<'a, Arrow<'a, C, Type<'a, C>>>,
+    records: Memo<'a, C, Record<'a, C, Type<'a, C>>>,
+    record_atoms: Atoms<'a, Record<'a, C, Type<'a, C>>>,
+    refrs: Memo<'a, C, Refr<'a, C, Type<'a, C>>>,
+    refr_atoms: Atoms<'a, Refr<'a, C, Type<'a, C>>>,
+    tys: Atoms<'a, Ty<'a, C>>,
+}
+impl<'i, 'a, C> Decoder<'i, 'a, C>
+where
+    C: SerialConfig,
+{
+    fn bdd<T>(
+        &mut self,
+        index: u32,
+        memo: fn(&mut Self) -> &mut Memo<'a, C, T>,
+        atom: fn(&mut Self, u32) -> Result<&'a T, DecodeError>,
+    ) -> Result<&'a Bdd<'a, C, T>, DecodeError>
+    where
+        T: TyAtom,
+    {
+        if let Some(bdd) = memo(self).get(&index) {
+            return Ok(bdd);
+        }
+        let bdd = match self.nodes[index as usize] {
+            Node::Bot => Bdd::bot(self.arena),
+            Node::Top => Bdd::top(self.arena),
+            Node::Atom {
+                atom: a,
+                pos,
+                lu,
+                neg,
+            } => &*self.arena.alloc(Bdd::Atom {
+                atom: atom(self, a)?,
+                pos: self.bdd(pos, memo, atom)?,
+                lu: self.bdd(lu, memo, atom)?,
+                neg: self.bdd(neg, memo, atom)?,
+                _c: PhantomData,
+            }),
+            _ => return Err(DecodeError::BadKind(index)),
+        };
+        memo(self).insert(index, bdd);
+        Ok(bdd)
+    }
+
+    /// The atom at `index`, decoded by `decode` the first time it's referenced, so that atoms
+    /// shared by several BDDs stay shared.
+    fn atom<T>(
+        &mut self,
+        index: u32,
+        memo: fn(&mut Self) -> &mut Atoms<'a, T>,
+        decode: fn(&mut Self, u32) -> Result<T, DecodeError>,
+    ) -> Result<&'a T, DecodeError> {
+        if let Some(atom) = memo(self).get(&index) {
+            return Ok(atom);
+        }
+        let atom = &*self.arena.alloc(decode(self, index)?);
+        memo(self).insert(index, atom);
+        Ok(atom)
+    }
+
+    fn type_atom(&mut self, index: u32) -> Result<&'a Type<'a, C>, DecodeError> {
+        self.atom(index, |d| &mut d.type_atoms, Self::decode_type)
+    }
+
+    fn decode_type(&mut self, index: u32) -> Result<Type<'a, C>, DecodeError> {
+        let Node::Type {
+            vars,
+            basics,
+            ints,
+            products,
+            arrows,
+            records,
+            refrs,
+        } = self.nodes[index as usize]
+        else {
+            return Err(DecodeError::BadKind(index));
+        };
+        Ok(Type {
+            vars: self.bdd(vars, |d| &mut d.vars, Self::var)?,
+            basics: self.bdd(basics, |d| &mut d.basics, Self::basic)?,
+            ints: self.ints(ints)?,
+            products: self.bdd(products, |d| &mut d.products, Self::product)?,
+            arrows: self.bdd(arrows, |d| &mut d.arrows, Self::arrow)?,
+            records: self.bdd(records, |d| &mut d.records, Self::record)?,
+            refrs: self.bdd(refrs, |d| &mut d.refrs, Self::refr)?,
+            _c: PhantomData,
+        })
+    }
+
+    fn ty_bdd(&mut self, index: u32) -> Result<&'a Bdd<'a, C, Type<'a, C>>, DecodeError> {
+        self.bdd(index, |d| &mut d.types, Self::type_atom)
+    }
+
+    fn var(&mut self, index: u32) -> Result<&'a Var<C>, DecodeError> {
+        self.atom(
+            index,
+            |d| &mut d.var_atoms,
+            |d, index| match &d.nodes[index as usize] {
+                Node::Dyn => Ok(Var::Dyn),
+                Node::Var(var) => Ok(Var::Named(var.clone())),
+                _ => Err(DecodeError::BadKind(index)),
+            },
+        )
+    }
+
+    fn basic(&mut self, index: u32) -> Result<&'a Basic<C>, DecodeError> {
+        self.atom(
+            index,
+            |d| &mut d.basic_atoms,
+            |d, index| match &d.nodes[index as usize] {
+                Node::Basic(basic) => Ok(Basic::All(basic.clone())),
+                Node::Literal(literal) => Ok(Basic::Literal(literal.clone())),
+                _ => Err(DecodeError::BadKind(index)),
+            },
+        )
+    }
+
+    fn ints(&mut self, index: u32) -> Result<&'a Intervals<'a, C>, DecodeError> {
+        if let Some(ints) = self.ints.get(&index) {
+            return Ok(ints);
+        }
+        let Node::Ints(ranges) = &self.nodes[index as usize] else {
+            return Err(DecodeError::BadKind(index));
+        };
+        let ints = Intervals::from_ranges(self.arena, ranges.iter().copied());
+        self.ints.insert(index, ints);
+        Ok(ints)
+    }
+
+    fn product(&mut self, index: u32) -> Result<&'a Product<'a, C, Type<'a, C>>, DecodeError> {
+        self.atom(
+            index,
+            |d| &mut d.product_atoms,
+            |d, index| {
+                let Node::Product(l, r) = d.nodes[index as usize] else {
+                    return Err(DecodeError::BadKind(index));
+                };
+                Ok(Product(d.ty_bdd(l)?, d.ty_bdd(r)?))
+            },
+        )
+    }
+
+    fn arrow(&mut self, index: u32) -> Result<&'a Arrow<'a, C, Type<'a, C>>, DecodeError> {
+        self.atom(
+            index,
+            |d| &mut d.arrow_atoms,
+            |d, index| {
+                let Node::Arrow(dom, cod) = d.nodes[index as usize] else {
+                    return Err(DecodeError::BadKind(index));
+                };
+                Ok(Arrow(d.ty_bdd(dom)?, d.ty_bdd(cod)?))
+            },
+        )
+    }
+
+    fn record(&mut self, index: u32) -> Result<&'a Record<'a, C, Type<'a, C>>, DecodeError> {
+        self.atom(index, |d| &mut d.record_atoms, Self::decode_record)
+    }
+
+    fn decode_record(&mut self, index: u32) -> Result<Record<'a, C, Type<'a, C>>, DecodeError> {
+        let Node::Record { fields, open } = &self.nodes[index as usize] else {
+            return Err(DecodeError::BadKind(index));
+        };
+        let mut map = bumpalo::collections::Vec::with_capacity_in(fields.len(), self.arena);
+        for (prop, ty) in fields {
+            map.push((prop.clone(), self.ty_bdd(*ty)?));
+        }
+        let open = if *open {
+            bdd::Openness::Open
+        } else {
+            bdd::Openness::Closed
+        };
+        Ok(Record { map, open })
+    }
+
+    fn refr(&mut self, index: u32) -> Result<&'a Refr<'a, C, Type<'a, C>>, DecodeError> {
+        self.atom(index, |d| &mut d.refr_atoms, Self::decode_refr)
+    }
+
+    fn decode_refr(&mut self, index: u32) -> Result<Refr<'a, C, Type<'a, C>>, DecodeError> {
+        let Node::Refr { id, args } = &self.nodes[index as usize] else {
+            return Err(DecodeError::BadKind(index));
+        };
+        let mut decoded = bumpalo::collections::Vec::with_capacity_in(args.len(), self.arena);
+        for arg in args {
+            decoded.push(self.ty_bdd(*arg)?);
+        }
+        Ok(Refr {
+            id: id.clone(),
+            args: decoded,
+        })
+    }
+
+    fn ty(&mut self, index: u32) -> Result<&'a Ty<'a, C>, DecodeError> {
+        if let Some(ty) = self.tys.get(&index) {
+            return Ok(ty);
+        }
+        let tys = |this: &mut Self,
+                   indices: &[u32]|
+         -> Result<bumpalo::collections::Vec<'a, &'a Ty<'a, C>>, DecodeError> {
+            let mut tys = bumpalo::collections::Vec::with_capacity_in(indices.len(), this.arena);
+            for index in indices {
+                tys.push(this.ty(*index)?);
+            }
+            Ok(tys)
+        };
+        let ty = match &self.nodes[index as usize] {
+            Node::TyTop => Ty::Top,
+            Node::TyBot => Ty::Bot,
+            Node::TyVar(var) => Ty::Var(var.clone()),
+            Node::TyBasic(basic) => Ty::Basic(basic.clone()),
+            Node::TyProduct(l, r) => Ty::Product(ty::Product(self.ty(*l)?, self.ty(*r)?)),
+            Node::TyArrow(d, c) => Ty::Arrow(ty::Arrow(self.ty(*d)?, self.ty(*c)?)),
+            Node::TyRecord { fields, open } => {
+                let mut map = bumpalo::collections::Vec::with_capacity_in(fields.len(), self.arena);
+                for (prop, ty) in fields {
+                    map.push((prop.clone(), self.ty(*ty)?));
+                }
+                let open = if *open {
+                    ty::Openness::Open
+                } else {
+                    ty::Openness::Closed
+                };
+                Ty::Record(ty::Record { map, open })
+            }
+            Node::TyUnion(members) => Ty::Union(tys(self, members)?),
+            Node::TyInter(members) => Ty::Inter(tys(self, members)?),
+            Node::TyNot(ty) => Ty::Not(self.ty(*ty)?),
+            Node::TyRef { id, args } => Ty::Ref(ty::Ref {
+                id: id.clone(),
+                args: tys(self, args)?,
+            }),
+            _ => return Err(DecodeError::BadKind(index)),
+        };
+        let ty = &*self.arena.alloc(ty);
+        self.tys.insert(index, ty);
+        Ok(ty)
+    }
+}
@@ -0,0 +1,156 @@
+//! Simplification of inferred types, in the spirit of algebraic subtyping (MLsub): a scheme is
+//! replaced with an equivalent one with fewer quantified variables. Variables occurring in only
+//! one polarity are removed, and a variable that is indistinguishable from another variable or
+//! from a type it co-occurs with is replaced by it. Each replacement is checked by subsumption,
+//! so that the simplified scheme is equivalent to the original one.
+
+use crate::{
+    bdd::{Bdd, Polarity, Solver, Type, Var},
+    scheme::Scheme,
+    ty::TyConfig,
+};
+
+impl<'a, C> Scheme<'a, C>
+where
+    C: TyConfig,
+{
+    /// An equivalent scheme with fewer quantified variables. Bounded variables are kept.
+    pub fn simplify(self, solver: &Solver<'_, 'a, C>) -> Self {
+        let arena = solver.arena;
+        let mut scheme = self;
+        // A variable occurring only positively (negatively) can be replaced by the empty (top)
+        // type, which gives a subtype of every instance.
+        let polarities = scheme.body.polarities(solver.defs);
+        let polar: Vec<_> = scheme
+            .unbounded()
+            .filter_map(|var| match polarities.get(var) {
+                Some(Polarity::Positive) => Some((var.clone(), Bdd::bot(arena))),
+                Some(Polarity::Negative) => Some((var.clone(), Bdd::top(arena))),
+                Some(Polarity::Both) => None,
+                None => Some((var.clone(), Bdd::bot(arena))),
+            })
+            .collect();
+        for (var, ty) in polar {
+            scheme = scheme.replace(arena, &var, ty);
+        }
+
+        let mut i = 0;
+        loop {
+            let Some(var) = scheme.unbounded().nth(i).cloned() else {
+                break;
+            };
+            let replacement = candidates(arena, &scheme, &var).into_iter().find_map(|ty| {
+                let simplified = scheme.replace(arena, &var, ty);
+                simplified.subsumes(solver, &scheme).then_some(simplified)
+            });
+            match replacement {
+                Some(simplified) => scheme = simplified,
+                None => i += 1,
+            }
+        }
+        scheme
+    }
+
+    fn unbounded(&self) -> impl Iterator<Item = &C::Var> {
+        self.quantified
+            .iter()
+            .filter(|q| *q.lower == Bdd::Bot && *q.upper == Bdd::Top)
+            .map(|q| &q.var)
+    }
+
+    /// The scheme where `var` isn't quantified anymore and is replaced with `ty` in the body.
+    fn replace(
+        &self,
+        arena: &'a bumpalo::Bump,
+        var: &C::Var,
+        ty: &'a Bdd<'a, C, Type<'a, C>>,
+    ) -> Self {
+        Self {
+            quantified: self
+                .quantified
+                .iter()
+                .filter(|q| q.var != *var)
+                .cloned()
+                .collect(),
+            body: Bdd::subst(arena, self.body, &|v| match v {
+                Var::Named(v) if v == var => Some(ty),
+                _ => None,
+            }),
+        }
+    }
+}
+
+/// The types that `var` might be replaced with: the empty and top types, the other quantified
+/// variables, and the types it co-occurs with in unions and intersections.
+fn candidates<'a, C>(
+    arena: &'a bumpalo::Bump,
+    scheme: &Scheme<'a, C>,
+    var: &C::Var,
+) -> Vec<&'a Bdd<'a, C, Type<'a, C>>>
+where
+    C: TyConfig,
+{
+    let mut candidates = vec![Bdd::bot(arena), Bdd::top(arena)];
+    for other in scheme.vars().filter(|v| *v != var) {
+        candidates.push(Bdd::atom(
+            arena,
+            arena.alloc(Type::from_vars(
+                arena,
+                Bdd::atom(arena, arena.alloc(Var::Named(other.clone()))),
+            )),
+        ));
+    }
+    co_occurrences(arena, scheme.body, var, &mut candidates);
+    candidates
+}
+
+/// Collects the constructor parts of the types found with `var` in the same boolean
+/// combination, at any depth.
+fn co_occurrences<'a, C>(
+    arena: &'a bumpalo::Bump,
+    bdd: &'a Bdd<'a, C, Type<'a, C>>,
+    var: &C::Var,
+    found: &mut Vec<&'a Bdd<'a, C, Type<'a, C>>>,
+) where
+    C: TyConfig,
+{
+    let mut atoms = Vec::new();
+    let mut mentioned = false;
+    bdd.for_each_atom(&mut |ty| {
+        atoms.push(ty);
+        ty.vars
+            .for_each_atom(&mut |v| mentioned |= matches!(v, Var::Named(v) if v == var));
+    });
+    for ty in atoms {
+        if mentioned {
+            let ty = Type {
+                vars: Bdd::bot(arena),
+                ..*ty
+            };
+            if ty != Type::empty(arena) {
+                let ty = Bdd::atom(arena, arena.alloc(ty));
+                if !ty.free_vars().contains(&var) && !found.contains(&ty) {
+                    found.push(ty);
+                }
+            }
+        }
+        ty.products.for_each_atom(&mut |product| {
+            co_occurrences(arena, product.0, var, found);
+            co_occurrences(arena, product.1, var, found);
+        });
+        ty.arrows.for_each_atom(&mut |arrow| {
+            co_occurrences(arena, arrow.0, var, found);
+            co_occurrences(arena, arrow.1, var, found);
+        });
+        ty.records.for_each_atom(&mut |record| {
+            for (_, ty) in record.map.iter() {
+                co_occurrences(arena, ty, var, found);
+            }
+        });
+        ty.refrs.for_each_atom(&mut |refr| {
+            for ty in refr.args.iter() {
+                co_occurrences(arena, ty, var, found);
+            }
+        });
+    }
+}
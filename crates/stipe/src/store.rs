@@ -0,0 +1,478 @@
+//! A type store shared between threads. A `Context` allocates its types in a single arena and
+//! hands out references into it, so its types can't cross threads. A `SharedStore` instead
+//! hash-conses owned type nodes in sharded tables, and hands out `StoreId`s allocated from an
+//! atomic counter.
+//!
+//! The store shares the syntax of types and the results of checks, not their BDDs: its
+//! constructors are named after those of `Context` but take and return `StoreId`s, and a check
+//! that isn't cached materializes its types, and the definitions of the named types, in a fresh
+//! `Context` on the calling thread. Threads build types and look up known results concurrently,
+//! but each new check costs as much as in a `Context` of its own.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{BuildHasher, Hash, RandomState},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU32, Ordering},
+    },
+};
+
+use crate::{
+    Context,
+    bdd::{Bdd, Openness, Type},
+    defs::Defs,
+    ty::TyConfig,
+};
+
+/// The number of shards of each table, which threads lock independently.
+const SHARDS: usize = 16;
+
+/// A configuration whose types can be shared between threads.
+pub trait StoreConfig:
+    TyConfig<
+        TyName: Send + Sync,
+        Basic: Send + Sync,
+        Literal: Send + Sync,
+        Int: Send + Sync,
+        Var: Send + Sync,
+        Prop: Send + Sync,
+    >
+{
+}
+impl<C> StoreConfig for C where
+    C: TyConfig<
+            TyName: Send + Sync,
+            Basic: Send + Sync,
+            Literal: Send + Sync,
+            Int: Send + Sync,
+            Var: Send + Sync,
+            Prop: Send + Sync,
+        >
+{
+}
+
+/// The identity of a type in a `SharedStore`. Types built the same way (up to the order of the
+/// members of unions and intersections) get the same identity. Identities also tell which store
+/// they're from, so that those of another store are rejected rather than mistaken for others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StoreId {
+    store: u32,
+    index: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreError {
+    /// An identity from another store.
+    Foreign(StoreId),
+}
+
+/// The number of stores made so far, which identifies the next one.
+static STORES: AtomicU32 = AtomicU32::new(0);
+
+/// Takes the next value of `counter`. Panics rather than wrap around, which would give the same
+/// identity to different stores or types.
+fn next(counter: &AtomicU32) -> u32 {
+    counter
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_add(1))
+        .expect("identities exhausted")
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Node<C>
+where
+    C: TyConfig,
+{
+    Top,
+    Bot,
+    Dynamic,
+    Var(C::Var),
+    Basic(C::Basic),
+    Literal(C::Literal),
+    Ints(C::Int, C::Int),
+    Product(StoreId, StoreId),
+    Arrow(StoreId, StoreId),
+    Record(Openness, Vec<(C::Prop, StoreId)>),
+    Refr(C::TyName, Vec<StoreId>),
+    Union(StoreId, StoreId),
+    Inter(StoreId, StoreId),
+    Not(StoreId),
+}
+
+type Shards<K, V> = [RwLock<HashMap<K, V>>; SHARDS];
+
+/// The definition of a named type in a `SharedStore`.
+#[derive(Debug)]
+struct StoreDef<C>
+where
+    C: TyConfig,
+{
+    params: Vec<C::Var>,
+    body: StoreId,
+    opaque: bool,
+}
+
+/// Types and the results of checks on them, shared between threads. Types are built with the
+/// constructors, which reject the identities of other stores, and named types are defined with
+/// `define` and `define_opaque`.
+pub struct SharedStore<C>
+where
+    C: StoreConfig,
+{
+    hasher: RandomState,
+    store: u32,
+    next: AtomicU32,
+    /// The definitions of the named types. Checks hold the read lock, so that their results are
+    /// cached for the definitions they were made with.
+    defs: RwLock<BTreeMap<C::TyName, StoreDef<C>>>,
+    /// The identities of the nodes, sharded by the hash of the node.
+    ids: Shards<Arc<Node<C>>, StoreId>,
+    /// The nodes, sharded by identity.
+    nodes: Shards<StoreId, Arc<Node<C>>>,
+    /// The results of the subtyping checks done so far, sharded by the first type.
+    subtypes: Shards<(StoreId, StoreId), bool>,
+}
+impl<C> Default for SharedStore<C>
+where
+    C: StoreConfig,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<C> SharedStore<C>
+where
+    C: StoreConfig,
+{
+    /// An empty store, whose identities are distinct from those of every other store.
+    pub fn new() -> Self {
+        Self {
+            hasher: RandomState::new(),
+            store: next(&STORES),
+            next: AtomicU32::new(0),
+            defs: Default::default(),
+            ids: Default::default(),
+            nodes: Default::default(),
+            subtypes: Default::default(),
+        }
+    }
+
+    /// The type of all values.
+    pub fn top(&self) -> StoreId {
+        self.intern(Node::Top)
+    }
+
+    /// The empty type.
+    pub fn bot(&self) -> StoreId {
+        self.intern(Node::Bot)
+    }
+
+    /// The unknown type `?` of gradual typing.
+    pub fn dynamic(&self) -> StoreId {
+        self.intern(Node::Dynamic)
+    }
+
+    pub fn var(&self, var: C::Var) -> StoreId {
+        self.intern(Node::Var(var))
+    }
+
+    pub fn basic(&self, basic: C::Basic) -> StoreId {
+        self.intern(Node::Basic(basic))
+    }
+
+    pub fn literal(&self, literal: C::Literal) -> StoreId {
+        self.intern(Node::Literal(literal))
+    }
+
+    /// The integers from `lo` to `hi` inclusive, empty if `lo > hi`.
+    pub fn interval(&self, lo: C::Int, hi: C::Int) -> StoreId {
+        if lo > hi {
+            return self.bot();
+        }
+        self.intern(Node::Ints(lo, hi))
+    }
+
+    /// The pairs of a value of `l` and a value of `r`. This and the other constructors taking
+    /// types fail if one is from another store.
+    pub fn product(&self, l: StoreId, r: StoreId) -> Result<StoreId, StoreError> {
+        Ok(self.intern(Node::Product(self.own(l)?, self.own(r)?)))
+    }
+
+    /// The functions from `l` to `r`.
+    pub fn arrow(&self, l: StoreId, r: StoreId) -> Result<StoreId, StoreError> {
+        Ok(self.intern(Node::Arrow(self.own(l)?, self.own(r)?)))
+    }
+
+    pub fn record<I>(&self, open: Openness, props: I) -> Result<StoreId, StoreError>
+    where
+        I: IntoIterator<Item = (C::Prop, StoreId)>,
+    {
+        let props = props
+            .into_iter()
+            .map(|(prop, ty)| Ok((prop, self.own(ty)?)));
+        Ok(self.intern(Node::Record(open, props.collect::<Result<_, _>>()?)))
+    }
+
+    /// A reference to the named type `id` applied to `args`, defined with `define`.
+    pub fn refr<I>(&self, id: C::TyName, args: I) -> Result<StoreId, StoreError>
+    where
+        I: IntoIterator<Item = StoreId>,
+    {
+        let args = args.into_iter().map(|arg| self.own(arg));
+        Ok(self.intern(Node::Refr(id, args.collect::<Result<_, _>>()?)))
+    }
+
+    pub fn union<I>(&self, members: I) -> Result<StoreId, StoreError>
+    where
+        I: IntoIterator<Item = StoreId>,
+    {
+        let (top, bot) = (self.top(), self.bot());
+        self.combine(members, bot, top, Node::Union)
+    }
+
+    pub fn inter<I>(&self, members: I) -> Result<StoreId, StoreError>
+    where
+        I: IntoIterator<Item = StoreId>,
+    {
+        let (top, bot) = (self.top(), self.bot());
+        self.combine(members, top, bot, Node::Inter)
+    }
+
+    pub fn not(&self, ty: StoreId) -> Result<StoreId, StoreError> {
+        Ok(match &*self.node(ty)? {
+            Node::Top => self.bot(),
+            Node::Bot => self.top(),
+            Node::Not(ty) => *ty,
+            _ => self.intern(Node::Not(ty)),
+        })
+    }
+
+    pub fn diff(&self, t1: StoreId, t2: StoreId) -> Result<StoreId, StoreError> {
+        self.inter([t1, self.not(t2)?])
+    }
+
+    /// Defines (or redefines) a named type, as `Defs::define` does. Since this can change the
+    /// results of checks, those cached so far are dropped.
+    pub fn define(
+        &self,
+        name: C::TyName,
+        params: Vec<C::Var>,
+        body: StoreId,
+    ) -> Result<(), StoreError> {
+        self.insert(name, params, body, false)
+    }
+
+    /// Defines (or redefines) an opaque named type, as `Defs::define_opaque` does.
+    pub fn define_opaque(
+        &self,
+        name: C::TyName,
+        params: Vec<C::Var>,
+        body: StoreId,
+    ) -> Result<(), StoreError> {
+        self.insert(name, params, body, true)
+    }
+
+    pub fn is_empty(&self, ty: StoreId) -> Result<bool, StoreError> {
+        self.is_subtype(ty, self.bot())
+    }
+
+    /// Whether `t1` is a subtype of `t2`, given the definitions of the named types. The result
+    /// is shared with the other threads.
+    pub fn is_subtype(&self, t1: StoreId, t2: StoreId) -> Result<bool, StoreError> {
+        let key = (t1, t2);
+        let defs = self.defs.read().unwrap();
+        let shard = &self.subtypes[self.shard(&t1)];
+        if let Some(result) = shard.read().unwrap().get(&key) {
+            return Ok(*result);
+        }
+        let ctx = Context::new();
+        let mut memo = HashMap::new();
+        let (b1, b2) = (
+            self.materialize_in(&ctx, t1, &mut memo)?,
+            self.materialize_in(&ctx, t2, &mut memo)?,
+        );
+        let defs = self.defs_in(&ctx, &defs, &mut memo);
+        let result = ctx.solver(&defs).is_subtype(b1, b2);
+        shard.write().unwrap().insert(key, result);
+        Ok(result)
+    }
+
+    /// The definitions of the named types in a context, to check types materialized there with
+    /// a `Solver`.
+    pub fn defs<'a>(&self, ctx: &'a Context<C>) -> Defs<'a, C> {
+        self.defs_in(ctx, &self.defs.read().unwrap(), &mut HashMap::new())
+    }
+
+    /// The type in a context, e.g. to check it against named types with a `Solver`.
+    pub fn materialize<'a>(
+        &self,
+        ctx: &'a Context<C>,
+        ty: StoreId,
+    ) -> Result<&'a Bdd<'a, C, Type<'a, C>>, StoreError> {
+        self.materialize_in(ctx, ty, &mut HashMap::new())
+    }
+
+    fn materialize_in<'a>(
+        &self,
+        ctx: &'a Context<C>,
+        ty: StoreId,
+        memo: &mut HashMap<StoreId, &'a Bdd<'a, C, Type<'a, C>>>,
+    ) -> Result<&'a Bdd<'a, C, Type<'a, C>>, StoreError> {
+        if let Some(bdd) = memo.get(&ty) {
+            return Ok(bdd);
+        }
+        let arena = &ctx.arena;
+        let node = self.node(ty)?;
+        let mut go = |ty| self.materialize_in(ctx, ty, memo);
+        let bdd = match &*node {
+            Node::Top => ctx.top(),
+            Node::Bot => ctx.bot(),
+            Node::Dynamic => ctx.dynamic(),
+            Node::Var(var) => ctx.ty(Type::from_vars(arena, ctx.var(var.clone()))),
+            Node::Basic(basic) => ctx.ty(Type::from_basics(arena, ctx.basic(basic.clone()))),
+            Node::Literal(literal) => {
+                ctx.ty(Type::from_basics(arena, ctx.literal(literal.clone())))
+            }
+            Node::Ints(lo, hi) => ctx.ty(Type::from_ints(arena, ctx.interval(*lo, *hi))),
+            Node::Product(l, r) => {
+                let (l, r) = (go(*l)?, go(*r)?);
+                ctx.ty(Type::from_products(arena, ctx.product(l, r)))
+            }
+            Node::Arrow(l, r) => {
+                let (l, r) = (go(*l)?, go(*r)?);
+                ctx.ty(Type::from_arrows(arena, ctx.arrow(l, r)))
+            }
+            Node::Record(open, props) => {
+                let props = props.iter().map(|(prop, ty)| Ok((prop.clone(), go(*ty)?)));
+                let props = props.collect::<Result<Vec<_>, _>>()?;
+                ctx.ty(Type::from_records(arena, ctx.record(*open, props)))
+            }
+            Node::Refr(id, args) => {
+                let args = args.iter().map(|arg| go(*arg));
+                let args = args.collect::<Result<Vec<_>, _>>()?;
+                ctx.ty(Type::from_refrs(arena, ctx.refr(id.clone(), args)))
+            }
+            Node::Union(t1, t2) => {
+                let (t1, t2) = (go(*t1)?, go(*t2)?);
+                ctx.union([t1, t2])
+            }
+            Node::Inter(t1, t2) => {
+                let (t1, t2) = (go(*t1)?, go(*t2)?);
+                ctx.inter([t1, t2])
+            }
+            Node::Not(ty) => ctx.not(go(*ty)?),
+        };
+        memo.insert(ty, bdd);
+        Ok(bdd)
+    }
+
+    fn defs_in<'a>(
+        &self,
+        ctx: &'a Context<C>,
+        defs: &BTreeMap<C::TyName, StoreDef<C>>,
+        memo: &mut HashMap<StoreId, &'a Bdd<'a, C, Type<'a, C>>>,
+    ) -> Defs<'a, C> {
+        let definitions = defs.iter().map(|(name, def)| {
+            let body = self
+                .materialize_in(ctx, def.body, memo)
+                .expect("bodies are checked when defined");
+            (name.clone(), def.params.clone(), body, def.opaque)
+        });
+        let mut materialized = Defs::new();
+        materialized.define_all(definitions.collect::<Vec<_>>());
+        materialized
+    }
+
+    fn insert(
+        &self,
+        name: C::TyName,
+        params: Vec<C::Var>,
+        body: StoreId,
+        opaque: bool,
+    ) -> Result<(), StoreError> {
+        let body = self.own(body)?;
+        let mut defs = self.defs.write().unwrap();
+        defs.insert(
+            name,
+            StoreDef {
+                params,
+                body,
+                opaque,
+            },
+        );
+        for shard in &self.subtypes {
+            shard.write().unwrap().clear();
+        }
+        Ok(())
+    }
+
+    /// The union or intersection of `members`, where `unit` is its neutral element and `zero`
+    /// its absorbing one. Members are sorted so that the order they're given in doesn't matter.
+    fn combine<I>(
+        &self,
+        members: I,
+        unit: StoreId,
+        zero: StoreId,
+        node: fn(StoreId, StoreId) -> Node<C>,
+    ) -> Result<StoreId, StoreError>
+    where
+        I: IntoIterator<Item = StoreId>,
+    {
+        let mut members = members
+            .into_iter()
+            .map(|ty| self.own(ty))
+            .filter(|ty| *ty != Ok(unit))
+            .collect::<Result<Vec<_>, _>>()?;
+        if members.contains(&zero) {
+            return Ok(zero);
+        }
+        members.sort();
+        members.dedup();
+        Ok(members
+            .into_iter()
+            .reduce(|acc, ty| self.intern(node(acc, ty)))
+            .unwrap_or(unit))
+    }
+
+    /// The type if it's from this store.
+    fn own(&self, ty: StoreId) -> Result<StoreId, StoreError> {
+        if ty.store == self.store {
+            Ok(ty)
+        } else {
+            Err(StoreError::Foreign(ty))
+        }
+    }
+
+    fn shard<T: Hash>(&self, value: &T) -> usize {
+        self.hasher.hash_one(value) as usize % SHARDS
+    }
+
+    fn node(&self, ty: StoreId) -> Result<Arc<Node<C>>, StoreError> {
+        self.own(ty)?;
+        let shard = self.nodes[ty.index as usize % SHARDS].read().unwrap();
+        shard.get(&ty).cloned().ok_or(StoreError::Foreign(ty))
+    }
+
+    fn intern(&self, node: Node<C>) -> StoreId {
+        let shard = &self.ids[self.shard(&node)];
+        if let Some(id) = shard.read().unwrap().get(&node) {
+            return *id;
+        }
+        let mut ids = shard.write().unwrap();
+        // Another thread may have interned the node since the read lock was released.
+        if let Some(id) = ids.get(&node) {
+            return *id;
+        }
+        let id = StoreId {
+            store: self.store,
+            index: next(&self.next),
+        };
+        let node = Arc::new(node);
+        self.nodes[id.index as usize % SHARDS]
+            .write()
+            .unwrap()
+            .insert(id, node.clone());
+        ids.insert(node, id);
+        id
+    }
+}
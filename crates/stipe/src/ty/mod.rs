@@ -8,13 +8,46 @@ pub use product::Product;
 pub use record::{Openness, Record};
 pub use refr::Ref;
 
-use crate::bdd::TyAtom;
+use crate::bdd::{Bound, TyAtom};
 
-pub trait TyConfig: PartialEq + Eq + PartialOrd + Ord + std::fmt::Debug {
-    type TyName: PartialEq + Eq + PartialOrd + Ord + std::fmt::Debug;
-    type Basic: TyAtom;
-    type Var: TyAtom;
-    type Prop: PartialEq + Eq + PartialOrd + Ord + std::fmt::Debug;
+pub trait TyConfig: PartialEq + Eq + PartialOrd + Ord + std::hash::Hash + std::fmt::Debug {
+    type TyName: Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash + std::fmt::Debug;
+    type Basic: TyAtom + Clone;
+    type Literal: TyAtom + Clone;
+    /// The integers of the interval component of types, if `INTERVALS` is set. Configurations
+    /// without intervals can pick any `Bound`, such as `u8`.
+    type Int: Bound;
+    type Var: TyAtom + Clone;
+    type Prop: Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash + std::fmt::Debug;
+
+    /// The basic type that a literal belongs to.
+    fn literal_basic(literal: &Self::Literal) -> Self::Basic;
+
+    /// The variable made for the `index`th call to `Context::fresh_var`, named after `hint`. It
+    /// must differ from the variables made for other indices and from those written by users.
+    /// Every fresh variable, including those of tallying and instantiation, is made here. There
+    /// is no default, since nothing else in a configuration makes a `Self::Var`.
+    fn fresh_var(index: u64, hint: &str) -> Self::Var;
+
+    /// Whether types have an interval component. Without it, every interval is empty.
+    const INTERVALS: bool = false;
+
+    /// Whether a basic type is that of all the integers. With intervals, its values are those of
+    /// the interval component, so that intervals are contained in it.
+    fn int_basic(_basic: &Self::Basic) -> bool {
+        false
+    }
+
+    /// The integer that a literal stands for, if any. With intervals, such a literal is the
+    /// interval of that integer, so that it overlaps the intervals containing it.
+    fn literal_int(_literal: &Self::Literal) -> Option<Self::Int> {
+        None
+    }
+
+    /// All the literals of a basic type, if it has finitely many of them (e.g. booleans).
+    fn basic_literals(_basic: &Self::Basic) -> Option<Vec<Self::Literal>> {
+        None
+    }
 }
 
 pub enum Ty<'a, C>
@@ -0,0 +1,366 @@
+//! Emission of types as TypeScript declarations. Products become tuples, records become object
+//! types (with an index signature when open), arrows become function types and references become
+//! generic type references. TypeScript has no negation, so negated atoms are dropped, which
+//! widens the emitted type, and its object types admit other properties, so closed records are
+//! widened too; each such approximation is reported as a `Warning`.
+
+use std::fmt::{Display, Write};
+
+use crate::{
+    Context,
+    bdd::{Basic, Bdd, Bound, Intervals, Openness, TyAtom, Type, Var},
+    defs::Defs,
+    ty::TyConfig,
+};
+
+/// A configuration whose types can be emitted as TypeScript, with the syntax of its basics.
+pub trait TsConfig: TyConfig<TyName: Display, Prop: Display, Var: Display, Int: Display> {
+    /// The TypeScript type of the values of a basic type.
+    fn basic_ts(basic: &Self::Basic) -> String;
+
+    /// The TypeScript literal type of a literal.
+    fn literal_ts(literal: &Self::Literal) -> String;
+
+    /// The TypeScript type of all the values of basic types.
+    fn basics_ts() -> String {
+        "null | boolean | number | string".to_string()
+    }
+}
+
+/// An approximation made when emitting a type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// The negation of this type was dropped.
+    Negation(String),
+    /// A bounded range of integers was widened to `number`.
+    Interval(String),
+    /// This closed record was emitted as an object type, which also admits other properties.
+    Closed(String),
+}
+
+/// The TypeScript code emitted for some types.
+#[derive(Debug)]
+pub struct Emitted {
+    /// The emitted type or declarations.
+    pub code: String,
+    /// The approximations made, in the order they were made.
+    pub warnings: Vec<Warning>,
+}
+
+/// The TypeScript type of `ty`.
+pub fn emit_type<'a, C>(ctx: &'a Context<C>, ty: &'a Bdd<'a, C, Type<'a, C>>) -> Emitted
+where
+    C: TsConfig,
+{
+    let mut emitter = Emitter {
+        arena: &ctx.arena,
+        warnings: Vec::new(),
+    };
+    let code = emitter.ty(ty).code;
+    Emitted {
+        code,
+        warnings: emitter.warnings,
+    }
+}
+
+/// A declaration file with an exported type alias for each of the types of `defs`. Opaque types
+/// are emitted as branded object types, hiding their representation.
+pub fn emit_declarations<'a, C>(ctx: &'a Context<C>, defs: &Defs<'a, C>) -> Emitted
+where
+    C: TsConfig,
+{
+    let mut emitter = Emitter {
+        arena: &ctx.arena,
+        warnings: Vec::new(),
+    };
+    let mut code = String::new();
+    for (name, def) in defs.iter() {
+        let params = if def.params.is_empty() {
+            String::new()
+        } else {
+            let params: Vec<_> = def.params.iter().map(|param| param.to_string()).collect();
+            format!("<{}>", params.join(", "))
+        };
+        let body = if def.opaque {
+            format!("{{ readonly __brand: \"{name}\" }}")
+        } else {
+            emitter.ty(def.body).code
+        };
+        writeln!(code, "export type {name}{params} = {body};").unwrap();
+    }
+    Emitted {
+        code,
+        warnings: emitter.warnings,
+    }
+}
+
+/// How tightly a TypeScript type binds, from function types to the types needing no
+/// parentheses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    Function,
+    Union,
+    Inter,
+    Primary,
+}
+
+struct Ts {
+    code: String,
+    prec: Prec,
+}
+impl Ts {
+    fn primary(code: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            prec: Prec::Primary,
+        }
+    }
+
+    fn never() -> Self {
+        Self::primary("never")
+    }
+
+    /// The code of the type, in parentheses if it binds less tightly than `prec`.
+    fn at(self, prec: Prec) -> String {
+        if self.prec < prec {
+            format!("({})", self.code)
+        } else {
+            self.code
+        }
+    }
+}
+
+struct Emitter<'a> {
+    arena: &'a bumpalo::Bump,
+    warnings: Vec<Warning>,
+}
+impl<'a> Emitter<'a> {
+    fn ty<C>(&mut self, bdd: &'a Bdd<'a, C, Type<'a, C>>) -> Ts
+    where
+        C: TsConfig,
+    {
+        // Without variables, negations can be pushed into each kind of values, where they often
+        // cancel out.
+        if bdd.top_vars().is_empty() {
+            let flat = Bdd::flatten(self.arena, bdd);
+            return self.type_atom(&flat);
+        }
+        self.bdd(bdd, || Ts::primary("unknown"), Self::type_atom)
+    }
+
+    /// The union of the clauses of a BDD, where `top` is the type of all the values of its
+    /// kind.
+    fn bdd<C, T>(
+        &mut self,
+        bdd: &'a Bdd<'a, C, T>,
+        top: impl Fn() -> Ts,
+        atom: impl Fn(&mut Self, &'a T) -> Ts,
+    ) -> Ts
+    where
+        C: TyConfig,
+        T: TyAtom,
+    {
+        let mut clauses = Vec::new();
+        for (pos, neg) in bdd.dnf() {
+            for a in neg {
+                let negated = atom(self, a).code;
+                self.warnings.push(Warning::Negation(negated));
+            }
+            clauses.push(match pos.as_slice() {
+                [] => top(),
+                pos => inter(pos.iter().map(|a| atom(self, a))),
+            });
+        }
+        union(clauses)
+    }
+
+    fn type_atom<C>(&mut self, ty: &Type<'a, C>) -> Ts
+    where
+        C: TsConfig,
+    {
+        let parts = [
+            self.bdd(
+                ty.vars,
+                || Ts::primary("unknown"),
+                |_, var| match var {
+                    Var::Dyn => Ts::primary("any"),
+                    Var::Named(var) => Ts::primary(var.to_string()),
+                },
+            ),
+            self.bdd(
+                ty.basics,
+                || Ts {
+                    code: C::basics_ts(),
+                    prec: Prec::Union,
+                },
+                |_, basic| match basic {
+                    Basic::All(basic) => Ts::primary(C::basic_ts(basic)),
+                    Basic::Literal(literal) => Ts::primary(C::literal_ts(literal)),
+                },
+            ),
+            self.ints(ty.ints),
+            self.bdd(
+                ty.products,
+                || Ts::primary("[unknown, unknown]"),
+                |emitter, product| {
+                    let (l, r) = (emitter.ty(product.0), emitter.ty(product.1));
+                    Ts::primary(format!("[{}, {}]", l.code, r.code))
+                },
+            ),
+            self.bdd(
+                ty.arrows,
+                || Ts {
+                    code: "(arg: never) => unknown".to_string(),
+                    prec: Prec::Function,
+                },
+                |emitter, arrow| {
+                    let (l, r) = (emitter.ty(arrow.0), emitter.ty(arrow.1));
+                    Ts {
+                        code: format!("(arg: {}) => {}", l.code, r.code),
+                        prec: Prec::Function,
+                    }
+                },
+            ),
+            self.bdd(
+                ty.records,
+                || Ts::primary("{ [key: string]: unknown }"),
+                |emitter, record| {
+                    let mut members: Vec<_> = record
+                        .map
+                        .iter()
+                        .map(|(prop, ty)| format!("{}: {}", prop_name(prop), emitter.ty(ty).code))
+                        .collect();
+                    if record.open == Openness::Open {
+                        members.push("[key: string]: unknown".to_string());
+                    }
+                    let code = if members.is_empty() {
+                        "{}".to_string()
+                    } else {
+                        format!("{{ {} }}", members.join("; "))
+                    };
+                    if record.open == Openness::Closed {
+                        emitter.warnings.push(Warning::Closed(code.clone()));
+                    }
+                    Ts::primary(code)
+                },
+            ),
+            self.bdd(
+                ty.refrs,
+                || Ts::primary("unknown"),
+                |emitter, refr| {
+                    if refr.args.is_empty() {
+                        return Ts::primary(refr.id.to_string());
+                    }
+                    let args: Vec<_> = refr.args.iter().map(|arg| emitter.ty(arg).code).collect();
+                    Ts::primary(format!("{}<{}>", refr.id, args.join(", ")))
+                },
+            ),
+        ];
+        union(parts)
+    }
+
+    fn ints<C>(&mut self, ints: &Intervals<'_, C>) -> Ts
+    where
+        C: TsConfig,
+    {
+        let mut members = Vec::new();
+        let mut number = false;
+        for (lo, hi) in ints.ranges.iter() {
+            if lo == hi {
+                members.push(Ts::primary(lo.to_string()));
+            } else {
+                if (*lo, *hi) != (C::Int::MIN, C::Int::MAX) {
+                    self.warnings
+                        .push(Warning::Interval(format!("{lo}..={hi}")));
+                }
+                number = true;
+            }
+        }
+        if number {
+            // `number` covers the literals.
+            return Ts::primary("number");
+        }
+        union(members)
+    }
+}
+
+fn union(members: impl IntoIterator<Item = Ts>) -> Ts {
+    let mut distinct: Vec<Ts> = Vec::new();
+    for member in members {
+        match member.code.as_str() {
+            "never" => {}
+            "unknown" => return Ts::primary("unknown"),
+            code => {
+                if distinct.iter().all(|other| other.code != code) {
+                    distinct.push(member);
+                }
+            }
+        }
+    }
+    match distinct.len() {
+        0 => Ts::never(),
+        1 => distinct.pop().unwrap(),
+        _ => Ts {
+            code: distinct
+                .into_iter()
+                .map(|member| member.at(Prec::Union))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            prec: Prec::Union,
+        },
+    }
+}
+
+fn inter(members: impl IntoIterator<Item = Ts>) -> Ts {
+    let mut members: Vec<Ts> = members
+        .into_iter()
+        .filter(|member| member.code != "unknown")
+        .collect();
+    if members.iter().any(|member| member.code == "never") {
+        return Ts::never();
+    }
+    match members.len() {
+        0 => Ts::primary("unknown"),
+        1 => members.pop().unwrap(),
+        _ => Ts {
+            code: members
+                .into_iter()
+                .map(|member| member.at(Prec::Inter))
+                .collect::<Vec<_>>()
+                .join(" & "),
+            prec: Prec::Inter,
+        },
+    }
+}
+
+/// A property name, quoted unless it's an identifier.
+fn prop_name(prop: &impl Display) -> String {
+    let name = prop.to_string();
+    let mut chars = name.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if identifier { name } else { string_ts(&name) }
+}
+
+/// A TypeScript string literal, escaped as in JSON.
+pub fn string_ts(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            '\u{8}' => literal.push_str("\\b"),
+            '\u{c}' => literal.push_str("\\f"),
+            c if c < ' ' => write!(literal, "\\u{:04x}", c as u32).unwrap(),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
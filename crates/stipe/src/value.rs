@@ -0,0 +1,247 @@
+//! Membership of runtime values in types, to validate dynamic data (e.g. decoded JSON) at the
+//! boundaries of typed code.
+
+use crate::{
+    bdd::{Basic, Bdd, Openness, Product, Record, Refr, Solver, TyAtom, Type, Var},
+    narrow::Step,
+    ty::TyConfig,
+};
+
+/// A runtime value. Basic values are literals, which belong to the basic type given by
+/// `TyConfig::literal_basic`, or to the intervals containing them for integer literals. Integers
+/// belong to the intervals containing them, or without `TyConfig::INTERVALS` to the integer
+/// basic and to their literal. Functions are opaque: they're assumed to belong to every arrow
+/// type.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Value<C>
+where
+    C: TyConfig,
+{
+    Basic(C::Literal),
+    Int(C::Int),
+    Pair(Box<Value<C>>, Box<Value<C>>),
+    Record(Vec<(C::Prop, Value<C>)>),
+    Function,
+}
+
+/// Whether `value` belongs to `ty`. References are unfolded with the definitions of the solver,
+/// including those of opaque types, and variables other than `Dyn` contain no values.
+pub fn check_value<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    value: &Value<C>,
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+) -> bool
+where
+    C: TyConfig,
+{
+    Checker::new(solver).contains(value, ty)
+}
+
+/// The path to the part of `value` that makes it not belong to `ty`, or `None` if it belongs to
+/// it. The path stops at the innermost part whose expected type is unambiguous: for a value
+/// matching none of the cases of a union, it's the path to the whole value.
+pub fn value_mismatch<'a, C>(
+    solver: &Solver<'_, 'a, C>,
+    value: &Value<C>,
+    ty: &'a Bdd<'a, C, Type<'a, C>>,
+) -> Option<Vec<Step<C>>>
+where
+    C: TyConfig,
+{
+    let mut checker = Checker::new(solver);
+    if checker.contains(value, ty) {
+        return None;
+    }
+    let mut path = Vec::new();
+    checker.mismatch(value, ty, &mut path);
+    Some(path)
+}
+
+struct Checker<'s, 'd, 'a, C>
+where
+    C: TyConfig,
+{
+    solver: &'s Solver<'d, 'a, C>,
+    /// The references unfolded since the last step into a part of the value, which would unfold
+    /// forever if met again.
+    pending: Vec<&'a Refr<'a, C, Type<'a, C>>>,
+}
+impl<'s, 'd, 'a, C> Checker<'s, 'd, 'a, C>
+where
+    C: TyConfig,
+{
+    fn new(solver: &'s Solver<'d, 'a, C>) -> Self {
+        Self {
+            solver,
+            pending: Vec::new(),
+        }
+    }
+
+    fn contains(&mut self, value: &Value<C>, ty: &'a Bdd<'a, C, Type<'a, C>>) -> bool {
+        eval(ty, &mut |ty| self.in_type(value, ty))
+    }
+
+    fn in_type(&mut self, value: &Value<C>, ty: &'a Type<'a, C>) -> bool {
+        if eval(ty.vars, &mut |var| matches!(var, Var::Dyn)) {
+            return true;
+        }
+        let in_kind = match value {
+            Value::Basic(literal) => match C::literal_int(literal).filter(|_| C::INTERVALS) {
+                Some(n) => ty.ints.contains(n),
+                None => eval(ty.basics, &mut |basic| match basic {
+                    Basic::All(basic) => C::literal_basic(literal) == *basic,
+                    Basic::Literal(other) => literal == other,
+                }),
+            },
+            Value::Int(n) if C::INTERVALS => ty.ints.contains(*n),
+            Value::Int(n) => eval(ty.basics, &mut |basic| match basic {
+                Basic::All(basic) => C::int_basic(basic),
+                Basic::Literal(literal) => C::literal_int(literal) == Some(*n),
+            }),
+            Value::Pair(l, r) => eval(ty.products, &mut |Product(tl, tr)| {
+                self.part(l, tl) && self.part(r, tr)
+            }),
+            Value::Record(fields) => eval(ty.records, &mut |record| self.in_record(fields, record)),
+            Value::Function => eval(ty.arrows, &mut |_| true),
+        };
+        in_kind
+            || eval(ty.refrs, &mut |refr| match self.unfold(refr) {
+                Some(body) => {
+                    let contains = self.contains(value, body);
+                    self.pending.pop();
+                    contains
+                }
+                None => false,
+            })
+    }
+
+    fn in_record(
+        &mut self,
+        fields: &[(C::Prop, Value<C>)],
+        record: &'a Record<'a, C, Type<'a, C>>,
+    ) -> bool {
+        shape_fits(fields, record)
+            && record.map.iter().all(|(prop, ty)| {
+                let (_, value) = fields.iter().find(|(p, _)| p == prop).unwrap();
+                self.part(value, ty)
+            })
+    }
+
+    /// Whether a part of the value belongs to a type, where references may be unfolded again.
+    fn part(&mut self, value: &Value<C>, ty: &'a Bdd<'a, C, Type<'a, C>>) -> bool {
+        let pending = std::mem::take(&mut self.pending);
+        let contains = self.contains(value, ty);
+        self.pending = pending;
+        contains
+    }
+
+    /// The body of a reference, unless it's undefined or already being unfolded. On success,
+    /// the reference is pending until the caller pops it.
+    fn unfold(
+        &mut self,
+        refr: &'a Refr<'a, C, Type<'a, C>>,
+    ) -> Option<&'a Bdd<'a, C, Type<'a, C>>> {
+        if self.pending.contains(&refr) {
+            return None;
+        }
+        let body = self.solver.defs.unfold(self.solver.arena, refr)?;
+        self.pending.push(refr);
+        Some(body)
+    }
+
+    /// Extends `path` towards the part of `value`, which doesn't belong to `ty`, that causes the
+    /// mismatch.
+    fn mismatch(
+        &mut self,
+        value: &Value<C>,
+        ty: &'a Bdd<'a, C, Type<'a, C>>,
+        path: &mut Vec<Step<C>>,
+    ) {
+        let flat = Bdd::flatten(self.solver.arena, ty);
+        match value {
+            Value::Pair(l, r) => {
+                if let [Product(tl, tr)] = candidates(flat.products, |_| true).as_slice() {
+                    for (step, value, ty) in [(Step::Left, l, tl), (Step::Right, r, tr)] {
+                        if !self.part(value, ty) {
+                            path.push(step);
+                            self.pending.clear();
+                            return self.mismatch(value, ty, path);
+                        }
+                    }
+                }
+            }
+            Value::Record(fields) => {
+                let records = candidates(flat.records, |record| shape_fits(fields, record));
+                if let [record] = records.as_slice() {
+                    for (prop, ty) in record.map.iter() {
+                        let (_, value) = fields.iter().find(|(p, _)| p == prop).unwrap();
+                        if !self.part(value, ty) {
+                            path.push(Step::Field(prop.clone()));
+                            self.pending.clear();
+                            return self.mismatch(value, ty, path);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        // The value may be expected to belong to a single referenced type.
+        if let [refr] = candidates(flat.refrs, |_| true).as_slice()
+            && let Some(body) = self.unfold(refr)
+        {
+            self.mismatch(value, body, path);
+        }
+    }
+}
+
+/// Whether a BDD contains a value, given whether each of its atoms does.
+fn eval<'a, C, T>(bdd: &'a Bdd<'a, C, T>, f: &mut impl FnMut(&'a T) -> bool) -> bool
+where
+    C: TyConfig,
+    T: TyAtom,
+{
+    match bdd {
+        Bdd::Top => true,
+        Bdd::Bot => false,
+        Bdd::Atom {
+            atom, pos, lu, neg, ..
+        } => {
+            if f(atom) {
+                eval(pos, f) || eval(lu, f)
+            } else {
+                eval(lu, f) || eval(neg, f)
+            }
+        }
+    }
+}
+
+/// The distinct atoms occurring positively in the clauses of a BDD that satisfy `keep`.
+fn candidates<'a, C, T>(bdd: &'a Bdd<'a, C, T>, keep: impl Fn(&T) -> bool) -> Vec<&'a T>
+where
+    C: TyConfig,
+    T: TyAtom,
+{
+    let mut atoms: Vec<&T> = Vec::new();
+    for (pos, _) in bdd.dnf() {
+        for atom in pos {
+            if keep(atom) && !atoms.contains(&atom) {
+                atoms.push(atom);
+            }
+        }
+    }
+    atoms
+}
+
+/// Whether the fields of a record value are those of a record type: all its fields are present,
+/// and there are no others if it's closed.
+fn shape_fits<C>(fields: &[(C::Prop, Value<C>)], record: &Record<'_, C, Type<'_, C>>) -> bool
+where
+    C: TyConfig,
+{
+    record
+        .map
+        .iter()
+        .all(|(prop, _)| fields.iter().any(|(p, _)| p == prop))
+        && (record.open == Openness::Open
+            || fields.iter().all(|(prop, _)| record.get(prop).is_some()))
+}